@@ -0,0 +1,121 @@
+//! Bundler benchmark over a synthetic library.
+//!
+//! Generates a contest project, depending on a library with many modules (all
+//! of them used by the solution), and times `bundle` runs of the built binary.
+//!
+//! Run with `cargo bench`, the library size and the number of runs can be
+//! changed with `ALGORIST_BENCH_MODULES` (default: 1000) and
+//! `ALGORIST_BENCH_RUNS` (default: 5) environment variables.
+
+use std::{
+    env,
+    fmt::Write,
+    fs,
+    path::Path,
+    process::{self, Command, Stdio},
+    time::{Duration, Instant},
+};
+
+/// Functions per generated module.
+const FUNCTIONS: usize = 10;
+
+fn main() {
+    let modules = env_usize("ALGORIST_BENCH_MODULES", 1000);
+    let runs = env_usize("ALGORIST_BENCH_RUNS", 5);
+
+    let dir = env::temp_dir().join(format!("algorist-bench-{}", process::id()));
+    generate_project(&dir, modules);
+
+    // The first run warms up file system caches, and is not measured.
+    bundle(&dir);
+    let mut timings = (0..runs).map(|_| bundle(&dir)).collect::<Vec<_>>();
+    timings.sort();
+
+    let size = fs::metadata(dir.join("bundled/src/bin/a.rs"))
+        .map(|metadata| metadata.len())
+        .unwrap_or_default();
+    println!(
+        "bundle: {modules} modules, {} KiB output, {runs} runs: min {:?}, median {:?}, max {:?}",
+        size / 1024,
+        timings[0],
+        timings[timings.len() / 2],
+        timings[timings.len() - 1],
+    );
+
+    fs::remove_dir_all(&dir).expect("failed to remove benchmark project");
+}
+
+fn env_usize(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&value| value > 0)
+        .unwrap_or(default)
+}
+
+/// Run `bundle` in the project, returning how long it took.
+fn bundle(dir: &Path) -> Duration {
+    let started = Instant::now();
+    let status = Command::new(env!("CARGO_BIN_EXE_algorist"))
+        .args(["bundle", "a"])
+        .current_dir(dir)
+        .stdout(Stdio::null())
+        .status()
+        .expect("failed to run bundle");
+    assert!(status.success(), "bundle failed with status: {status}");
+    started.elapsed()
+}
+
+/// Generate a contest project, with a library of `modules` modules, chained
+/// with `crate::` paths, and a solution using all of them.
+fn generate_project(dir: &Path, modules: usize) {
+    let lib_src = dir.join("crates/biglib/src");
+    fs::create_dir_all(&lib_src).expect("failed to create library directory");
+    fs::create_dir_all(dir.join("src/bin")).expect("failed to create binary directory");
+
+    write(
+        &dir.join("Cargo.toml"),
+        "[package]\nname = \"bench\"\nversion = \"0.1.0\"\nedition = \
+         \"2021\"\n\n[dependencies]\nbiglib = { path = \"crates/biglib\" }\n",
+    );
+    write(
+        &dir.join("crates/biglib/Cargo.toml"),
+        "[package]\nname = \"biglib\"\nversion = \"0.1.0\"\nedition = \"2021\"\n",
+    );
+
+    let mut lib = String::new();
+    let (mut uses, mut main) = (String::new(), String::new());
+    for m in 0..modules {
+        writeln!(lib, "pub mod m{m};").unwrap();
+        writeln!(uses, "use biglib::m{m};").unwrap();
+        writeln!(main, "    sum += m{m}::f0(1);").unwrap();
+
+        let mut module = format!(
+            "/// Synthetic module {m}.\npub struct S{m} {{\n    pub value: u64,\n}}\n\nimpl S{m} \
+             {{\n    pub fn get(&self) -> u64 {{\n        self.value\n    }}\n}}\n"
+        );
+        for f in 0..FUNCTIONS {
+            let call = match (m, f) {
+                (0, _) => "x".to_string(),
+                _ if f + 1 < FUNCTIONS => format!("f{}(x)", f + 1),
+                _ => format!("crate::m{}::f0(x)", m - 1),
+            };
+            write!(
+                module,
+                "\n/// Function {f} of module {m}.\npub fn f{f}(x: u64) -> u64 {{\n    let s = \
+                 S{m} {{ value: x }};\n    s.get().wrapping_add({call})\n}}\n"
+            )
+            .unwrap();
+        }
+        write(&lib_src.join(format!("m{m}.rs")), &module);
+    }
+    let main = format!(
+        "{uses}\nfn main() {{\n    let mut sum = 0u64;\n{main}    println!(\"{{sum}}\");\n}}\n"
+    );
+    write(&lib_src.join("lib.rs"), &lib);
+    write(&dir.join("src/bin/a.rs"), &main);
+}
+
+fn write(path: &Path, content: &str) {
+    fs::write(path, content).unwrap_or_else(|e| panic!("failed to write {path:?}: {e}"));
+}
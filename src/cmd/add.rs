@@ -1,8 +1,25 @@
 use {
-    crate::cmd::{SubCmd, TPL_DIR, copy_to},
+    crate::cmd::{
+        SubCmd,
+        config::setting,
+        meta::{Event, MetaStore, record_events},
+        problem::{
+            normalize_id,
+            resolve_id,
+            solution_path,
+            solve_module_path,
+            statement_path,
+            write_statement_stub,
+            write_template,
+        },
+    },
     anyhow::{Context, Result, anyhow},
     argh::FromArgs,
-    std::{fs, path::PathBuf},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
 };
 
 /// Add a problem template to `src/bin/`.
@@ -12,15 +29,33 @@ pub struct AddProblemSubCmd {
     #[argh(positional)]
     /// problem ID
     id: String,
+
+    #[argh(option)]
+    /// problem URL, recorded in the statement notes stub
+    url: Option<String>,
+
+    #[argh(option)]
+    /// tag of the problem, e.g. `dp`, for filtering (can be repeated)
+    tag: Vec<String>,
+
+    #[argh(option)]
+    /// difficulty of the problem, e.g. its Codeforces rating, for filtering
+    difficulty: Option<u64>,
+
+    #[argh(option)]
+    /// start from another problem: the code within its marked regions (e.g.
+    /// the solution), along with its imports and helpers, is put into the
+    /// template
+    from: Option<String>,
 }
 
 impl SubCmd for AddProblemSubCmd {
     fn run(&self) -> Result<()> {
         // The `./src` directory must be present.
-        let src_dir = PathBuf::from("./")
+        let root_dir = PathBuf::from("./")
             .canonicalize()
-            .context("failed to canonicalize root directory path")?
-            .join("src");
+            .context("failed to canonicalize root directory path")?;
+        let src_dir = root_dir.join("src");
 
         if !src_dir.exists() {
             return Err(anyhow!("Source directory does not exist: {:?}", src_dir));
@@ -32,7 +67,8 @@ impl SubCmd for AddProblemSubCmd {
             fs::create_dir(&bin_dir).context("failed to create src/bin directory")?;
         }
 
-        let id = self.id.trim_end_matches(".rs");
+        let id = normalize_id(&self.id);
+        let id = id.as_str();
 
         // Copy template file to the `src/bin` directory.
         // If the file already exists, emit an error.
@@ -40,8 +76,44 @@ impl SubCmd for AddProblemSubCmd {
         if target_file.exists() {
             return Err(anyhow!("Problem file already exists: {:?}", target_file));
         }
-        copy_to(&TPL_DIR, "problem.rs", &target_file)?;
-        println!("Problem template added at {target_file:?}");
+        let module_file = root_dir.join(solve_module_path(id));
+        if module_file.exists() {
+            return Err(anyhow!("Solve module already exists: {:?}", module_file));
+        }
+        let from = match &self.from {
+            Some(from) => {
+                let from = root_dir.join(solution_path(&root_dir, &resolve_id(&root_dir, from)?));
+                let content = fs::read_to_string(&from)
+                    .context(format!("failed to read {}", from.display()))?;
+                Some((from, content))
+            }
+            None => None,
+        };
+        let solution_file = write_template(
+            &root_dir,
+            id,
+            from.as_ref().map(|(_, content)| content.as_str()),
+        )
+        .context(match &from {
+            Some((from, _)) => format!("failed to add problem from {}", from.display()),
+            None => "failed to add problem".to_string(),
+        })?;
+        let solution_file = root_dir.join(solution_file);
+        println!("Problem template added at {solution_file:?}");
+        if solution_file != target_file {
+            println!("Problem file, calling its `solve()`, added at {target_file:?}");
+        }
+        record_events(&root_dir, &[id], Event::Created);
+        if !self.tag.is_empty() || self.difficulty.is_some() {
+            let tags = self
+                .tag
+                .iter()
+                .map(|tag| tag.trim().to_lowercase())
+                .collect::<Vec<_>>();
+            let mut store = MetaStore::open(&root_dir)?;
+            store.set_labels(id, &tags, self.difficulty);
+            store.save().context("failed to record problem tags")?;
+        }
 
         // Create empty `inputs/{id}.txt` file.
         let inputs_dir = PathBuf::from("./inputs")
@@ -51,6 +123,36 @@ impl SubCmd for AddProblemSubCmd {
         fs::write(&target_file, "")?;
         println!("Input file created at {target_file:?}");
 
+        // Statement notes are kept next to the solution, e.g. constraints to keep in
+        // mind.
+        if write_statement_stub(&root_dir, id, self.url.as_deref())? {
+            println!("Statement notes stub created at {:?}", statement_path(id));
+        }
+
+        // Open the problem file, if an editor is configured.
+        if let Some(editor) = setting("editor")? {
+            open_in_editor(&editor, &solution_file);
+        }
+
         Ok(())
     }
 }
+
+/// Open the file with the editor command (which may include arguments, e.g.
+/// `code -r`).
+///
+/// Failures are reported, but never fail the command: the problem is added
+/// already.
+pub fn open_in_editor(editor: &str, file: &Path) {
+    let mut parts = editor.split_whitespace();
+    let Some(program) = parts.next() else {
+        return;
+    };
+    println!("Opening {file:?} with `{editor}`");
+    let status = Command::new(program).args(parts).arg(file).status();
+    match status {
+        Ok(status) if status.success() => {}
+        Ok(status) => println!("Warning: editor exited with status: {status}"),
+        Err(e) => println!("Warning: failed to run editor `{editor}`: {e}"),
+    }
+}
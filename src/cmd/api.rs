@@ -0,0 +1,442 @@
+use {
+    crate::cmd::{bundle::mod_path_attr, cfg::is_test_only},
+    anyhow::{Context, Result},
+    prettyplease::unparse,
+    std::{
+        collections::BTreeMap,
+        fmt,
+        fs,
+        path::{Path, PathBuf},
+    },
+    syn::parse_quote,
+};
+
+/// Public API of a library crate: paths of public items (e.g. `math::gcd`),
+/// mapped to their signatures.
+///
+/// Signatures are skeletons of the items: bodies, private fields and
+/// attributes (other than `derive`) are removed, so that only the changes
+/// affecting users of the crate are detected.
+#[derive(Debug, Default)]
+pub struct PublicApi {
+    signatures: BTreeMap<String, String>,
+
+    /// Doc comments of the items, which have them.
+    docs: BTreeMap<String, String>,
+}
+
+impl PublicApi {
+    /// Collect the public API of the crate, located in the given directory
+    /// (the one with `Cargo.toml`).
+    pub fn collect(crate_dir: &Path) -> Result<Self> {
+        let mut api = Self::default();
+        let src = crate_dir.join("src");
+        let file = parse(&src.join("lib.rs"))?;
+        api.collect_items(&file.items, &[], &src, Some(&src))?;
+        Ok(api)
+    }
+
+    /// Paths of the public items, e.g. `math::gcd`, `ds::Fenwick::new` or
+    /// `wln!` (for exported macros).
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.signatures.keys().map(|path| path.as_str())
+    }
+
+    /// Public items: paths, signatures and doc comments (empty, if missing).
+    pub fn items(&self) -> impl Iterator<Item = (&str, &str, &str)> {
+        self.signatures.iter().map(|(path, sig)| {
+            let doc = self.docs.get(path).map_or("", |doc| doc.as_str());
+            (path.as_str(), sig.as_str(), doc)
+        })
+    }
+
+    /// Compare with a newer version of the API.
+    pub fn diff(&self, new: &PublicApi) -> ApiDiff {
+        let mut diff = ApiDiff::default();
+        for (path, old_sig) in &self.signatures {
+            match new.signatures.get(path) {
+                None => diff.removed.push((path.clone(), old_sig.clone())),
+                Some(new_sig) if new_sig != old_sig => {
+                    diff.changed
+                        .push((path.clone(), old_sig.clone(), new_sig.clone()))
+                }
+                _ => {}
+            }
+        }
+        for (path, new_sig) in &new.signatures {
+            if !self.signatures.contains_key(path) {
+                diff.added.push((path.clone(), new_sig.clone()));
+            }
+        }
+        diff
+    }
+
+    /// Collect public items of a module, located in `dir` (where its
+    /// submodule files are).
+    /// Collect the items of a module, whose submodules are in `dir`.
+    ///
+    /// `file_dir` is the directory of the module's file, `#[path]` modules are
+    /// relative to it (within inline modules, it is `None`, and they are
+    /// relative to `dir`).
+    fn collect_items(
+        &mut self,
+        items: &[syn::Item],
+        module: &[String],
+        dir: &Path,
+        file_dir: Option<&Path>,
+    ) -> Result<()> {
+        for item in items {
+            match item {
+                // Exported macros are available at the crate root, regardless of the
+                // module they are defined in.
+                syn::Item::Macro(item)
+                    if item
+                        .attrs
+                        .iter()
+                        .any(|attr| attr.path().is_ident("macro_export")) =>
+                {
+                    if let Some(ident) = &item.ident {
+                        self.insert(
+                            format!("{ident}!"),
+                            format!("macro_rules! {ident}"),
+                            &item.attrs,
+                        );
+                    }
+                }
+                syn::Item::Mod(item) if is_public(&item.vis) && !is_test_only(&item.attrs) => {
+                    let name = item.ident.to_string();
+                    let path = [module, std::slice::from_ref(&name)].concat();
+                    match (&item.content, mod_path_attr(item)) {
+                        (Some((_, items)), _) => {
+                            self.collect_items(items, &path, &dir.join(&name), None)?
+                        }
+                        // Files of `#[path]` modules are treated as `mod.rs` ones: their
+                        // submodules are next to them.
+                        (None, Some(file)) => {
+                            let file = file_dir.unwrap_or(dir).join(file);
+                            let file_dir = file.parent().unwrap_or(dir);
+                            let items = parse(&file)?.items;
+                            self.collect_items(&items, &path, file_dir, Some(file_dir))?;
+                        }
+                        (None, None) => {
+                            let dir = dir.join(&name);
+                            let file = mod_file(&dir);
+                            let file_dir = file.parent().unwrap_or(&dir).to_path_buf();
+                            let items = parse(&file)?.items;
+                            self.collect_items(&items, &path, &dir, Some(&file_dir))?;
+                        }
+                    }
+                }
+                syn::Item::Impl(item) => self.collect_impl(item, module),
+                item => {
+                    if let Some((name, skeleton)) = skeleton(item) {
+                        self.insert(join(module, &name), signature(skeleton), item_attrs(item));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Collect public methods of inherent impls, and implemented traits.
+    fn collect_impl(&mut self, item: &syn::ItemImpl, module: &[String]) {
+        if item.trait_.is_some() {
+            let mut header = item.clone();
+            header.attrs.clear();
+            header.items.clear();
+            let name = signature(syn::Item::Impl(header));
+            self.signatures.insert(join(module, &name), name);
+            return;
+        }
+
+        let self_ty = &item.self_ty;
+        let type_name = match self_ty.as_ref() {
+            syn::Type::Path(ty) => ty
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.ident.to_string())
+                .unwrap_or_default(),
+            ty => signature(parse_quote!(type T = #ty;))
+                .trim_start_matches("type T = ")
+                .trim_end_matches(';')
+                .to_string(),
+        };
+        for impl_item in &item.items {
+            if let syn::ImplItem::Fn(method) = impl_item
+                && is_public(&method.vis)
+            {
+                let name = format!("{type_name}::{}", method.sig.ident);
+                let sig = &method.sig;
+                self.insert(
+                    join(module, &name),
+                    signature(parse_quote!(pub #sig {})),
+                    &method.attrs,
+                );
+            }
+        }
+    }
+
+    fn insert(&mut self, path: String, signature: String, attrs: &[syn::Attribute]) {
+        let doc = doc_text(attrs);
+        if !doc.is_empty() {
+            self.docs.insert(path.clone(), doc);
+        }
+        self.signatures.insert(path, signature);
+    }
+}
+
+/// Library crates in `crates`, the project depends on, by the names solutions
+/// refer to them.
+pub fn library_crates(root: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let package_name = |dir: &Path| {
+        fs::read_to_string(dir.join("Cargo.toml"))
+            .ok()?
+            .parse::<toml::Value>()
+            .ok()?
+            .get("package")?
+            .get("name")?
+            .as_str()
+            .map(|name| name.to_string())
+    };
+    let mut packages = Vec::new();
+    if let Ok(entries) = fs::read_dir(root.join("crates")) {
+        for entry in entries {
+            let dir = entry?.path();
+            if let Some(package) = package_name(&dir) {
+                packages.push((package, dir));
+            }
+        }
+    }
+
+    let manifest = fs::read_to_string(root.join("Cargo.toml"))
+        .context("failed to read Cargo.toml")?
+        .parse::<toml::Value>()
+        .context("failed to parse Cargo.toml")?;
+    let mut crates = Vec::new();
+    let deps = manifest
+        .get("dependencies")
+        .and_then(|deps| deps.as_table());
+    for (key, dep) in deps.into_iter().flatten() {
+        let package = dep
+            .get("package")
+            .and_then(|package| package.as_str())
+            .unwrap_or(key);
+        if let Some((_, dir)) = packages.iter().find(|(name, _)| name == package) {
+            crates.push((key.replace('-', "_"), dir.clone()));
+        }
+    }
+    crates.sort();
+    Ok(crates)
+}
+
+/// Difference between two versions of a public API.
+#[derive(Debug, Default)]
+pub struct ApiDiff {
+    pub added: Vec<(String, String)>,
+    pub removed: Vec<(String, String)>,
+    pub changed: Vec<(String, String, String)>,
+}
+
+impl ApiDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Whether users of the old API may break: items were removed or changed.
+    pub fn is_breaking(&self) -> bool {
+        !self.removed.is_empty() || !self.changed.is_empty()
+    }
+}
+
+impl fmt::Display for ApiDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (path, sig) in &self.removed {
+            writeln!(f, "- {path}: {sig}")?;
+        }
+        for (path, old, new) in &self.changed {
+            writeln!(f, "~ {path}: {old}")?;
+            writeln!(f, "  {:width$}  -> {new}", "", width = path.len())?;
+        }
+        for (path, sig) in &self.added {
+            writeln!(f, "+ {path}: {sig}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Name and skeleton of a public item, if it is one.
+fn skeleton(item: &syn::Item) -> Option<(String, syn::Item)> {
+    let mut item = item.clone();
+    let name = match &mut item {
+        syn::Item::Fn(item) if is_public(&item.vis) => {
+            item.attrs.clear();
+            item.block = parse_quote!({});
+            item.sig.ident.to_string()
+        }
+        syn::Item::Struct(item) if is_public(&item.vis) => {
+            retain_derives(&mut item.attrs);
+            let fields = std::mem::replace(&mut item.fields, syn::Fields::Unit);
+            item.fields = match fields {
+                syn::Fields::Named(mut fields) => {
+                    fields.named = fields
+                        .named
+                        .into_iter()
+                        .filter(|field| is_public(&field.vis))
+                        .map(without_attrs)
+                        .collect();
+                    syn::Fields::Named(fields)
+                }
+                syn::Fields::Unnamed(mut fields) => {
+                    fields.unnamed = fields.unnamed.into_iter().map(without_attrs).collect();
+                    syn::Fields::Unnamed(fields)
+                }
+                syn::Fields::Unit => syn::Fields::Unit,
+            };
+            item.ident.to_string()
+        }
+        syn::Item::Enum(item) if is_public(&item.vis) => {
+            retain_derives(&mut item.attrs);
+            for variant in &mut item.variants {
+                variant.attrs.clear();
+            }
+            item.ident.to_string()
+        }
+        syn::Item::Trait(item) if is_public(&item.vis) => {
+            item.attrs.clear();
+            for trait_item in &mut item.items {
+                if let syn::TraitItem::Fn(method) = trait_item {
+                    method.attrs.clear();
+                    method.default = None;
+                }
+            }
+            item.ident.to_string()
+        }
+        syn::Item::Const(item) if is_public(&item.vis) => {
+            item.attrs.clear();
+            *item.expr = parse_quote!(_);
+            item.ident.to_string()
+        }
+        syn::Item::Static(item) if is_public(&item.vis) => {
+            item.attrs.clear();
+            *item.expr = parse_quote!(_);
+            item.ident.to_string()
+        }
+        syn::Item::Type(item) if is_public(&item.vis) => {
+            item.attrs.clear();
+            item.ident.to_string()
+        }
+        syn::Item::Use(item) if is_public(&item.vis) => {
+            item.attrs.clear();
+            let mut tree = &item.tree;
+            while let syn::UseTree::Path(path) = tree {
+                tree = &path.tree;
+            }
+            match tree {
+                syn::UseTree::Name(leaf) => leaf.ident.to_string(),
+                syn::UseTree::Rename(leaf) => leaf.rename.to_string(),
+                // Globs and groups are keyed by the whole declaration.
+                _ => signature(syn::Item::Use(item.clone())),
+            }
+        }
+        _ => return None,
+    };
+    Some((name, item))
+}
+
+fn item_attrs(item: &syn::Item) -> &[syn::Attribute] {
+    match item {
+        syn::Item::Const(item) => &item.attrs,
+        syn::Item::Enum(item) => &item.attrs,
+        syn::Item::Fn(item) => &item.attrs,
+        syn::Item::Static(item) => &item.attrs,
+        syn::Item::Struct(item) => &item.attrs,
+        syn::Item::Trait(item) => &item.attrs,
+        syn::Item::Type(item) => &item.attrs,
+        syn::Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+/// Text of the doc comments (i.e. `#[doc = "..."]` attributes), with the
+/// lines trimmed.
+fn doc_text(attrs: &[syn::Attribute]) -> String {
+    attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(lit),
+                        ..
+                    }),
+                ..
+            }) if path.is_ident("doc") => Some(lit.value()),
+            _ => None,
+        })
+        .flat_map(|doc| {
+            doc.lines()
+                .map(|line| line.trim().to_string())
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+fn retain_derives(attrs: &mut Vec<syn::Attribute>) {
+    attrs.retain(|attr| attr.path().is_ident("derive"));
+}
+
+fn without_attrs(mut field: syn::Field) -> syn::Field {
+    field.attrs.clear();
+    field
+}
+
+fn is_public(vis: &syn::Visibility) -> bool {
+    matches!(vis, syn::Visibility::Public(_))
+}
+
+/// One-line signature of an item skeleton, without the (empty) body.
+fn signature(item: syn::Item) -> String {
+    let file = syn::File {
+        shebang: None,
+        attrs: Vec::new(),
+        items: vec![item],
+    };
+    one_line(&unparse(&file))
+        .trim_end_matches(" {}")
+        .to_string()
+}
+
+/// Collapse whitespace into single spaces.
+fn one_line(s: &str) -> String {
+    s.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn join(module: &[String], name: &str) -> String {
+    module
+        .iter()
+        .map(String::as_str)
+        .chain(std::iter::once(name))
+        .collect::<Vec<_>>()
+        .join("::")
+}
+
+/// File of a module, given the directory of its submodules: either `foo.rs`
+/// or `foo/mod.rs`.
+fn mod_file(dir: &Path) -> PathBuf {
+    let file = dir.with_extension("rs");
+    if file.exists() {
+        file
+    } else {
+        dir.join("mod.rs")
+    }
+}
+
+fn parse(path: &Path) -> Result<syn::File> {
+    let content = fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+    syn::parse_file(&content).context(format!("failed to parse {}", path.display()))
+}
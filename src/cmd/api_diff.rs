@@ -0,0 +1,56 @@
+use {
+    crate::cmd::{SubCmd, api::PublicApi},
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::path::{Path, PathBuf},
+};
+
+/// Compare public APIs of two versions of a library crate.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "api-diff")]
+pub struct ApiDiffSubCmd {
+    #[argh(positional)]
+    /// path to the old version of the crate (directory with `Cargo.toml`)
+    old: String,
+
+    #[argh(positional)]
+    /// path to the new version of the crate (directory with `Cargo.toml`)
+    new: String,
+}
+
+impl SubCmd for ApiDiffSubCmd {
+    fn run(&self) -> Result<()> {
+        let old = PublicApi::collect(&crate_dir(&self.old)?)
+            .context(format!("failed to collect API of {}", self.old))?;
+        let new = PublicApi::collect(&crate_dir(&self.new)?)
+            .context(format!("failed to collect API of {}", self.new))?;
+
+        let diff = old.diff(&new);
+        if diff.is_empty() {
+            println!("No public API changes.");
+            return Ok(());
+        }
+        print!("{diff}");
+        println!(
+            "{} added, {} removed, {} changed",
+            diff.added.len(),
+            diff.removed.len(),
+            diff.changed.len()
+        );
+        Ok(())
+    }
+}
+
+/// Crate directory, given either the directory or its `Cargo.toml`.
+fn crate_dir(path: &str) -> Result<PathBuf> {
+    let path = Path::new(path);
+    let dir = if path.ends_with("Cargo.toml") {
+        path.parent().unwrap_or(Path::new("."))
+    } else {
+        path
+    };
+    if !dir.join("Cargo.toml").exists() {
+        return Err(anyhow!("Cargo.toml is not found in {}", dir.display()));
+    }
+    Ok(dir.to_path_buf())
+}
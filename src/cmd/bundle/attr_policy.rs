@@ -0,0 +1,51 @@
+use std::collections::HashSet;
+
+/// Attributes dropped from the bundled library code, unless configured
+/// otherwise.
+const DEFAULT_DROPPED: [&str; 4] = ["doc", "allow", "cfg", "warn"];
+
+/// Decides which attributes of the library items survive bundling.
+///
+/// Attributes are matched by their path, e.g. `inline`, `must_use` or
+/// `rustfmt::skip`. Everything that is not explicitly dropped is retained, and
+/// the keep-list always takes precedence over the drop-list.
+#[derive(Debug, Clone)]
+pub struct AttrPolicy {
+    keep: HashSet<String>,
+    drop: HashSet<String>,
+}
+
+impl Default for AttrPolicy {
+    fn default() -> Self {
+        Self {
+            keep: HashSet::new(),
+            drop: DEFAULT_DROPPED.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+impl AttrPolicy {
+    /// Create a policy, extending the defaults with the given lists.
+    pub fn new(keep: &[String], drop: &[String]) -> Self {
+        let mut policy = Self::default();
+        policy.keep.extend(keep.iter().cloned());
+        policy.drop.extend(drop.iter().cloned());
+        policy
+    }
+
+    /// Whether the attribute should be retained in the output.
+    pub fn retains(&self, attr: &syn::Attribute) -> bool {
+        let path = attr_path(attr);
+        self.keep.contains(&path) || !self.drop.contains(&path)
+    }
+}
+
+/// Returns the path of an attribute as a string, e.g. `rustfmt::skip`.
+fn attr_path(attr: &syn::Attribute) -> String {
+    attr.path()
+        .segments
+        .iter()
+        .map(|segment| segment.ident.to_string())
+        .collect::<Vec<_>>()
+        .join("::")
+}
@@ -0,0 +1,278 @@
+use {
+    crate::cmd::{cargo, jobs::Jobs},
+    anyhow::{Context, Result, anyhow},
+    proc_macro2::{Span, TokenStream},
+    quote::ToTokens,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    syn::{
+        Expr,
+        Lit,
+        parse::Parser,
+        parse_file,
+        punctuated::Punctuated,
+        visit_mut::{self, VisitMut},
+    },
+};
+
+/// Whether the crate has a build script: either `build.rs` next to its
+/// `Cargo.toml`, or the one set with `package.build`.
+pub fn has_build_script(crate_path: &Path) -> bool {
+    let build = fs::read_to_string(crate_path.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Value>().ok())
+        .and_then(|value| value.get("package")?.get("build").cloned());
+    match build {
+        Some(toml::Value::String(_)) => true,
+        Some(toml::Value::Boolean(false)) => false,
+        _ => crate_path.join("build.rs").exists(),
+    }
+}
+
+/// Outputs of a crate's build script.
+#[derive(Debug)]
+pub struct BuildOutput {
+    /// Directory, generated files are written into (i.e. `OUT_DIR`).
+    pub out_dir: PathBuf,
+
+    /// `cfg`s set by the build script.
+    pub cfgs: Vec<String>,
+
+    /// Names of environment variables set by the build script.
+    pub env: Vec<String>,
+}
+
+impl BuildOutput {
+    /// Run the build script of the crate (by checking the crate with cargo, in
+    /// the current project), and collect its outputs.
+    pub fn run(crate_path: &Path) -> Result<Self> {
+        let manifest_path = crate_path
+            .join("Cargo.toml")
+            .canonicalize()
+            .context("failed to canonicalize crate manifest path")?;
+        let package = fs::read_to_string(&manifest_path)
+            .ok()
+            .and_then(|content| content.parse::<toml::Value>().ok())
+            .and_then(|value| Some(value.get("package")?.get("name")?.as_str()?.to_string()))
+            .context("crate manifest has no package name")?;
+
+        println!("Running build script of {package:?}...");
+        let output = cargo::command("check", Jobs::default())
+            .args(["--quiet", "--message-format=json", "-p", &package])
+            .output()
+            .context("failed to run cargo check")?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "cargo check failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ));
+        }
+
+        // Build script messages only refer to the package ID, which is found in the
+        // messages about the crate's own artifacts.
+        let messages = String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+            .collect::<Vec<_>>();
+        let package_id = messages
+            .iter()
+            .find(|message| {
+                message["manifest_path"].as_str().map(Path::new) == Some(&manifest_path)
+            })
+            .and_then(|message| message["package_id"].as_str())
+            .context(format!("package {package:?} is not built by cargo"))?;
+        let message = messages
+            .iter()
+            .find(|message| {
+                message["reason"] == "build-script-executed" && message["package_id"] == package_id
+            })
+            .context(format!("build script of {package:?} is not run by cargo"))?;
+
+        let strings = |value: &serde_json::Value| {
+            value
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|item| item.as_str().or_else(|| item[0].as_str()))
+                .map(|item| item.to_string())
+                .collect()
+        };
+        Ok(Self {
+            out_dir: message["out_dir"]
+                .as_str()
+                .map(PathBuf::from)
+                .context("build script has no output directory")?,
+            cfgs: strings(&message["cfgs"]),
+            env: strings(&message["env"]),
+        })
+    }
+}
+
+/// Replaces `include!`, `include_str!` and `include_bytes!` of the files,
+/// generated by a build script into `OUT_DIR`, with their contents.
+///
+/// Other references to `OUT_DIR` (e.g. `include!` of items within function
+/// bodies) can not be inlined, and are reported as errors.
+pub struct OutDirInliner<'a> {
+    out_dir: &'a Path,
+
+    /// Number of inlined files.
+    pub inlined: usize,
+
+    /// Generated files, which can not be inlined.
+    pub errors: Vec<String>,
+}
+
+impl<'a> OutDirInliner<'a> {
+    pub fn new(out_dir: &'a Path) -> Self {
+        Self {
+            out_dir,
+            inlined: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Generated file, a macro of the given kind refers to, if any.
+    fn generated_file(&self, mac: &syn::Macro, kind: &str) -> Option<PathBuf> {
+        mac.path
+            .is_ident(kind)
+            .then(|| out_dir_file(&mac.tokens))
+            .flatten()
+            .map(|file| self.out_dir.join(file.trim_start_matches('/')))
+    }
+
+    fn read(&mut self, path: &Path) -> Option<String> {
+        match fs::read_to_string(path) {
+            Ok(content) => Some(content),
+            Err(err) => {
+                self.errors
+                    .push(format!("failed to read {}: {err}", path.display()));
+                None
+            }
+        }
+    }
+
+    fn inline_items(&mut self, items: &mut Vec<syn::Item>) {
+        let mut new_items = Vec::with_capacity(items.len());
+        for item in items.drain(..) {
+            let file = match &item {
+                syn::Item::Macro(item) => self.generated_file(&item.mac, "include"),
+                _ => None,
+            };
+            let Some(path) = file else {
+                new_items.push(item);
+                continue;
+            };
+            let Some(content) = self.read(&path) else {
+                new_items.push(item);
+                continue;
+            };
+            match parse_file(&content) {
+                Ok(file) => {
+                    self.inlined += 1;
+                    new_items.extend(file.items);
+                }
+                Err(err) => {
+                    self.errors
+                        .push(format!("failed to parse {}: {err}", path.display()));
+                    new_items.push(item);
+                }
+            }
+        }
+        *items = new_items;
+    }
+}
+
+impl VisitMut for OutDirInliner<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.inline_items(&mut file.items);
+        visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, node: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut node.content {
+            self.inline_items(items);
+        }
+        visit_mut::visit_item_mod_mut(self, node);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if let Expr::Macro(node) = expr {
+            let mac = &node.mac;
+            if let Some(path) = self.generated_file(mac, "include") {
+                if let Some(content) = self.read(&path) {
+                    match syn::parse_str::<Expr>(&content) {
+                        Ok(inlined) => {
+                            self.inlined += 1;
+                            *expr = inlined;
+                        }
+                        Err(err) => self
+                            .errors
+                            .push(format!("failed to parse {}: {err}", path.display())),
+                    }
+                }
+                return;
+            }
+            if let Some(path) = self.generated_file(mac, "include_str") {
+                if let Some(content) = self.read(&path) {
+                    self.inlined += 1;
+                    let lit = syn::LitStr::new(&content, Span::call_site());
+                    *expr = syn::parse_quote!(#lit);
+                }
+                return;
+            }
+            if let Some(path) = self.generated_file(mac, "include_bytes") {
+                match fs::read(&path) {
+                    Ok(content) => {
+                        self.inlined += 1;
+                        let lit = syn::LitByteStr::new(&content, Span::call_site());
+                        *expr = syn::parse_quote!(#lit);
+                    }
+                    Err(err) => self
+                        .errors
+                        .push(format!("failed to read {}: {err}", path.display())),
+                }
+                return;
+            }
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        if mac.tokens.to_string().contains("\"OUT_DIR\"") {
+            self.errors.push(format!(
+                "`{}!` refers to `OUT_DIR`, but can not be inlined",
+                mac.path.to_token_stream()
+            ));
+        }
+    }
+}
+
+/// Path of the file within `OUT_DIR`, the macro arguments refer to, i.e. the
+/// `"/file.rs"` of `concat!(env!("OUT_DIR"), "/file.rs")`.
+fn out_dir_file(tokens: &TokenStream) -> Option<String> {
+    let Ok(Expr::Macro(concat)) = syn::parse2::<Expr>(tokens.clone()) else {
+        return None;
+    };
+    if !concat.mac.path.is_ident("concat") {
+        return None;
+    }
+    let args = Punctuated::<Expr, syn::Token![,]>::parse_terminated
+        .parse2(concat.mac.tokens)
+        .ok()?;
+    let mut args = args.into_iter();
+    match args.next()? {
+        Expr::Macro(env)
+            if env.mac.path.is_ident("env") && env.mac.tokens.to_string() == "\"OUT_DIR\"" => {}
+        _ => return None,
+    }
+    args.map(|arg| match arg {
+        Expr::Lit(syn::ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Some(lit.value()),
+        _ => None,
+    })
+    .collect()
+}
@@ -0,0 +1,167 @@
+use {
+    crate::cmd::{
+        bundle::{judge_profile::JudgeProfile, local_gate::LOCAL_FEATURE, source_map::SourceMap},
+        cargo,
+        jobs::Jobs,
+        manifest::{Dependency, Manifest, enclosing_workspace, package_name},
+    },
+    anyhow::{Context, Result, anyhow},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Project, bundled problems are written into (normally, `./bundled`).
+///
+/// Project-level outputs (directory layout and `Cargo.toml`) are shared by all
+/// problems, so that several problems can be bundled concurrently, each writing
+/// only its own binary file.
+#[derive(Debug, Clone)]
+pub struct BundledProject {
+    /// Root directory of the project.
+    dir: PathBuf,
+
+    /// Directory for the bundled binaries, i.e. `src/bin` in the project.
+    bin_dir: PathBuf,
+}
+
+impl BundledProject {
+    /// Create the project layout, if it doesn't exist yet.
+    pub fn prepare(dir: &Path) -> Result<Self> {
+        let project = Self::new(dir);
+        fs::create_dir_all(&project.bin_dir)
+            .context("failed to create bundled project directory")?;
+        Ok(project)
+    }
+
+    /// Project in the given directory, which may not exist yet.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            bin_dir: dir.join("src/bin"),
+        }
+    }
+
+    /// Write `Cargo.toml`, depending on the given (not bundled) project crates.
+    ///
+    /// The template manifest gets the dependencies, and the local feature is
+    /// not enabled by default, while the release profile follows the judge's
+    /// (so that the bundle is built the same way the judge builds it). Within a
+    /// workspace (e.g. an isolated contest project), the bundled project is a
+    /// workspace of its own. The file is only rewritten when its content
+    /// differs, so that the bundled project is not rebuilt needlessly.
+    pub fn write_manifest(
+        &self,
+        dependencies: &[(String, PathBuf)],
+        profile: &JudgeProfile,
+    ) -> Result<()> {
+        let mut manifest = Manifest::template()?;
+        for (name, path) in dependencies {
+            // Crate paths are relative to the project root, the bundled project
+            // is one level deeper.
+            let path = Path::new("..").join(path).display().to_string();
+            let package = package_name(path.as_ref()).filter(|package| package != name);
+            manifest
+                .set_dependency(name, &Dependency::Path { path, package })
+                .context("failed to update Cargo.toml template")?;
+        }
+        manifest.remove_default_feature(LOCAL_FEATURE);
+        let comment = format!(
+            "Release builds resemble the way {} builds solutions (see `judge` \
+             and\n`judge_profile` settings), so that the measured performance is close to the \
+             judge's.",
+            profile.judge.as_deref().unwrap_or("the judge")
+        );
+        manifest.set_profile("release", &comment, &profile.settings)?;
+        if enclosing_workspace(&self.dir).is_some() {
+            manifest.isolate_workspace(
+                "Bundled project is not a member of the enclosing workspace, but a workspace of \
+                 its own.",
+            );
+        }
+        manifest
+            .write(&self.dir.join("Cargo.toml"))
+            .context("failed to write bundled Cargo.toml")
+    }
+
+    /// Path of the bundled binary for a given problem.
+    pub fn bin_path(&self, problem_id: &str) -> PathBuf {
+        self.bin_dir.join(format!("{problem_id}.rs"))
+    }
+
+    /// Compile the bundled binary for a given problem: either check it, or
+    /// build it with the release profile (which the judge uses, so that errors
+    /// surfacing only there, e.g. when linking, are caught too).
+    ///
+    /// Errors are reported along with the locations in the original sources
+    /// (relative to `root`), the offending bundled lines come from.
+    pub fn check(
+        &self,
+        problem_id: &str,
+        source_map: &SourceMap,
+        root: &Path,
+        release: bool,
+        jobs: Jobs,
+    ) -> Result<()> {
+        let (subcommand, args): (_, &[&str]) = if release {
+            println!("Building bundled problem {problem_id:?} in release mode...");
+            ("build", &["--release"])
+        } else {
+            println!("Checking bundled problem {problem_id:?}...");
+            ("check", &[])
+        };
+        let output = cargo::command(subcommand, jobs)
+            .args(args)
+            .args(["--quiet", "--message-format=json", "--bin"])
+            .arg(problem_id)
+            .current_dir(&self.dir)
+            .output()
+            .context(format!("failed to run cargo {subcommand}"))?;
+
+        let bin_file = Path::new("src/bin").join(format!("{problem_id}.rs"));
+        let mut errors = 0;
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let Ok(message) = serde_json::from_str::<serde_json::Value>(line) else {
+                continue;
+            };
+            let message = &message["message"];
+            if message["level"] != "error" {
+                continue;
+            }
+            errors += 1;
+            print!("{}", message["rendered"].as_str().unwrap_or_default());
+
+            let spans = message["spans"].as_array().into_iter().flatten();
+            for span in spans.filter(|span| span["is_primary"] == true) {
+                let Some(line) = span["line_start"].as_u64() else {
+                    continue;
+                };
+                if span["file_name"].as_str().map(Path::new) != Some(&bin_file) {
+                    continue;
+                }
+                if let Some((file, origin)) = source_map.resolve(line as usize) {
+                    let file = file.strip_prefix(root).unwrap_or(file);
+                    println!(
+                        "  = note: bundled line {line} originates from {}:{origin}\n",
+                        file.display()
+                    );
+                }
+            }
+        }
+
+        if !output.status.success() {
+            // Failures without diagnostics (e.g. of the linker) are only on stderr.
+            if errors == 0 {
+                print!("{}", String::from_utf8_lossy(&output.stderr));
+            }
+            return Err(anyhow!(
+                "cargo {} failed with {errors} error(s), status: {}",
+                [&[subcommand], args].concat().join(" "),
+                output.status
+            ));
+        }
+        println!("Bundled problem {problem_id:?} compiles.");
+        Ok(())
+    }
+}
@@ -0,0 +1,52 @@
+use {
+    anyhow::{Context, Result, anyhow},
+    std::{
+        io::{ErrorKind, Write},
+        process::{Command, Stdio},
+    },
+};
+
+/// Clipboard tools, tried in order: macOS, Wayland, X11 and Windows (WSL)
+/// ones.
+const TOOLS: [(&str, &[&str]); 5] = [
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+/// Copy the text into the system clipboard, using the first available
+/// clipboard tool.
+///
+/// Returns the name of the tool used.
+pub fn copy(text: &str) -> Result<&'static str> {
+    for (tool, args) in TOOLS {
+        let mut child = match Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).context(format!("failed to run {tool}")),
+        };
+        child
+            .stdin
+            .take()
+            .context("failed to open clipboard tool input")?
+            .write_all(text.as_bytes())
+            .context(format!("failed to write into {tool}"))?;
+        let status = child.wait().context(format!("failed to wait for {tool}"))?;
+        if !status.success() {
+            return Err(anyhow!("{tool} failed with status: {status}"));
+        }
+        return Ok(tool);
+    }
+    Err(anyhow!(
+        "no clipboard tool found (tried: {})",
+        TOOLS.map(|(tool, _)| tool).join(", ")
+    ))
+}
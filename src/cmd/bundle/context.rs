@@ -1,17 +1,93 @@
 use {
     crate::cmd::{
-        TPL_DIR,
-        bundle::parsed_data::{Crates, ParsedPaths},
-        copy_to,
+        bundle::{
+            attr_policy::AttrPolicy,
+            bundled_project::BundledProject,
+            header::Header,
+            parsed_data::{Crates, ParsedPaths},
+            redact::Redactions,
+            rename::Renames,
+            source_map::SourceMap,
+            stats::BundleStats,
+            warnings::Warnings,
+        },
+        cargo,
+        jobs::Jobs,
     },
-    anyhow::{Context, Result},
+    anyhow::{Context, Result, anyhow},
     std::{
-        fs::{self, File},
-        io::BufWriter,
+        collections::HashSet,
+        fs,
         path::{Path, PathBuf},
     },
 };
 
+/// Name of the published algorithms library, used by contests created without
+/// a custom library.
+const ALGORIST_CRATE: &str = "algorist";
+
+/// Bundling options, normally set from the command line.
+#[derive(Debug, Default)]
+pub struct BundlerOptions {
+    /// Policy deciding which attributes of library items are retained.
+    pub attr_policy: AttrPolicy,
+
+    /// Comment header to prepend to the output file, if requested.
+    pub header: Option<Header>,
+
+    /// Strings to redact from the output file, if requested.
+    pub redactions: Option<Redactions>,
+
+    /// Identifiers to rename in the output file.
+    pub renames: Renames,
+
+    /// Whether to escape non-ASCII characters in string and char literals.
+    pub ascii_literals: bool,
+
+    /// Project crates to bundle, all of them if empty.
+    pub only_crates: Vec<String>,
+
+    /// Project crates not to bundle.
+    pub exclude_crates: Vec<String>,
+
+    /// Whether to keep test modules of the solution.
+    pub keep_tests: bool,
+
+    /// Whether to keep test modules of the library (and of the solution), along
+    /// with their `cfg` attributes.
+    pub with_tests: bool,
+
+    /// Whether to strip debug-only modules of the solution.
+    pub strip_debug: bool,
+
+    /// Whether to fail if there are any warnings.
+    pub deny_warnings: bool,
+
+    /// Crates, available on the judge, that the bundled file may refer to.
+    pub allow_crates: Vec<String>,
+
+    /// Whether to drop library items, not reachable from the solution.
+    pub prune_items: bool,
+
+    /// Whether to strip comments and collapse whitespace of the output file.
+    pub minify: bool,
+
+    /// Whether to rename modules to short names, when minifying.
+    pub minify_mods: bool,
+
+    /// Whether to skip pretty-printing of the output, emitting the tokens as
+    /// they are.
+    pub no_format: bool,
+
+    /// Run build scripts of the bundled crates, inlining the files they
+    /// generate.
+    pub run_build_script: bool,
+
+    /// Crates, vendored from crates.io, to bundle (along with their vendored
+    /// dependencies).
+    pub inline_deps: Vec<String>,
+}
+
 #[derive(Debug)]
 pub struct BundlerContext {
     /// Problem ID, used to locate the source file.
@@ -23,9 +99,18 @@ pub struct BundlerContext {
     /// Any import that is not from these crates will be ignored.
     pub crates: Crates,
 
+    /// Project crates, excluded from bundling.
+    ///
+    /// They remain regular dependencies of the bundled project, e.g. when
+    /// the crate is available on the judge.
+    pub external_crates: Vec<(String, PathBuf)>,
+
     /// Set of used modules, collected from the binary file.
     pub used_paths: ParsedPaths,
 
+    /// Identifiers, the binary file refers to.
+    pub used_idents: HashSet<String>,
+
     /// Root path of the project, in canonical form.
     pub root_path: String,
 
@@ -35,52 +120,167 @@ pub struct BundlerContext {
     /// Destination file path, in canonical form.
     pub dst: PathBuf,
 
-    /// Output file writer.
-    /// All bundled code will be written to this file.
-    pub out: BufWriter<File>,
+    /// Bundling options.
+    pub opts: BundlerOptions,
+
+    /// Output buffer.
+    /// All bundled code is collected here, and written to the destination file
+    /// once bundling is complete.
+    pub out: Vec<u8>,
+
+    /// Origins of the bundled code, to map compile errors back to sources.
+    pub source_map: SourceMap,
+
+    /// Timings and file counts of the bundling phases.
+    pub stats: BundleStats,
+
+    /// Warnings, reported once bundling is complete.
+    pub warnings: Warnings,
 }
 
 impl BundlerContext {
-    pub fn new(problem_id: &str) -> Result<Self> {
+    pub fn new(problem_id: &str, project: &BundledProject, opts: BundlerOptions) -> Result<Self> {
         // Validate the problem ID.
         let src = PathBuf::from(format!("./src/bin/{}.rs", problem_id))
             .canonicalize()
             .context("source file for the problem is not found")?;
 
-        // Create the destination directory if it doesn't exist.
-        let bundled_dir = PathBuf::from("./bundled");
-        fs::create_dir_all(bundled_dir.join("src/bin"))?;
-
-        // Copy over `Cargo.toml` file to the bundled directory.
-        // Replace the `{{EXTERNAL_CRATE}}` placeholder with an empty string.
-        let cargo_toml = bundled_dir.join("Cargo.toml");
-        copy_to(&TPL_DIR, "Cargo.toml.tpl", &cargo_toml)?;
-        fs::write(
-            &cargo_toml,
-            fs::read_to_string(&cargo_toml)?.replace("{{EXTERNAL_CRATE}}", ""),
-        )?;
-
-        let dst = bundled_dir
-            .join("src/bin")
-            .join(format!("{}.rs", problem_id));
-        let out = BufWriter::new(File::create(&dst).context("failed to create output file")?);
+        // Only the problem's own binary is written by the bundler, the rest of the
+        // bundled project is prepared in advance.
+        let dst = project.bin_path(problem_id);
 
         let root_path = PathBuf::from("./")
             .canonicalize()
             .context("Failed to canonicalize root path")?;
 
-        // Get the list of crates available in the project.
-        let crates =
+        // Get the list of crates available in the project, and set aside the ones
+        // that should not be bundled.
+        let mut crates =
             Crates::new(Path::new("crates")).context("failed to get library crate names")?;
 
+        // Published `algorist` crate is normally vendored into `crates`. If it is not
+        // (e.g. vendoring was skipped), bundle it from wherever cargo resolves it.
+        if !crates.contains(ALGORIST_CRATE) && depends_on(ALGORIST_CRATE) {
+            let path = registry_crate_path(ALGORIST_CRATE)
+                .context(format!("failed to locate the source of {ALGORIST_CRATE:?}"))?;
+            println!("Using {ALGORIST_CRATE:?} crate from {}", path.display());
+            crates.push(ALGORIST_CRATE, path);
+        }
+
+        // Solutions may also use the project's own library target, if it has one.
+        if Path::new("src/lib.rs").exists()
+            && let Some(name) = project_lib_name()
+        {
+            crates.push(&name, PathBuf::from("."));
+        }
+        let only_crates = normalize_crate_names(&opts.only_crates);
+        let exclude_crates = normalize_crate_names(&opts.exclude_crates);
+        if let Some(name) = only_crates
+            .iter()
+            .chain(&exclude_crates)
+            .find(|name| !crates.contains(name))
+        {
+            return Err(anyhow!("crate {name:?} is not found in the project"));
+        }
+        // Crates, vendored from crates.io (but the library itself), are only bundled
+        // when asked for. The ones the project depends on are kept as dependencies.
+        let inline_deps = normalize_crate_names(&opts.inline_deps);
+        if let Some(name) = inline_deps.iter().find(|name| !crates.contains(name)) {
+            return Err(anyhow!("crate {name:?} is not found in the project"));
+        }
+        let inlined = crates.with_dependencies(
+            std::iter::once(ALGORIST_CRATE).chain(inline_deps.iter().map(String::as_str)),
+        );
+        let mut external_crates = Vec::new();
+        for (name, path) in crates.clone().into_iter() {
+            if crates.is_vendored(&name) && !inlined.contains(&name) {
+                crates.remove(&name);
+                if depends_on(&name) {
+                    external_crates.push((name, path));
+                }
+            }
+        }
+        for (name, path) in crates.clone().into_iter() {
+            if (!only_crates.is_empty() && !only_crates.contains(&name))
+                || exclude_crates.contains(&name)
+            {
+                crates.remove(&name);
+                external_crates.push((name, path));
+            }
+        }
+        external_crates.sort();
+
         Ok(Self {
             problem_id: problem_id.to_string(),
             crates,
+            external_crates,
             used_paths: ParsedPaths::new(),
+            used_idents: HashSet::new(),
             root_path: root_path.display().to_string(),
             src,
             dst,
-            out,
+            opts,
+            out: Vec::new(),
+            source_map: SourceMap::default(),
+            stats: BundleStats::default(),
+            warnings: Warnings::default(),
         })
     }
 }
+
+/// Crate names, as used in paths (i.e. with dashes replaced by underscores).
+fn normalize_crate_names(names: &[String]) -> Vec<String> {
+    names.iter().map(|name| name.replace('-', "_")).collect()
+}
+
+/// Name of the project's library target: either set explicitly in `[lib]`,
+/// or the package name.
+fn project_lib_name() -> Option<String> {
+    let value = fs::read_to_string("Cargo.toml")
+        .ok()?
+        .parse::<toml::Value>()
+        .ok()?;
+    value
+        .get("lib")
+        .and_then(|lib| lib.get("name"))
+        .or_else(|| value.get("package")?.get("name"))?
+        .as_str()
+        .map(|name| name.to_string())
+}
+
+/// Whether the project's `Cargo.toml` declares a dependency on the crate.
+fn depends_on(name: &str) -> bool {
+    fs::read_to_string("Cargo.toml")
+        .ok()
+        .and_then(|content| content.parse::<toml::Value>().ok())
+        .and_then(|value| value.get("dependencies")?.get(name).cloned())
+        .is_some()
+}
+
+/// Locate the source directory of a (non-path) dependency, using `cargo
+/// metadata`.
+fn registry_crate_path(name: &str) -> Result<PathBuf> {
+    let output = cargo::command("metadata", Jobs::default())
+        .args(["--format-version", "1"])
+        .output()
+        .context("failed to run cargo metadata")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "cargo metadata failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let metadata: serde_json::Value =
+        serde_json::from_slice(&output.stdout).context("failed to parse cargo metadata")?;
+    metadata["packages"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|package| package["name"] == name && !package["source"].is_null())
+        .and_then(|package| package["manifest_path"].as_str())
+        .and_then(|manifest_path| Path::new(manifest_path).parent())
+        .context(format!("{name:?} is not found in cargo metadata"))?
+        .canonicalize()
+        .context("failed to canonicalize crate path")
+}
@@ -0,0 +1,202 @@
+use {
+    proc_macro2::{Group, Ident, Punct, Spacing, Span, TokenStream, TokenTree},
+    std::collections::HashMap,
+    syn::{parse_quote, visit_mut::VisitMut},
+};
+
+/// Rewrite `crate::`-rooted paths of a library crate, so that they keep
+/// resolving once the crate is bundled as a module: `crate::math::gcd` becomes
+/// `crate::algorist::math::gcd`.
+///
+/// Paths rooted at the project crates, the crate depends on (`deps` maps the
+/// names the crate refers to them by to the crate names), are rewritten to go
+/// through their modules as well: `algorist::math::gcd` becomes
+/// `crate::algorist::math::gcd`.
+///
+/// Paths are rewritten on the AST, so string literals and comments are never
+/// touched. Macro arguments and bodies are not parsed, so paths within them
+/// (including `$crate::` ones) are rewritten on the token level.
+pub fn rewrite_crate_paths(file: &mut syn::File, crate_name: &str, deps: &HashMap<String, String>) {
+    CratePaths::new(Some(crate_name), deps).visit_file_mut(file);
+}
+
+/// Rewrite paths of a module of the solution, rooted at the project crates
+/// (see [`rewrite_crate_paths`]), so that they go through the crate modules.
+/// The crate modules are only in scope at the root of the bundled file.
+pub fn rewrite_dependency_paths(module: &mut syn::ItemMod, deps: &HashMap<String, String>) {
+    CratePaths::new(None, deps).visit_item_mod_mut(module);
+}
+
+struct CratePaths {
+    /// Module of the crate, `crate::`-rooted paths are rewritten to go
+    /// through (paths of the solution are left as they are).
+    crate_name: Option<Ident>,
+
+    /// Modules of the project crates, the crate depends on, by the names the
+    /// crate refers to them.
+    deps: HashMap<String, Ident>,
+}
+
+impl CratePaths {
+    fn new(crate_name: Option<&str>, deps: &HashMap<String, String>) -> Self {
+        Self {
+            crate_name: crate_name.map(|name| Ident::new(name, Span::call_site())),
+            deps: deps
+                .iter()
+                .map(|(name, target)| (name.clone(), Ident::new(target, Span::call_site())))
+                .collect(),
+        }
+    }
+
+    fn dep(&self, ident: &Ident) -> Option<Ident> {
+        self.deps.get(&ident.to_string()).cloned()
+    }
+
+    fn rewrite_use_tree(&self, tree: &mut syn::UseTree) {
+        let crate_token = Ident::new("crate", Span::call_site());
+        match tree {
+            syn::UseTree::Path(path)
+                if path.ident == "crate"
+                    && let Some(crate_name) = &self.crate_name =>
+            {
+                let rest = std::mem::replace(
+                    &mut *path.tree,
+                    syn::UseTree::Glob(syn::UseGlob {
+                        star_token: Default::default(),
+                    }),
+                );
+                *path.tree = syn::UseTree::Path(syn::UsePath {
+                    ident: crate_name.clone(),
+                    colon2_token: Default::default(),
+                    tree: Box::new(rest),
+                });
+            }
+            syn::UseTree::Path(path) => {
+                if let Some(dep) = self.dep(&path.ident) {
+                    path.ident = dep;
+                    let rest = std::mem::replace(tree, parse_quote!(*));
+                    *tree = parse_quote!(#crate_token::#rest);
+                }
+            }
+            // `use algorist;` or `use algorist as alg;`
+            syn::UseTree::Name(_) | syn::UseTree::Rename(_) => {
+                let (ident, name) = match &*tree {
+                    syn::UseTree::Rename(rename) => (&rename.ident, rename.rename.clone()),
+                    syn::UseTree::Name(name) => (&name.ident, name.ident.clone()),
+                    _ => unreachable!(),
+                };
+                if let Some(dep) = self.dep(ident) {
+                    *tree = if name == dep {
+                        parse_quote!(#crate_token::#dep)
+                    } else {
+                        parse_quote!(#crate_token::#dep as #name)
+                    };
+                }
+            }
+            syn::UseTree::Group(group) => {
+                for tree in &mut group.items {
+                    self.rewrite_use_tree(tree);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn rewrite_tokens(&self, tokens: TokenStream) -> TokenStream {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let is_colon = |i: usize, spacing: Spacing| matches!(tokens.get(i), Some(TokenTree::Punct(p)) if p.as_char() == ':' && p.spacing() == spacing);
+
+        let mut rewritten = Vec::with_capacity(tokens.len());
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Group(group) => {
+                    let mut new =
+                        Group::new(group.delimiter(), self.rewrite_tokens(group.stream()));
+                    new.set_span(group.span());
+                    rewritten.push(TokenTree::Group(new));
+                }
+                // The crate name goes right after `crate::`.
+                TokenTree::Punct(p)
+                    if p.as_char() == ':'
+                        && p.spacing() == Spacing::Alone
+                        && is_colon(i.wrapping_sub(1), Spacing::Joint)
+                        && matches!(tokens.get(i.wrapping_sub(2)), Some(TokenTree::Ident(ident)) if ident == "crate")
+                        && let Some(crate_name) = &self.crate_name =>
+                {
+                    rewritten.push(token.clone());
+                    rewritten.push(TokenTree::Ident(crate_name.clone()));
+                    rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Joint)));
+                    rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Alone)));
+                }
+                // Dependency crates, at the start of a path, go after `crate::`.
+                TokenTree::Ident(ident)
+                    if is_colon(i + 1, Spacing::Joint)
+                        && !matches!(tokens.get(i.wrapping_sub(1)), Some(TokenTree::Punct(p)) if p.as_char() == ':' || p.as_char() == '$')
+                        && let Some(dep) = self.dep(ident) =>
+                {
+                    rewritten.push(TokenTree::Ident(Ident::new("crate", ident.span())));
+                    rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Joint)));
+                    rewritten.push(TokenTree::Punct(Punct::new(':', Spacing::Alone)));
+                    rewritten.push(TokenTree::Ident(dep));
+                }
+                token => rewritten.push(token.clone()),
+            }
+        }
+        rewritten.into_iter().collect()
+    }
+}
+
+impl VisitMut for CratePaths {
+    fn visit_path_mut(&mut self, path: &mut syn::Path) {
+        syn::visit_mut::visit_path_mut(self, path);
+        if path.segments.len() > 1
+            && path.segments[0].ident == "crate"
+            && let Some(crate_name) = &self.crate_name
+        {
+            let segments = std::mem::take(&mut path.segments);
+            let mut segments = segments.into_iter();
+            path.segments.extend(segments.next());
+            path.segments.push(crate_name.clone().into());
+            path.segments.extend(segments);
+        } else if path.segments.len() > 1
+            && let Some(dep) = self.dep(&path.segments[0].ident)
+        {
+            path.leading_colon = None;
+            path.segments[0].ident = dep;
+            path.segments
+                .insert(0, Ident::new("crate", Span::call_site()).into());
+        }
+    }
+
+    fn visit_item_use_mut(&mut self, node: &mut syn::ItemUse) {
+        self.rewrite_use_tree(&mut node.tree);
+        if matches!(&node.tree, syn::UseTree::Path(path) if path.ident == "crate") {
+            node.leading_colon = None;
+        }
+    }
+
+    fn visit_item_mut(&mut self, item: &mut syn::Item) {
+        // Dependency crates are sibling modules now, not extern crates.
+        if let syn::Item::ExternCrate(node) = item
+            && let Some(dep) = self.dep(&node.ident)
+        {
+            let vis = &node.vis;
+            let name = node
+                .rename
+                .as_ref()
+                .map_or(&node.ident, |(_, rename)| rename);
+            *item = if *name == dep {
+                parse_quote!(#vis use crate::#dep;)
+            } else {
+                parse_quote!(#vis use crate::#dep as #name;)
+            };
+            return;
+        }
+        syn::visit_mut::visit_item_mut(self, item);
+    }
+
+    fn visit_macro_mut(&mut self, node: &mut syn::Macro) {
+        syn::visit_mut::visit_macro_mut(self, node);
+        node.tokens = self.rewrite_tokens(std::mem::take(&mut node.tokens));
+    }
+}
@@ -0,0 +1,34 @@
+use crate::cmd::bundle::context::BundlerContext;
+
+/// Print the tree of the library modules, residing in files of their own,
+/// marking the ones that would be bundled, along with the reasons.
+///
+/// Modules are listed as they are found when traversing the crates, so this
+/// is what the bundler would inline, before any pruning of the items.
+pub fn print_modules(ctx: &BundlerContext) {
+    let used_paths = &ctx.used_paths;
+    let (mut bundled, mut bundled_size, mut total_size) = (0, 0, 0);
+    println!("\nModules of the library crates ([+] bundled, [-] left out):");
+    for (path, &size) in used_paths.modules() {
+        let mut segments = path.split('/');
+        let name = segments.next_back().unwrap_or_default();
+        let indent = "  ".repeat(segments.count());
+        total_size += size;
+        if used_paths.contains_path(path) {
+            bundled += 1;
+            bundled_size += size;
+            let reason = used_paths
+                .reason(path)
+                .map(|reason| format!(": {reason}"))
+                .unwrap_or_default();
+            println!("[+] {indent}{name} ({size} bytes){reason}");
+        } else {
+            println!("[-] {indent}{name} ({size} bytes)");
+        }
+    }
+    println!(
+        "\n{bundled} of {} module file(s) would be bundled: {bundled_size} of {total_size} bytes \
+         (dry run, nothing is written)",
+        used_paths.modules().len()
+    );
+}
@@ -0,0 +1,68 @@
+use {
+    proc_macro2::{Group, Literal, TokenStream, TokenTree},
+    std::str::FromStr,
+    syn::visit_mut::VisitMut,
+};
+
+/// Escape non-ASCII characters in string and char literals as `\u{...}`.
+///
+/// Some judges mangle non-ASCII source files, while escaped literals evaluate
+/// to exactly the same values. Raw strings are turned into regular ones, as
+/// they cannot contain escapes.
+pub fn escape_non_ascii_literals(file: &mut syn::File) {
+    AsciiLiterals.visit_file_mut(file);
+}
+
+struct AsciiLiterals;
+
+impl AsciiLiterals {
+    /// Escaped literal, if the given one contains any non-ASCII characters.
+    fn escape(lit: &syn::Lit) -> Option<Literal> {
+        let text = match lit {
+            syn::Lit::Str(s) if !s.value().is_ascii() => {
+                format!("\"{}\"{}", s.value().escape_default(), s.suffix())
+            }
+            syn::Lit::Char(c) if !c.value().is_ascii() => {
+                format!("'{}'{}", c.value().escape_default(), c.suffix())
+            }
+            _ => return None,
+        };
+        let mut escaped = Literal::from_str(&text).ok()?;
+        escaped.set_span(lit.span());
+        Some(escaped)
+    }
+
+    /// Macro arguments and bodies are not parsed, so literals within them are
+    /// escaped on the token level.
+    fn escape_tokens(tokens: TokenStream) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                TokenTree::Literal(literal) => {
+                    let escaped = Self::escape(&syn::Lit::new(literal.clone()));
+                    TokenTree::Literal(escaped.unwrap_or(literal))
+                }
+                TokenTree::Group(group) => {
+                    let mut escaped =
+                        Group::new(group.delimiter(), Self::escape_tokens(group.stream()));
+                    escaped.set_span(group.span());
+                    TokenTree::Group(escaped)
+                }
+                token => token,
+            })
+            .collect()
+    }
+}
+
+impl VisitMut for AsciiLiterals {
+    fn visit_lit_mut(&mut self, lit: &mut syn::Lit) {
+        if let Some(escaped) = Self::escape(lit) {
+            *lit = syn::Lit::new(escaped);
+        }
+    }
+
+    fn visit_macro_mut(&mut self, node: &mut syn::Macro) {
+        syn::visit_mut::visit_macro_mut(self, node);
+        node.tokens = Self::escape_tokens(std::mem::take(&mut node.tokens));
+    }
+}
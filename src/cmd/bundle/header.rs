@@ -0,0 +1,72 @@
+use {
+    crate::cmd::{
+        bundle::context::BundlerContext,
+        format_utc,
+        now_secs,
+        problem::statement_url,
+        vcs::Vcs,
+    },
+    std::path::Path,
+};
+
+/// Comment header, prepended to the bundled file.
+///
+/// Makes archived submissions self-describing: which problem they solve, who
+/// and when bundled them, and which library snapshot was used.
+#[derive(Debug, Clone, Default)]
+pub struct Header {
+    /// Problem URL.
+    ///
+    /// If not set, the one of the statement notes (`statements/{id}.md`) is
+    /// used.
+    pub url: Option<String>,
+
+    /// Author of the solution.
+    ///
+    /// If not set, the user name, configured for the VCS (`git` or `jj`), is
+    /// used.
+    pub author: Option<String>,
+}
+
+impl Header {
+    /// Render the header as a block of line comments.
+    pub fn render(&self, ctx: &BundlerContext) -> String {
+        let mut lines = vec![format!("Problem: {}", ctx.problem_id)];
+        let noted_url = || statement_url(Path::new("."), &ctx.problem_id);
+        if let Some(url) = self.url.clone().or_else(noted_url) {
+            lines.push(format!("URL: {url}"));
+        }
+        let vcs_user = || Vcs::detect(Path::new("."))?.user_name(Path::new("."));
+        if let Some(author) = self.author.clone().or_else(vcs_user) {
+            lines.push(format!("Author: {author}"));
+        }
+        lines.push(format!("Bundled: {}", format_utc(now_secs())));
+
+        let mut crates = ctx
+            .crates
+            .clone()
+            .into_iter()
+            .filter(|(name, _)| ctx.used_paths.contains_path(name))
+            .collect::<Vec<_>>();
+        crates.sort();
+        for (name, path) in crates {
+            match Vcs::detect(&path).and_then(|vcs| vcs.last_commit(&path)) {
+                Some(commit) => lines.push(format!("Library: {name} @ {commit}")),
+                None => lines.push(format!("Library: {name}")),
+            }
+        }
+        lines.push(format!(
+            "Tool: {} {}",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION")
+        ));
+
+        // Values come from the CLI, notes and VCS config: a stray line break
+        // would end the comment and leak text into the submission as code.
+        lines
+            .into_iter()
+            .map(|line| line.replace(char::is_control, " "))
+            .map(|line| format!("// {}\n", line.trim_end()))
+            .collect::<String>()
+    }
+}
@@ -0,0 +1,194 @@
+use {
+    crate::cmd::bundle::{phases::utils::parse_source, source_map::SourceMap},
+    anyhow::{Context, Result, anyhow},
+    proc_macro2::Span,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    syn::{
+        Expr,
+        visit_mut::{self, VisitMut},
+    },
+};
+
+/// Macros, including files.
+const INCLUDE_MACROS: [&str; 3] = ["include", "include_str", "include_bytes"];
+
+/// Replace `include!`, `include_str!` and `include_bytes!` of the files, given
+/// by paths relative to the source file (e.g. `include!("tables.rs")`), with
+/// their contents, as the files won't be next to the bundled one.
+///
+/// Included items are marked in the source map (if given) with the included
+/// file. Returns the number of inlined files.
+pub fn inline_includes(
+    ast: &mut syn::File,
+    file: &Path,
+    source_map: Option<&mut SourceMap>,
+) -> Result<usize> {
+    let mut inliner = IncludeInliner {
+        chain: vec![file.to_path_buf()],
+        source_map,
+        inlined: 0,
+        error: None,
+    };
+    inliner.visit_file_mut(ast);
+    match inliner.error {
+        Some(err) => Err(err),
+        None => Ok(inliner.inlined),
+    }
+}
+
+struct IncludeInliner<'a> {
+    /// Source file, along with the files including it (included files may
+    /// include others, relative to themselves).
+    chain: Vec<PathBuf>,
+    source_map: Option<&'a mut SourceMap>,
+    inlined: usize,
+
+    /// First error, the inlining stopped at (visitors can't return errors).
+    error: Option<anyhow::Error>,
+}
+
+impl IncludeInliner<'_> {
+    /// File, the macro of the given kind includes, if it is given by a literal
+    /// path. Other includes (e.g. of files within `OUT_DIR`) are inlined
+    /// elsewhere, if at all.
+    fn included_file(&self, mac: &syn::Macro, kind: &str) -> Option<PathBuf> {
+        if !mac.path.is_ident(kind) {
+            return None;
+        }
+        let path = syn::parse2::<syn::LitStr>(mac.tokens.clone()).ok()?.value();
+        let dir = self.chain.last()?.parent()?;
+        Some(dir.join(path))
+    }
+
+    fn read(&self, path: &Path) -> Result<String> {
+        let including = self.chain.last().map(|file| file.display().to_string());
+        fs::read_to_string(path).context(format!(
+            "failed to read {}, included by {}",
+            path.display(),
+            including.unwrap_or_default()
+        ))
+    }
+
+    /// Items of the included file, with its own includes inlined.
+    fn items(&mut self, path: PathBuf) -> Result<Vec<syn::Item>> {
+        if self.chain.contains(&path) {
+            return Err(anyhow!("{} includes itself", path.display()));
+        }
+        let mut file = parse_source(&self.read(&path)?, &path)?;
+        let mut nested = IncludeInliner {
+            chain: [self.chain.as_slice(), std::slice::from_ref(&path)].concat(),
+            source_map: self.source_map.as_deref_mut(),
+            inlined: 0,
+            error: None,
+        };
+        nested.visit_file_mut(&mut file);
+        if let Some(err) = nested.error {
+            return Err(err);
+        }
+        self.inlined += nested.inlined + 1;
+        if let Some(source_map) = self.source_map.as_deref_mut() {
+            source_map.mark_items(&mut file.items, &path);
+        }
+        Ok(file.items)
+    }
+
+    fn inline_items(&mut self, items: &mut Vec<syn::Item>) {
+        let mut new_items = Vec::with_capacity(items.len());
+        for item in items.drain(..) {
+            let file = match &item {
+                syn::Item::Macro(item) => self.included_file(&item.mac, "include"),
+                _ => None,
+            };
+            match file {
+                Some(path) if self.error.is_none() => match self.items(path) {
+                    Ok(items) => new_items.extend(items),
+                    Err(err) => {
+                        self.error = Some(err);
+                        new_items.push(item);
+                    }
+                },
+                _ => new_items.push(item),
+            }
+        }
+        *items = new_items;
+    }
+
+    fn inline_expr(&mut self, mac: &syn::Macro) -> Result<Option<Expr>> {
+        if let Some(path) = self.included_file(mac, "include") {
+            let content = self.read(&path)?;
+            let expr = syn::parse_str::<Expr>(&content)
+                .map_err(|err| anyhow!("failed to parse {}: {err}", path.display()))?;
+            self.inlined += 1;
+            return Ok(Some(expr));
+        }
+        if let Some(path) = self.included_file(mac, "include_str") {
+            let lit = syn::LitStr::new(&self.read(&path)?, Span::call_site());
+            self.inlined += 1;
+            return Ok(Some(syn::parse_quote!(#lit)));
+        }
+        if let Some(path) = self.included_file(mac, "include_bytes") {
+            let content = fs::read(&path).context(format!("failed to read {}", path.display()))?;
+            let lit = syn::LitByteStr::new(&content, Span::call_site());
+            self.inlined += 1;
+            return Ok(Some(syn::parse_quote!(#lit)));
+        }
+        Ok(None)
+    }
+}
+
+impl VisitMut for IncludeInliner<'_> {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.inline_items(&mut file.items);
+        visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, node: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut node.content {
+            self.inline_items(items);
+        }
+        visit_mut::visit_item_mod_mut(self, node);
+    }
+
+    fn visit_expr_mut(&mut self, expr: &mut Expr) {
+        if self.error.is_some() {
+            return;
+        }
+        if let Expr::Macro(node) = expr {
+            match self.inline_expr(&node.mac) {
+                Ok(Some(inlined)) => {
+                    *expr = inlined;
+                    return;
+                }
+                Ok(None) => {}
+                Err(err) => {
+                    self.error = Some(err);
+                    return;
+                }
+            }
+        }
+        visit_mut::visit_expr_mut(self, expr);
+    }
+
+    fn visit_macro_mut(&mut self, mac: &mut syn::Macro) {
+        // Includes in other positions (e.g. of items within function bodies).
+        let file = INCLUDE_MACROS
+            .iter()
+            .find_map(|kind| self.included_file(mac, kind));
+        if let Some(path) = file
+            && self.error.is_none()
+        {
+            self.error = Some(anyhow!(
+                "`{}!` of {} can not be inlined in this position, include it at the module level \
+                 or as an expression",
+                mac.path
+                    .get_ident()
+                    .map(ToString::to_string)
+                    .unwrap_or_default(),
+                path.display()
+            ));
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use anyhow::{Result, anyhow};
+
+/// Keys of `[profile.release]`, which can be overridden.
+const KEYS: &[&str] = &[
+    "opt-level",
+    "lto",
+    "codegen-units",
+    "panic",
+    "incremental",
+    "debug-assertions",
+    "overflow-checks",
+];
+
+/// Release profile of the bundled project, approximating the way a judge
+/// builds solutions, so that locally measured performance of the bundle is
+/// close to what the judge sees.
+///
+/// Judges build from scratch, so incremental compilation (which partitions the
+/// code differently) is always disabled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JudgeProfile {
+    /// Name of the judge, the profile is for (if known).
+    pub judge: Option<String>,
+
+    /// `[profile.release]` settings, as TOML values.
+    pub settings: Vec<(String, String)>,
+}
+
+impl JudgeProfile {
+    /// Built-in profile of the given judge, with the overrides applied.
+    ///
+    /// Overrides are comma-separated `key=value` pairs, e.g.
+    /// `opt-level=3,lto=fat`.
+    pub fn resolve(judge: Option<&str>, overrides: Option<&str>) -> Result<Self> {
+        let defaults: &[(&str, &str)] = match judge {
+            // `rustc -O`, without Cargo.
+            Some("codeforces") => &[
+                ("opt-level", "2"),
+                ("lto", "false"),
+                ("codegen-units", "16"),
+                ("panic", "\"unwind\""),
+            ],
+            Some("atcoder") => &[
+                ("opt-level", "3"),
+                ("lto", "true"),
+                ("codegen-units", "16"),
+                ("panic", "\"abort\""),
+            ],
+            // Cargo's defaults for release builds.
+            _ => &[
+                ("opt-level", "3"),
+                ("lto", "false"),
+                ("codegen-units", "16"),
+                ("panic", "\"unwind\""),
+            ],
+        };
+        let mut profile = Self {
+            judge: judge.map(|judge| judge.to_string()),
+            settings: defaults
+                .iter()
+                .chain([("incremental", "false")].iter())
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+        };
+
+        let overrides = overrides.unwrap_or_default().split(',');
+        for pair in overrides.map(str::trim).filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair
+                .split_once('=')
+                .map(|(key, value)| (key.trim(), value.trim()))
+                .ok_or_else(|| {
+                    anyhow!("invalid profile override {pair:?}, expected `key=value`")
+                })?;
+            if !KEYS.contains(&key) {
+                return Err(anyhow!(
+                    "unsupported profile setting {key:?}, supported ones are: {}",
+                    KEYS.join(", ")
+                ));
+            }
+            profile.set(key, &toml_value(value));
+        }
+        Ok(profile)
+    }
+
+    fn set(&mut self, key: &str, value: &str) {
+        match self.settings.iter_mut().find(|(k, _)| k == key) {
+            Some((_, v)) => *v = value.to_string(),
+            None => self.settings.push((key.to_string(), value.to_string())),
+        }
+    }
+}
+
+/// Numbers, booleans and quoted strings are kept as is, everything else (e.g.
+/// `fat` or `abort`) is quoted.
+fn toml_value(value: &str) -> String {
+    let is_literal = value.parse::<i64>().is_ok()
+        || value == "true"
+        || value == "false"
+        || (value.len() > 1 && value.starts_with('"') && value.ends_with('"'));
+    if is_literal {
+        value.to_string()
+    } else {
+        format!("{value:?}")
+    }
+}
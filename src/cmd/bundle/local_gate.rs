@@ -0,0 +1,213 @@
+use {
+    crate::cmd::bundle::phases::utils::extract_imported_names,
+    proc_macro2::{TokenStream, TokenTree},
+    quote::ToTokens,
+    std::collections::HashSet,
+    syn::{
+        Attribute,
+        parse::Parser,
+        visit::{self, Visit},
+        visit_mut::VisitMut,
+    },
+};
+
+/// Name of the feature, gating local-only code in solutions.
+pub const LOCAL_FEATURE: &str = "local";
+
+/// Removes code gated with `#[cfg(feature = "local")]` from the solution.
+///
+/// Such code (debug helpers, visualizations, property tests) relies on
+/// local-only dependencies, and must never reach the judge.
+#[derive(Debug, Default)]
+pub struct LocalGateStripper {
+    /// Names of the removed items (functions, types, imports etc.). Names of
+    /// removed statements and methods are not recorded: local bindings and
+    /// methods are not referred to by paths, so uses of them can't be told
+    /// apart from the unrelated ones.
+    pub removed: HashSet<String>,
+
+    /// Number of removed items and statements.
+    pub count: usize,
+}
+
+impl LocalGateStripper {
+    /// Strip local-only code from the file.
+    ///
+    /// Returns names of the removed items, which are still referred to by
+    /// paths of the remaining code: such solution won't compile without the
+    /// local feature.
+    pub fn strip(&mut self, file: &mut syn::File) -> Vec<String> {
+        self.visit_file_mut(file);
+
+        let mut uses = PathUses::default();
+        uses.visit_file(file);
+        let mut dangling = self
+            .removed
+            .iter()
+            .filter(|name| uses.0.contains(*name))
+            .cloned()
+            .collect::<Vec<_>>();
+        dangling.sort();
+        dangling
+    }
+
+    fn retain_items(&mut self, items: &mut Vec<syn::Item>) {
+        items.retain(|item| {
+            if !is_local_only(item.to_token_stream()) {
+                return true;
+            }
+            self.count += 1;
+            self.removed.extend(item_names(item));
+            false
+        });
+    }
+}
+
+impl VisitMut for LocalGateStripper {
+    fn visit_file_mut(&mut self, file: &mut syn::File) {
+        self.retain_items(&mut file.items);
+        syn::visit_mut::visit_file_mut(self, file);
+    }
+
+    fn visit_item_mod_mut(&mut self, node: &mut syn::ItemMod) {
+        if let Some((_, items)) = &mut node.content {
+            self.retain_items(items);
+        }
+        syn::visit_mut::visit_item_mod_mut(self, node);
+    }
+
+    fn visit_item_impl_mut(&mut self, node: &mut syn::ItemImpl) {
+        node.items.retain(|item| {
+            if !is_local_only(item.to_token_stream()) {
+                return true;
+            }
+            self.count += 1;
+            false
+        });
+        syn::visit_mut::visit_item_impl_mut(self, node);
+    }
+
+    fn visit_block_mut(&mut self, node: &mut syn::Block) {
+        node.stmts.retain(|stmt| {
+            if !is_local_only(stmt.to_token_stream()) {
+                return true;
+            }
+            self.count += 1;
+            if let syn::Stmt::Item(item) = stmt {
+                self.removed.extend(item_names(item));
+            }
+            false
+        });
+        syn::visit_mut::visit_block_mut(self, node);
+    }
+}
+
+/// Identifiers, the segments of paths (in expressions, types, patterns, imports
+/// and macro invocations) are made of.
+#[derive(Default)]
+struct PathUses(HashSet<String>);
+
+impl<'ast> Visit<'ast> for PathUses {
+    fn visit_path(&mut self, node: &'ast syn::Path) {
+        self.0
+            .extend(node.segments.iter().map(|seg| seg.ident.to_string()));
+        visit::visit_path(self, node);
+    }
+
+    fn visit_use_tree(&mut self, node: &'ast syn::UseTree) {
+        match node {
+            syn::UseTree::Path(path) => {
+                self.0.insert(path.ident.to_string());
+            }
+            syn::UseTree::Name(name) => {
+                self.0.insert(name.ident.to_string());
+            }
+            syn::UseTree::Rename(rename) => {
+                self.0.insert(rename.ident.to_string());
+            }
+            _ => {}
+        }
+        visit::visit_use_tree(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        // Macro arguments are not parsed, any identifier in them may be a path.
+        collect_idents(node.tokens.clone(), &mut self.0);
+        visit::visit_macro(self, node);
+    }
+}
+
+/// Whether the tokens (of an item or statement) start with an outer attribute
+/// gating them on the local feature.
+fn is_local_only(tokens: TokenStream) -> bool {
+    let parser = |input: syn::parse::ParseStream| {
+        let attrs = input.call(Attribute::parse_outer)?;
+        input.parse::<TokenStream>()?;
+        Ok(attrs)
+    };
+    parser
+        .parse2(tokens)
+        .map(|attrs| {
+            attrs.iter().any(|attr| {
+                attr.path().is_ident("cfg")
+                    && attr
+                        .parse_args::<syn::Meta>()
+                        .is_ok_and(|meta| requires_local(&meta))
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Whether the cfg predicate can only hold with the local feature enabled.
+fn requires_local(meta: &syn::Meta) -> bool {
+    match meta {
+        syn::Meta::NameValue(nv) if nv.path.is_ident("feature") => matches!(
+            &nv.value,
+            syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(s), .. }) if s.value() == LOCAL_FEATURE
+        ),
+        syn::Meta::List(list) if list.path.is_ident("all") => list
+            .parse_args_with(
+                syn::punctuated::Punctuated::<syn::Meta, syn::Token![,]>::parse_terminated,
+            )
+            .is_ok_and(|metas| metas.iter().any(requires_local)),
+        _ => false,
+    }
+}
+
+/// Names, introduced by an item.
+fn item_names(item: &syn::Item) -> Vec<String> {
+    let ident = match item {
+        syn::Item::Const(item) => &item.ident,
+        syn::Item::Enum(item) => &item.ident,
+        syn::Item::Fn(item) => &item.sig.ident,
+        syn::Item::Mod(item) => &item.ident,
+        syn::Item::Static(item) => &item.ident,
+        syn::Item::Struct(item) => &item.ident,
+        syn::Item::Trait(item) => &item.ident,
+        syn::Item::Type(item) => &item.ident,
+        syn::Item::Union(item) => &item.ident,
+        syn::Item::Macro(syn::ItemMacro {
+            ident: Some(ident), ..
+        }) => ident,
+        syn::Item::Use(item) => {
+            return extract_imported_names(&item.tree, Vec::new())
+                .into_iter()
+                .map(|(_, name)| name)
+                .collect();
+        }
+        _ => return Vec::new(),
+    };
+    vec![ident.to_string()]
+}
+
+pub fn collect_idents(tokens: TokenStream, idents: &mut HashSet<String>) {
+    for token in tokens {
+        match token {
+            TokenTree::Ident(ident) => {
+                idents.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => collect_idents(group.stream(), idents),
+            _ => {}
+        }
+    }
+}
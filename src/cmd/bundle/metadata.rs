@@ -0,0 +1,56 @@
+use {
+    crate::cmd::{bundle::context::BundlerContext, write_atomic},
+    anyhow::{Context, Result},
+    serde_json::json,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Write the metadata of the bundled file (included modules, with their sizes
+/// and the reasons, and resolved `pub use` aliases) into a JSON file next to
+/// it, for editor plugins and scripts. Returns the path to the metadata file.
+///
+/// Module sizes are the ones of the source files, as bundled modules may be
+/// pruned, minified or reformatted.
+pub fn write_metadata(ctx: &BundlerContext, output: Option<&Path>) -> Result<PathBuf> {
+    let used_paths = &ctx.used_paths;
+    let path = |path: &str| path.replace('/', "::");
+    let modules = used_paths
+        .modules()
+        .iter()
+        .filter(|(module, _)| used_paths.contains_path(module))
+        .map(|(module, size)| {
+            json!({
+                "path": path(module),
+                "bytes": size,
+                "reason": used_paths.reason(module).map(|reason| reason.to_string()),
+            })
+        })
+        .collect::<Vec<_>>();
+    let aliases = used_paths
+        .used_aliases()
+        .into_iter()
+        .map(|(alias, fully_qualified)| (path(alias), json!(path(fully_qualified))))
+        .collect::<serde_json::Map<_, _>>();
+    let bytes = fs::metadata(&ctx.dst)
+        .context("failed to read bundled file")?
+        .len();
+    let metadata = json!({
+        "problem": ctx.problem_id,
+        "bundled": ctx.dst.display().to_string(),
+        "output": output.map(|output| output.display().to_string()),
+        "bytes": bytes,
+        "modules": modules,
+        "aliases": aliases,
+    });
+
+    let metadata_path = ctx.dst.with_extension("json");
+    write_atomic(
+        &metadata_path,
+        serde_json::to_string_pretty(&metadata)? + "\n",
+    )
+    .context(format!("failed to write {}", metadata_path.display()))?;
+    Ok(metadata_path)
+}
@@ -0,0 +1,343 @@
+use {
+    crate::cmd::bundle::{rename::Renames, warnings::Warnings},
+    anyhow::{Context, Result},
+    proc_macro2::{Delimiter, Spacing, TokenStream, TokenTree},
+    quote::ToTokens,
+    std::collections::{HashMap, HashSet},
+};
+
+/// Minify the bundled file, for judges with tight source size limits.
+///
+/// Comments (doc comments included) are stripped, and whitespace is reduced to
+/// what keeps the tokens apart, with each top-level item on a line of its own.
+/// Leading comment lines (i.e. the header) are kept as they are. With
+/// `short_mods`, modules are also renamed to short names.
+pub fn minify(content: &str, short_mods: bool) -> Result<String> {
+    let header_len = content
+        .split_inclusive('\n')
+        .take_while(|line| line.starts_with("//"))
+        .map(str::len)
+        .sum::<usize>();
+    let mut file = syn::parse_file(content).context("bundled file is not valid Rust")?;
+    if short_mods {
+        short_module_names(file.to_token_stream())?.apply(
+            &mut file,
+            true,
+            &mut Warnings::default(),
+        );
+    }
+
+    let mut out = content[..header_len].to_string();
+    let lines = file
+        .attrs
+        .iter()
+        .map(ToTokens::to_token_stream)
+        .collect::<TokenStream>();
+    for tokens in std::iter::once(lines).chain(file.items.iter().map(ToTokens::to_token_stream)) {
+        let mut printer = Printer::default();
+        printer.print(tokens);
+        if !printer.out.is_empty() {
+            out.push_str(&printer.out);
+            out.push('\n');
+        }
+    }
+    Ok(out)
+}
+
+/// Renames of modules to the shortest names, not used in the file (modules
+/// occurring most often get the shortest ones).
+///
+/// Only modules, the names of which occur solely in their declarations and in
+/// paths through the local modules (e.g. `crate::algorist::graph::Dfs`), are
+/// renamed: a name, used for anything else (a method, a variable, a module of
+/// `std`), is kept, so that the file still compiles.
+fn short_module_names(tokens: TokenStream) -> Result<Renames> {
+    let mut scan = ModuleScan::default();
+    scan.declarations(tokens.clone());
+    scan.paths(tokens, &[]);
+
+    let mut modules = scan
+        .uses
+        .into_iter()
+        .filter(|(name, _)| !scan.other_uses.contains(name))
+        .collect::<Vec<_>>();
+    modules.sort_by(|(a, a_uses), (b, b_uses)| b_uses.cmp(a_uses).then(a.cmp(b)));
+
+    let mut names = (1..).map(short_name).filter(|name| {
+        !scan.idents.contains(name) && name != "gen" && syn::parse_str::<syn::Ident>(name).is_ok()
+    });
+    let mut specs = Vec::new();
+    for (module, _) in modules {
+        let name = names.next().expect("names are unlimited");
+        if name.len() < module.len() {
+            specs.push(format!("{module}={name}"));
+        }
+    }
+    Renames::new(&specs)
+}
+
+/// Name number `n` (1-based) of the sequence `a`, ..., `z`, `aa`, `ab`, ...
+fn short_name(mut n: usize) -> String {
+    let mut name = Vec::new();
+    while n > 0 {
+        n -= 1;
+        name.push(b'a' + (n % 26) as u8);
+        n /= 26;
+    }
+    name.reverse();
+    String::from_utf8(name).expect("name is ASCII")
+}
+
+/// Occurrences of the module names in the token stream of the file.
+#[derive(Debug, Default)]
+struct ModuleScan {
+    /// All identifiers of the file.
+    idents: HashSet<String>,
+
+    /// Declared modules, with the number of their occurrences as modules.
+    uses: HashMap<String, usize>,
+
+    /// Module names, occurring as anything else.
+    other_uses: HashSet<String>,
+}
+
+impl ModuleScan {
+    /// Find module declarations (`mod name`).
+    fn declarations(&mut self, tokens: TokenStream) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        for (i, token) in tokens.iter().enumerate() {
+            match token {
+                TokenTree::Group(group) => self.declarations(group.stream()),
+                TokenTree::Ident(ident) => {
+                    self.idents.insert(ident.to_string());
+                    if ident == "mod"
+                        && let Some(TokenTree::Ident(name)) = tokens.get(i + 1)
+                    {
+                        self.uses.insert(name.to_string(), 0);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Check each path (a sequence of `::`-separated identifiers) of the
+    /// tokens, continuing the given prefix, if the tokens are a group of a
+    /// `use` declaration.
+    fn paths(&mut self, tokens: TokenStream, prefix: &[String]) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let is_colons = |i: usize| {
+            matches!(
+                (tokens.get(i), tokens.get(i + 1)),
+                (Some(TokenTree::Punct(a)), Some(TokenTree::Punct(b)))
+                    if a.as_char() == ':' && a.spacing() == Spacing::Joint && b.as_char() == ':'
+            )
+        };
+        let mut i = 0;
+        while i < tokens.len() {
+            let ident = match &tokens[i] {
+                TokenTree::Group(group) => {
+                    self.paths(group.stream(), &[]);
+                    i += 1;
+                    continue;
+                }
+                TokenTree::Ident(ident) => ident,
+                _ => {
+                    i += 1;
+                    continue;
+                }
+            };
+            if ident == "mod"
+                && let Some(TokenTree::Ident(name)) = tokens.get(i + 1)
+            {
+                *self.uses.entry(name.to_string()).or_default() += 1;
+                i += 2;
+                continue;
+            }
+
+            // Paths, starting with `::` (or with a macro variable), are foreign.
+            let foreign = (i >= 2 && is_colons(i - 2))
+                || (i >= 1
+                    && matches!(&tokens[i - 1], TokenTree::Punct(p) if p.as_char() == '$')
+                    && ident != "crate");
+            let mut path = prefix.to_vec();
+            path.push(ident.to_string());
+            let mut end = i + 1;
+            while is_colons(end)
+                && let Some(TokenTree::Ident(segment)) = tokens.get(end + 2)
+            {
+                path.push(segment.to_string());
+                end += 3;
+            }
+
+            // Groups of `use` declarations continue the path.
+            let group = match tokens.get(end + 2) {
+                Some(TokenTree::Group(group))
+                    if is_colons(end) && group.delimiter() == Delimiter::Brace =>
+                {
+                    Some(group)
+                }
+                _ => None,
+            };
+            self.check_path(&path, prefix.len(), foreign, group.is_some());
+            if let Some(group) = group {
+                let prefix = if foreign {
+                    vec!["::".to_string()]
+                } else {
+                    path
+                };
+                self.paths(group.stream(), &prefix);
+                end += 3;
+            }
+            i = end;
+        }
+    }
+
+    /// Record occurrences of module names in the path (those after `skip`
+    /// segments, which come from the enclosing `use` group).
+    ///
+    /// A name occurs as a module, if the path has more than one segment (or is
+    /// continued by a `use` group), and all the segments before the name are
+    /// local modules (or `crate`, `self` and `super`).
+    fn check_path(&mut self, path: &[String], skip: usize, foreign: bool, continued: bool) {
+        // Number of leading segments, which are local modules.
+        let local = path
+            .iter()
+            .take_while(|segment| {
+                matches!(segment.as_str(), "crate" | "self" | "super")
+                    || self.uses.contains_key(*segment)
+            })
+            .count();
+        for (i, name) in path.iter().enumerate().skip(skip) {
+            let Some(uses) = self.uses.get_mut(name) else {
+                continue;
+            };
+            if !foreign && (path.len() > 1 || continued) && i <= local {
+                *uses += 1;
+            } else {
+                self.other_uses.insert(name.clone());
+            }
+        }
+    }
+}
+
+/// Prints tokens with as little whitespace as possible.
+#[derive(Debug, Default)]
+struct Printer {
+    out: String,
+    prev: Prev,
+}
+
+/// Kind of the previously printed token, deciding whether the next one should
+/// be separated with a space.
+#[derive(Debug, Default, Clone, Copy)]
+enum Prev {
+    #[default]
+    Nothing,
+
+    /// Identifier, keyword or literal.
+    Word,
+
+    Punct(char, Spacing),
+    Delimiter,
+}
+
+impl Printer {
+    fn print(&mut self, tokens: TokenStream) {
+        let tokens = tokens.into_iter().collect::<Vec<_>>();
+        let mut i = 0;
+        while i < tokens.len() {
+            if let Some(len) = doc_attr(&tokens[i..]) {
+                i += len;
+                continue;
+            }
+            match &tokens[i] {
+                TokenTree::Group(group) if group.delimiter() == Delimiter::None => {
+                    self.print(group.stream());
+                }
+                TokenTree::Group(group) => {
+                    let (open, close) = match group.delimiter() {
+                        Delimiter::Parenthesis => ('(', ')'),
+                        Delimiter::Bracket => ('[', ']'),
+                        _ => ('{', '}'),
+                    };
+                    self.out.push(open);
+                    self.prev = Prev::Delimiter;
+                    self.print(group.stream());
+                    self.out.push(close);
+                    self.prev = Prev::Delimiter;
+                }
+                TokenTree::Ident(_) | TokenTree::Literal(_) => {
+                    if matches!(self.prev, Prev::Word) {
+                        self.out.push(' ');
+                    }
+                    self.out.push_str(&tokens[i].to_string());
+                    self.prev = Prev::Word;
+                }
+                TokenTree::Punct(punct) => {
+                    let c = punct.as_char();
+                    let space = match self.prev {
+                        // E.g. `break 'outer`, or `r #"..."` in macro bodies.
+                        Prev::Word => matches!(c, '\'' | '#'),
+                        Prev::Punct(prev, Spacing::Alone) => glues(prev, c),
+                        _ => false,
+                    };
+                    if space {
+                        self.out.push(' ');
+                    }
+                    self.out.push(c);
+                    self.prev = Prev::Punct(c, punct.spacing());
+                }
+            }
+            i += 1;
+        }
+    }
+}
+
+/// Whether the punctuation characters, written one after another, would be
+/// read as a single token (or as the start of a comment).
+///
+/// Closing `>` of generics are never glued, so `Vec<Vec<u8>>` stays as is.
+fn glues(a: char, b: char) -> bool {
+    matches!(
+        (a, b),
+        ('&', '&' | '=')
+            | ('|', '|' | '=')
+            | ('<', '<' | '=' | '-')
+            | ('>', '=')
+            | ('=', '=' | '>')
+            | ('!' | '+' | '%' | '^', '=')
+            | ('-', '=' | '>')
+            | ('*', '=' | '/')
+            | ('/', '=' | '/' | '*')
+            | ('.', '.')
+            | (':', ':')
+    )
+}
+
+/// Length of the doc attribute (`#[doc = ...]` or `#![doc = ...]`), the tokens
+/// start with, if any.
+fn doc_attr(tokens: &[TokenTree]) -> Option<usize> {
+    let TokenTree::Punct(hash) = tokens.first()? else {
+        return None;
+    };
+    if hash.as_char() != '#' {
+        return None;
+    }
+    let (group, len) = match tokens.get(1)? {
+        TokenTree::Punct(bang) if bang.as_char() == '!' => (tokens.get(2)?, 3),
+        group => (group, 2),
+    };
+    match group {
+        TokenTree::Group(group)
+            if group.delimiter() == Delimiter::Bracket
+                && matches!(
+                    group.stream().into_iter().next(),
+                    Some(TokenTree::Ident(ident)) if ident == "doc"
+                ) =>
+        {
+            Some(len)
+        }
+        _ => None,
+    }
+}
@@ -1,12 +1,57 @@
+mod attr_policy;
+mod build_script;
+mod bundled_project;
+mod clipboard;
 mod context;
+mod crate_paths;
+mod dry_run;
+mod escape;
+mod header;
+mod include;
+mod judge_profile;
+mod local_gate;
+mod metadata;
+mod minify;
 mod parsed_data;
 mod phases;
+mod prune;
+mod redact;
+mod rename;
+mod source_map;
+mod stats;
+mod verify;
+mod warnings;
 
+pub use phases::utils::mod_path_attr;
 use {
-    crate::cmd::{SubCmd, bundle::context::BundlerContext},
-    anyhow::{Context, Result},
+    crate::cmd::{
+        SubCmd,
+        bundle::{
+            attr_policy::AttrPolicy,
+            bundled_project::BundledProject,
+            context::{BundlerContext, BundlerOptions},
+            header::Header,
+            judge_profile::JudgeProfile,
+            redact::Redactions,
+            rename::Renames,
+        },
+        config::setting,
+        fmt,
+        jobs::Jobs,
+        meta::{Event, update_events},
+        metrics::{Failure, record_failure, record_phase},
+        problem::{for_each_id, select_ids},
+        write_atomic,
+    },
+    anyhow::{Context, Result, anyhow},
     argh::FromArgs,
     phases::BunlingPhase,
+    std::{
+        collections::HashMap,
+        fs,
+        path::{Path, PathBuf},
+        time::Instant,
+    },
 };
 
 /// Bundle given problem into a single file.
@@ -15,24 +60,409 @@ use {
 pub struct BundleProblemSubCmd {
     #[argh(positional)]
     /// problem ID
-    id: String,
+    id: Option<String>,
+
+    #[argh(switch)]
+    /// bundle all problems
+    all: bool,
+
+    #[argh(switch)]
+    /// bundle only problems, modified since they were last bundled
+    changed: bool,
+
+    #[argh(option)]
+    /// attribute to retain in the bundled library code, takes precedence over
+    /// `--drop-attr` (can be repeated, in addition to the configured
+    /// `keep_attrs`)
+    keep_attr: Vec<String>,
+
+    #[argh(switch)]
+    /// keep doc comments of the bundled library code, e.g. for practice
+    /// archives (same as `--keep-attr doc`)
+    keep_docs: bool,
+
+    #[argh(option)]
+    /// attribute to drop from the bundled library code, in addition to the
+    /// default `doc`, `allow`, `cfg` and `warn` (can be repeated, in addition
+    /// to the configured `drop_attrs`)
+    drop_attr: Vec<String>,
+
+    #[argh(switch)]
+    /// prepend a comment header with submission metadata (problem, URL,
+    /// author, timestamp, library commit, tool version), regardless of the
+    /// `bundle_header` setting
+    header: bool,
+
+    #[argh(option)]
+    /// problem URL to include in the header (defaults to the one of the
+    /// statement notes)
+    url: Option<String>,
+
+    #[argh(option)]
+    /// author to include in the header (defaults to the configured `handle`,
+    /// or the user name of `git` or `jj`)
+    author: Option<String>,
+
+    #[argh(switch)]
+    /// redact local absolute paths (project root, home directory) and the
+    /// strings given with `--redact-str` from the bundled file
+    redact: bool,
+
+    #[argh(option)]
+    /// string to redact, as `from` or `from=to` (can be repeated, implies
+    /// `--redact`)
+    redact_str: Vec<String>,
+
+    #[argh(option)]
+    /// rename an identifier throughout the bundled file, as `from=to` (can be
+    /// repeated)
+    rename: Vec<String>,
+
+    #[argh(switch)]
+    /// escape non-ASCII characters in string and char literals as `\u{...}`
+    ascii_literals: bool,
+
+    #[argh(option)]
+    /// bundle only the given project crate (can be repeated)
+    only_crate: Vec<String>,
+
+    #[argh(option)]
+    /// do not bundle the given project crate, keeping it as a regular
+    /// dependency of the bundled project (can be repeated)
+    exclude_crate: Vec<String>,
+
+    #[argh(switch)]
+    /// keep `#[cfg(test)]` modules of the solution in the bundled file
+    keep_tests: bool,
+
+    #[argh(switch)]
+    /// keep `#[cfg(test)]` modules of the library (and of the solution) in the
+    /// bundled file, along with their `cfg` attributes, so the tests can be run
+    /// from the bundled file
+    with_tests: bool,
+
+    #[argh(switch)]
+    /// strip `#[cfg(debug_assertions)]` modules of the solution from the
+    /// bundled file
+    strip_debug: bool,
+
+    #[argh(switch)]
+    /// fail, instead of writing the bundled file, if there are any warnings
+    deny_warnings: bool,
+
+    #[argh(option)]
+    /// crate, available on the judge, that the bundled file may refer to (can
+    /// be repeated)
+    allow_crate: Vec<String>,
+
+    #[argh(switch)]
+    /// compile the bundled file, reporting errors at their original locations
+    check: bool,
+
+    #[argh(switch)]
+    /// like `--check`, but build the bundled file with the release profile,
+    /// as the judge would
+    check_release: bool,
+
+    #[argh(switch)]
+    /// drop library items (functions, types, impls etc.), which are not
+    /// reachable from the solution, instead of bundling used modules whole
+    prune_items: bool,
+
+    #[argh(switch)]
+    /// strip comments and collapse whitespace of the bundled file, for judges
+    /// with tight source size limits
+    minify: bool,
+
+    #[argh(switch)]
+    /// skip pretty-printing of the bundled file, emitting the tokens as they
+    /// are (faster for large libraries, compile errors of `--check` are not
+    /// mapped back to the sources)
+    no_format: bool,
+
+    #[argh(switch)]
+    /// format the bundled file with `rustfmt`, using the project's
+    /// `rustfmt.toml` (or the shipped one), instead of keeping the generated
+    /// layout
+    rustfmt: bool,
+
+    #[argh(switch)]
+    /// with `--minify`, also rename the bundled modules to short names
+    minify_mods: bool,
+
+    #[argh(switch)]
+    /// run build scripts of the bundled crates, inlining the files they
+    /// generate into `OUT_DIR` wherever these are `include!`d
+    run_build_script: bool,
+
+    #[argh(option)]
+    /// crate, vendored from crates.io into `crates`, to bundle like the library
+    /// crates, instead of keeping it as a dependency (can be repeated, adds to
+    /// the configured `inline_deps`)
+    inline_dep: Vec<String>,
+
+    #[argh(option)]
+    /// maximum number of compiler processes, verifying bundled files
+    /// (overrides the configured `jobs`)
+    jobs: Option<usize>,
+
+    #[argh(option)]
+    /// niceness of the processes, verifying bundled files, from -20 to 19
+    /// (overrides the configured `nice`)
+    nice: Option<i32>,
+
+    #[argh(switch)]
+    /// list the library modules, which would be bundled, along with the
+    /// reasons (e.g. a re-export, or a dependency of another module), without
+    /// writing any files
+    dry_run: bool,
+
+    #[argh(option)]
+    /// also write the bundled file to the given path (a directory gets
+    /// `<id>.rs` in it), e.g. a submission folder
+    output: Option<String>,
+
+    #[argh(switch)]
+    /// write metadata of the bundled file (included modules, with their sizes
+    /// and the reasons, resolved `pub use` aliases, output paths) into a JSON
+    /// file next to it
+    emit_metadata: bool,
+
+    #[argh(switch)]
+    /// copy the bundled file into the clipboard, regardless of the
+    /// `clipboard` setting
+    clipboard: bool,
 }
 
 impl SubCmd for BundleProblemSubCmd {
     fn run(&self) -> Result<()> {
-        let mut ctx = BundlerContext::new(&self.id).context(format!(
-            "failed to create bundler context for problem {}",
-            self.id
-        ))?;
+        let ids = select_ids(
+            Path::new("."),
+            self.id.as_deref(),
+            self.all,
+            self.changed,
+            Event::Bundled,
+        )?;
+        if ids.is_empty() {
+            println!("No problems to bundle.");
+            return Ok(());
+        }
+        if self.keep_docs && self.minify {
+            return Err(anyhow!(
+                "`--keep-docs` and `--minify` can't be used together"
+            ));
+        }
+        if self.rustfmt && self.minify {
+            return Err(anyhow!("`--rustfmt` and `--minify` can't be used together"));
+        }
+        if self.minify_mods && !self.minify {
+            return Err(anyhow!("`--minify-mods` requires `--minify`"));
+        }
+        if let Some(output) = &self.output
+            && ids.len() > 1
+            && !is_dir(output)
+        {
+            return Err(anyhow!(
+                "{} problems would be bundled into the same file {output:?}, the output should be \
+                 a directory",
+                ids.len()
+            ));
+        }
+        let jobs = Jobs::resolve(self.jobs, self.nice)?;
+
+        // All problems share the bundled project: it is prepared once, and its manifest
+        // lists external crates of every problem, not just the last bundled one.
+        let project = if self.dry_run {
+            BundledProject::new(Path::new("./bundled"))
+        } else {
+            BundledProject::prepare(Path::new("./bundled"))
+                .context("failed to prepare bundled project")?
+        };
+        let mut contexts = ids
+            .iter()
+            .map(|id| {
+                let ctx = self.options().and_then(|opts| {
+                    BundlerContext::new(id, &project, opts)
+                        .context(format!("failed to create bundler context for problem {id}"))
+                });
+                (id.as_str(), ctx)
+            })
+            .collect::<HashMap<_, _>>();
+        if !self.dry_run {
+            let mut external_crates = contexts
+                .values()
+                .flatten()
+                .flat_map(|ctx| ctx.external_crates.iter().cloned())
+                .collect::<Vec<_>>();
+            external_crates.sort();
+            external_crates.dedup();
+            let profile = JudgeProfile::resolve(
+                setting("judge")?.as_deref(),
+                setting("judge_profile")?.as_deref(),
+            )
+            .context("failed to resolve judge profile")?;
+            project
+                .write_manifest(&external_crates, &profile)
+                .context("failed to write bundled project manifest")?;
+        }
+
+        for_each_id(&ids, |id| {
+            let ctx = contexts
+                .remove(id)
+                .expect("bundler context is created for every problem")?;
+            self.bundle(ctx, &project, jobs)
+        })
+    }
+}
 
+impl BundleProblemSubCmd {
+    /// Bundling options, from the command line and the configured settings.
+    fn options(&self) -> Result<BundlerOptions> {
+        let header = self.header || setting("bundle_header")?.as_deref() == Some("on");
+        let author = match &self.author {
+            Some(author) => Some(author.clone()),
+            None if header => setting("handle")?,
+            None => None,
+        };
+        let mut inline_deps = self.inline_dep.clone();
+        inline_deps.extend(setting_list("inline_deps")?);
+        let mut keep_attrs = self.keep_attr.clone();
+        keep_attrs.extend(setting_list("keep_attrs")?);
+        if self.keep_docs {
+            keep_attrs.push("doc".to_string());
+        }
+        let mut drop_attrs = self.drop_attr.clone();
+        drop_attrs.extend(setting_list("drop_attrs")?);
+        Ok(BundlerOptions {
+            attr_policy: AttrPolicy::new(&keep_attrs, &drop_attrs),
+            header: header.then(|| Header {
+                url: self.url.clone(),
+                author,
+            }),
+            redactions: (self.redact || !self.redact_str.is_empty())
+                .then(|| Redactions::new(&self.redact_str)),
+            renames: Renames::new(&self.rename)?,
+            ascii_literals: self.ascii_literals,
+            only_crates: self.only_crate.clone(),
+            exclude_crates: self.exclude_crate.clone(),
+            keep_tests: self.keep_tests || self.with_tests,
+            with_tests: self.with_tests,
+            strip_debug: self.strip_debug,
+            deny_warnings: self.deny_warnings,
+            allow_crates: self.allow_crate.clone(),
+            prune_items: self.prune_items,
+            minify: self.minify,
+            minify_mods: self.minify_mods,
+            no_format: self.no_format,
+            run_build_script: self.run_build_script,
+            inline_deps,
+        })
+    }
+
+    /// Bundles a single problem into the (already prepared) bundled project.
+    fn bundle(&self, mut ctx: BundlerContext, project: &BundledProject, jobs: Jobs) -> Result<()> {
+        let id = ctx.problem_id.clone();
+        let id = id.as_str();
+        if self.dry_run {
+            let bundler = Bundler::new(&mut ctx)?.traverse_crates()?.parse_binary()?;
+            dry_run::print_modules(bundler.ctx);
+            return Ok(());
+        }
+
+        let started = Instant::now();
         Bundler::new(&mut ctx)?
             .traverse_crates()?
             .parse_binary()?
             .expand_mods()?
-            .complete_bundling()
+            .complete_bundling()?;
+        record_phase("bundle", started);
+
+        println!("{}", ctx.stats);
+
+        // Broken bundles never make it to the output path, or into the clipboard.
+        let check = match (self.check, self.check_release) {
+            (_, true) => Some(true),
+            (true, false) => Some(false),
+            _ => match setting("bundle_check")?.as_deref() {
+                Some("check") => Some(false),
+                Some("release") => Some(true),
+                _ => None,
+            },
+        };
+        if let Some(release) = check {
+            let started = Instant::now();
+            let checked = project.check(
+                id,
+                &ctx.source_map,
+                Path::new(&ctx.root_path),
+                release,
+                jobs,
+            );
+            record_phase("check", started);
+            if checked.is_err() {
+                record_failure(Failure::Bundle);
+            }
+            checked.context(format!("bundled problem {id} does not compile"))?;
+        }
+        // Formatting comes after the check, as the source map refers to the generated
+        // layout.
+        if self.rustfmt && !fmt::rustfmt(std::slice::from_ref(&ctx.dst), false)?.success() {
+            return Err(anyhow!("rustfmt failed to format bundled problem {id}"));
+        }
+        // The bundled project keeps its copy, so that it can still be checked and
+        // tested.
+        let output = self.output.as_deref().map(|output| {
+            if is_dir(output) {
+                Path::new(output).join(format!("{id}.rs"))
+            } else {
+                PathBuf::from(output)
+            }
+        });
+        if let Some(output) = &output {
+            if let Some(parent) = output.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(parent)
+                    .context(format!("failed to create {}", parent.display()))?;
+            }
+            let content = fs::read(&ctx.dst).context("failed to read bundled file")?;
+            write_atomic(output, content)
+                .context(format!("failed to write {}", output.display()))?;
+            println!("Bundled file written to {}", output.display());
+        }
+        if self.emit_metadata {
+            let path = metadata::write_metadata(&ctx, output.as_deref())
+                .context("failed to write bundle metadata")?;
+            println!("Bundle metadata written to {}", path.display());
+        }
+        update_events(Path::new("."), &[id], Event::Bundled);
+
+        // Copy the bundled file into the clipboard, if asked or configured to. Only
+        // an explicit request fails the command.
+        if self.clipboard || setting("clipboard")?.as_deref() == Some("copy") {
+            let content = fs::read_to_string(&ctx.dst).context("failed to read bundled file")?;
+            match clipboard::copy(&content) {
+                Ok(tool) => println!(
+                    "Bundled file copied into the clipboard (with {tool}): {} bytes, {} lines",
+                    content.len(),
+                    content.lines().count()
+                ),
+                Err(e) if self.clipboard => {
+                    return Err(e).context("failed to copy bundled file into the clipboard");
+                }
+                Err(e) => {
+                    println!("Warning: failed to copy bundled file into the clipboard: {e:#}")
+                }
+            }
+        }
+        Ok(())
     }
 }
 
+/// Whether the output path is a directory: an existing one, or one with a
+/// trailing separator.
+fn is_dir(output: &str) -> bool {
+    output.ends_with(['/', std::path::MAIN_SEPARATOR]) || Path::new(output).is_dir()
+}
+
 #[derive(Debug)]
 struct Bundler<'a, P: BunlingPhase = phases::TraverseCrates> {
     ctx: &'a mut BundlerContext,
@@ -47,3 +477,17 @@ impl<'a> Bundler<'a> {
         })
     }
 }
+
+/// Values of a comma-separated setting, e.g. `rand,itoa` of `inline_deps`.
+fn setting_list(key: &str) -> Result<Vec<String>> {
+    Ok(setting(key)?
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default())
+}
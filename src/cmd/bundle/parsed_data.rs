@@ -1,6 +1,7 @@
 use {
     std::{
-        collections::{HashMap, HashSet},
+        collections::{BTreeMap, HashMap, HashSet},
+        fmt,
         fs,
         path::{Path, PathBuf},
     },
@@ -29,6 +30,86 @@ pub struct ParsedPaths {
     /// final output. Otherwise, it will be removed (since its module is also
     /// omitted).
     pub_use_used: HashSet<String>,
+
+    /// Index of macros exported with `#[macro_export]`.
+    ///
+    /// Exported macros live at the crate root, regardless of the module they
+    /// are defined in. The key is the root-level path of the macro (e.g.
+    /// `algorist/wln`), and the value is the path of the defining module
+    /// (e.g. `algorist/io`), which must be retained whenever the macro is
+    /// invoked.
+    macro_exports: HashMap<String, String>,
+
+    /// Macros, invoked within library modules: the key is the module path, and
+    /// the values are root-level paths of the invoked macros. Exported macros,
+    /// invoked by retained modules, must be retained as well.
+    macro_invocations: HashMap<String, HashSet<String>>,
+
+    /// Paths, library modules refer to (e.g. `algorist/math/gcd` for `use
+    /// super::math::gcd` within `algorist/io`): the key is the module path.
+    /// Whatever retained modules refer to, must be retained as well.
+    module_deps: HashMap<String, HashSet<String>>,
+
+    /// Modules, imported with a glob (e.g. `algorist/graph` for `use
+    /// algorist::graph::*`): their whole subtrees are used, along with the
+    /// `pub use` declarations within them, see [`Self::insert_glob`].
+    globs: HashSet<String>,
+
+    /// Names, the solution mentions (in code and macro arguments alike).
+    solution_names: HashSet<String>,
+
+    /// Traits, defined in library modules (e.g. `algorist/io/ReadExt`).
+    traits: HashSet<String>,
+
+    /// Public names of library modules (items, and names re-exported with
+    /// `pub use`), by module path: the names glob re-exports bring in. Enum
+    /// variants are listed under the path of the enum.
+    module_items: HashMap<String, HashSet<String>>,
+
+    /// `pub use` declarations, re-exporting with a glob (e.g. `pub use
+    /// super::math::*` within `algorist/io`): the key is the alias (e.g.
+    /// `algorist/io/math`), and the value is the re-exported module (e.g.
+    /// `algorist/math`).
+    glob_reexports: HashMap<String, String>,
+
+    /// Why each of the paths is used (the first reason found is kept).
+    reasons: HashMap<String, Reason>,
+
+    /// Library modules, residing in files of their own (crate roots included),
+    /// with the sizes of the files in bytes.
+    modules: BTreeMap<String, usize>,
+}
+
+/// Why a path is used, i.e. why the module it refers to is bundled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Reason {
+    /// Solution refers to the path.
+    Used,
+
+    /// Solution imports the module, the path is within, with a glob.
+    Glob(String),
+
+    /// Path is re-exported (with `pub use`) under the given one.
+    ReExport(String),
+
+    /// Module defines the given exported macro.
+    Macro(String),
+
+    /// Given module (which is bundled) refers to the path.
+    Dependency(String),
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = |path: &str| path.replace('/', "::");
+        match self {
+            Reason::Used => write!(f, "used by the solution"),
+            Reason::Glob(module) => write!(f, "glob import of {}", path(module)),
+            Reason::ReExport(alias) => write!(f, "re-exported as {}", path(alias)),
+            Reason::Macro(name) => write!(f, "defines macro {}!", path(name)),
+            Reason::Dependency(module) => write!(f, "dependency of {}", path(module)),
+        }
+    }
 }
 
 impl ParsedPaths {
@@ -37,10 +118,25 @@ impl ParsedPaths {
             paths: HashSet::new(),
             pub_use_decls: HashMap::new(),
             pub_use_used: HashSet::new(),
+            macro_exports: HashMap::new(),
+            macro_invocations: HashMap::new(),
+            module_deps: HashMap::new(),
+            globs: HashSet::new(),
+            solution_names: HashSet::new(),
+            traits: HashSet::new(),
+            module_items: HashMap::new(),
+            glob_reexports: HashMap::new(),
+            reasons: HashMap::new(),
+            modules: BTreeMap::new(),
         }
     }
 
+    /// Register a path, the solution refers to.
     pub fn insert_path(&mut self, path: &str) {
+        self.insert_path_for(path, Reason::Used);
+    }
+
+    fn insert_path_for(&mut self, path: &str, reason: Reason) {
         println!("Registering path: {}", path);
         let segments = path
             .split('/')
@@ -62,24 +158,197 @@ impl ParsedPaths {
 
             let cur_path = path.clone();
             self.paths.insert(cur_path.clone());
+            self.reasons
+                .entry(cur_path.clone())
+                .or_insert_with(|| reason.clone());
 
             // See if the current path is an alias created with `pub use`
             if let Some(fully_qualified) = self.pub_use_decls.get(&cur_path) {
                 // If it is, we need to insert the fully qualified name as well, if it is not
                 // already inserted.
                 if !self.paths.contains(fully_qualified) {
-                    self.insert_path(&fully_qualified.clone());
+                    self.insert_path_for(
+                        &fully_qualified.clone(),
+                        Reason::ReExport(cur_path.clone()),
+                    );
                 }
                 // Mark item as used, so that its `pub use` declaration and the corresponding
                 // module will be included in the final output.
-                self.pub_use_used.insert(cur_path);
+                self.pub_use_used.insert(cur_path.clone());
+            }
+
+            // See if the current path is a macro exported at the crate root, and if so,
+            // make sure that the module it is defined in is retained.
+            if let Some(module) = self.macro_exports.get(&cur_path)
+                && !self.paths.contains(module)
+            {
+                self.insert_path_for(&module.clone(), Reason::Macro(cur_path.clone()));
+            }
+        }
+    }
+
+    /// Register a module, imported with a glob.
+    ///
+    /// Names, re-exported by the module itself (e.g. by a prelude), are only
+    /// used if the solution mentions them. Traits are used regardless, as
+    /// their methods are called without naming them.
+    pub fn insert_glob(&mut self, path: &str) {
+        self.insert_path_for(path, Reason::Used);
+        self.globs.insert(path.to_string());
+
+        // Names, re-exported within the module, may be defined elsewhere.
+        let targets = self
+            .pub_use_decls
+            .iter()
+            .filter(|(alias, _)| self.is_under_glob(alias))
+            .filter(|(alias, fully_qualified)| {
+                !self.is_glob_scoped(alias)
+                    || self.is_trait(fully_qualified)
+                    || alias
+                        .rsplit('/')
+                        .next()
+                        .is_some_and(|name| self.solution_names.contains(name))
+            })
+            .map(|(alias, fully_qualified)| (alias.clone(), fully_qualified.clone()))
+            .collect::<Vec<_>>();
+        for (alias, fully_qualified) in targets {
+            self.pub_use_used.insert(alias.clone());
+            if !self.paths.contains(&fully_qualified) {
+                self.insert_path_for(&fully_qualified, Reason::ReExport(alias));
             }
         }
     }
 
+    /// Register the names, the solution mentions, before its imports are
+    /// processed.
+    pub fn set_solution_names(&mut self, names: HashSet<String>) {
+        self.solution_names = names;
+    }
+
+    /// Register a trait, defined in a library module.
+    pub fn insert_trait(&mut self, path: &str) {
+        self.traits.insert(path.to_string());
+    }
+
+    /// Register a public name of a library module.
+    pub fn insert_module_item(&mut self, module: &str, name: &str) {
+        self.module_items
+            .entry(module.to_string())
+            .or_default()
+            .insert(name.to_string());
+    }
+
+    /// Register a `pub use` declaration, re-exporting the given module with a
+    /// glob.
+    pub fn insert_glob_reexport(&mut self, alias: &str, module: &str) {
+        self.glob_reexports
+            .insert(alias.to_string(), module.to_string());
+    }
+
+    /// Expand glob re-exports into `pub use` declarations of the names they
+    /// bring in (e.g. `algorist/io/gcd` for `algorist/math/gcd`), so that the
+    /// names are tracked as named re-exports are. Names, defined in the module
+    /// itself, shadow the re-exported ones.
+    ///
+    /// Repeated until no more names are added, as re-exported modules may
+    /// re-export with globs themselves. Returns the glob re-exports of unknown
+    /// modules (e.g. of other crates), by alias.
+    pub fn expand_glob_reexports(&mut self) -> BTreeMap<&str, &str> {
+        loop {
+            let mut expanded = false;
+            for (alias, module) in self.glob_reexports.clone() {
+                let (Some((scope, _)), Some(names)) =
+                    (alias.rsplit_once('/'), self.module_items.get(&module))
+                else {
+                    continue;
+                };
+                let names = names
+                    .iter()
+                    .filter(|name| {
+                        self.module_items
+                            .get(scope)
+                            .is_none_or(|defined| !defined.contains(*name))
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                for name in names {
+                    self.pub_use_decls
+                        .insert(format!("{scope}/{name}"), format!("{module}/{name}"));
+                    self.module_items
+                        .entry(scope.to_string())
+                        .or_default()
+                        .insert(name);
+                    expanded = true;
+                }
+            }
+            if !expanded {
+                break;
+            }
+        }
+        self.glob_reexports
+            .iter()
+            .filter(|(_, module)| !self.module_items.contains_key(*module))
+            .map(|(alias, module)| (alias.as_str(), module.as_str()))
+            .collect()
+    }
+
+    /// Whether the path refers to a trait, directly or through re-exports.
+    fn is_trait(&self, path: &str) -> bool {
+        let mut path = path;
+        for _ in 0..=self.pub_use_decls.len() {
+            if self.traits.contains(path) {
+                return true;
+            }
+            match self.pub_use_decls.get(path) {
+                Some(fully_qualified) => path = fully_qualified,
+                None => break,
+            }
+        }
+        false
+    }
+
+    /// Whether the path is directly within a module, imported with a glob, i.e.
+    /// the glob brings it into the scope of the solution.
+    fn is_glob_scoped(&self, path: &str) -> bool {
+        path.rsplit_once('/')
+            .is_some_and(|(module, _)| self.globs.contains(module))
+    }
+
+    /// Why the path is used, if it is.
+    pub fn reason(&self, path: &str) -> Option<Reason> {
+        self.reasons
+            .get(path)
+            .cloned()
+            .or_else(|| Some(Reason::Glob(self.glob_of(path)?.clone())))
+    }
+
+    /// Register a library module, residing in a file of its own.
+    pub fn insert_module(&mut self, path: &str, size: usize) {
+        self.modules.insert(path.to_string(), size);
+    }
+
+    /// Library modules, residing in files of their own, with the sizes of the
+    /// files, ordered by path.
+    pub fn modules(&self) -> &BTreeMap<String, usize> {
+        &self.modules
+    }
+
     /// Check if path is contained in the set of used modules.
     pub fn contains_path(&self, other: &str) -> bool {
-        self.paths.contains(other)
+        self.paths.contains(other) || self.is_under_glob(other)
+    }
+
+    /// Whether the path is within a module, imported with a glob.
+    fn is_under_glob(&self, path: &str) -> bool {
+        self.glob_of(path).is_some()
+    }
+
+    /// Module, imported with a glob, the path is within (if any).
+    fn glob_of(&self, path: &str) -> Option<&String> {
+        self.globs.iter().find(|glob| {
+            path.strip_prefix(glob.as_str())
+                .is_some_and(|rest| rest.starts_with('/'))
+        })
     }
 
     /// Insert a `pub use` declaration into the set of used modules.
@@ -88,9 +357,125 @@ impl ParsedPaths {
             .insert(alias.to_string(), fully_qualified.to_string());
     }
 
+    /// `pub use` aliases, used in the binary file, mapped to the paths they
+    /// resolve to, ordered by alias.
+    pub fn used_aliases(&self) -> BTreeMap<&str, &str> {
+        self.pub_use_decls
+            .iter()
+            .filter(|(alias, _)| self.is_pub_use_used(alias))
+            .map(|(alias, fully_qualified)| (alias.as_str(), fully_qualified.as_str()))
+            .collect()
+    }
+
     /// Whether the `pub use` declaration used in the binary file.
+    ///
+    /// Glob re-exports are used, if any of the names they bring in is. Those
+    /// of unknown modules are retained, whatever the names are.
     pub fn is_pub_use_used(&self, alias: &str) -> bool {
+        let Some(module) = self.glob_reexports.get(alias) else {
+            return self.is_alias_used(alias);
+        };
+        let (Some((scope, _)), Some(names)) =
+            (alias.rsplit_once('/'), self.module_items.get(module))
+        else {
+            return true;
+        };
+        names.iter().any(|name| {
+            let reexport = format!("{scope}/{name}");
+            self.pub_use_decls.get(&reexport) == Some(&format!("{module}/{name}"))
+                && self.is_alias_used(&reexport)
+        })
+    }
+
+    fn is_alias_used(&self, alias: &str) -> bool {
         self.pub_use_used.contains(alias)
+            || (self.is_under_glob(alias) && !self.is_glob_scoped(alias))
+    }
+
+    /// Register a macro exported with `#[macro_export]`, along with the module
+    /// it is defined in.
+    pub fn insert_macro_export(&mut self, root_path: &str, module: &str) {
+        self.macro_exports
+            .insert(root_path.to_string(), module.to_string());
+    }
+
+    /// Names of macros exported by the given crate, which are defined in the
+    /// retained modules (and thus are available in the output file).
+    pub fn retained_macro_exports(&self, crate_name: &str) -> Vec<String> {
+        let mut names = self
+            .macro_exports
+            .iter()
+            .filter(|(_, module)| self.contains_path(module))
+            .filter_map(|(root_path, _)| {
+                root_path
+                    .strip_prefix(crate_name)
+                    .and_then(|name| name.strip_prefix('/'))
+                    .map(String::from)
+            })
+            .collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Register a macro invocation within a library module.
+    pub fn insert_macro_invocation(&mut self, module: &str, root_path: &str) {
+        self.macro_invocations
+            .entry(module.to_string())
+            .or_default()
+            .insert(root_path.to_string());
+    }
+
+    /// Register a path, a library module refers to.
+    pub fn insert_module_dep(&mut self, module: &str, path: &str) {
+        self.module_deps
+            .entry(module.to_string())
+            .or_default()
+            .insert(path.to_string());
+    }
+
+    /// Retain whatever the retained modules refer to: other modules (e.g. with
+    /// `use super::other::foo`), and modules defining the invoked exported
+    /// macros. Repeated until no more modules are added, as newly retained
+    /// modules may refer to yet other ones.
+    pub fn include_dependencies(&mut self) {
+        loop {
+            let macros = self
+                .macro_invocations
+                .iter()
+                .filter(|(module, _)| self.contains_path(module))
+                .flat_map(|(module, root_paths)| root_paths.iter().map(move |path| (module, path)))
+                .filter(|(_, root_path)| {
+                    self.macro_exports
+                        .get(*root_path)
+                        .is_some_and(|module| !self.contains_path(module))
+                });
+            let deps = self
+                .module_deps
+                .iter()
+                .filter(|(module, _)| self.contains_path(module))
+                .flat_map(|(module, paths)| paths.iter().map(move |path| (module, path)))
+                // Re-exports, the solution doesn't use via a glob, may still be used by
+                // the modules.
+                .filter(|(_, path)| {
+                    !self.contains_path(path)
+                        || (self.pub_use_decls.contains_key(*path) && !self.is_pub_use_used(path))
+                });
+            let pending = macros
+                .chain(deps)
+                .map(|(module, path)| (module.clone(), path.clone()))
+                .collect::<Vec<_>>();
+            if pending.is_empty() {
+                break;
+            }
+            for (module, path) in pending {
+                self.insert_path_for(&path, Reason::Dependency(module));
+            }
+        }
+    }
+
+    /// Whether the given root-level path refers to an exported macro.
+    pub fn is_macro_export(&self, root_path: &str) -> bool {
+        self.macro_exports.contains_key(root_path)
     }
 }
 
@@ -105,6 +490,9 @@ impl Crates {
     /// Normally, this directory is `crates` in the project root.
     pub fn new(crates_dir: &Path) -> std::io::Result<Crates> {
         let mut crates = Self(HashMap::new());
+        if !crates_dir.exists() {
+            return Ok(crates);
+        }
         for entry in fs::read_dir(crates_dir)? {
             let entry = entry?;
             let path = entry.path();
@@ -112,14 +500,13 @@ impl Crates {
                 let cargo_toml = path.join("Cargo.toml");
                 if cargo_toml.exists() {
                     let content = fs::read_to_string(cargo_toml)?;
-                    if let Ok(value) = content.parse::<Value>() {
-                        if let Some(name) = value
+                    if let Ok(value) = content.parse::<Value>()
+                        && let Some(name) = value
                             .get("package")
                             .and_then(|pkg| pkg.get("name"))
                             .and_then(|n| n.as_str())
-                        {
-                            crates.push(name, path);
-                        }
+                    {
+                        crates.push(name, path);
                     }
                 }
             }
@@ -135,6 +522,14 @@ impl Crates {
         self.0.contains_key(name)
     }
 
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.0.keys()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<PathBuf> {
+        self.0.remove(name)
+    }
+
     pub fn path(&self, name: &str) -> Option<&PathBuf> {
         self.0.get(name)
     }
@@ -142,4 +537,98 @@ impl Crates {
     pub fn into_iter(self) -> impl Iterator<Item = (String, PathBuf)> {
         self.0.into_iter()
     }
+
+    /// Whether the crate is vendored from a registry (e.g. crates.io), rather
+    /// than copied from a local path: `cargo vendor` only records the package
+    /// checksum for the former.
+    pub fn is_vendored(&self, name: &str) -> bool {
+        self.path(name)
+            .and_then(|path| fs::read_to_string(path.join(".cargo-checksum.json")).ok())
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+            .is_some_and(|value| {
+                value
+                    .get("package")
+                    .is_some_and(|package| !package.is_null())
+            })
+    }
+
+    /// Given crates, along with the project crates they depend on (directly or
+    /// transitively).
+    pub fn with_dependencies<'a>(
+        &self,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> HashSet<String> {
+        let mut found = HashSet::new();
+        let mut queue = names
+            .into_iter()
+            .filter(|name| self.contains(name))
+            .map(String::from)
+            .collect::<Vec<_>>();
+        while let Some(name) = queue.pop() {
+            if found.insert(name.clone()) {
+                queue.extend(self.dependencies(&name).into_values());
+            }
+        }
+        found
+    }
+
+    /// Project crates, the given crate depends on, as declared in its
+    /// `Cargo.toml`: names the crate refers to them by (which differ for
+    /// renamed dependencies), mapped to the crate names.
+    pub fn dependencies(&self, name: &str) -> HashMap<String, String> {
+        let Some(deps) = self
+            .path(name)
+            .and_then(|path| fs::read_to_string(path.join("Cargo.toml")).ok())
+            .and_then(|content| content.parse::<Value>().ok())
+            .and_then(|value| value.get("dependencies")?.as_table().cloned())
+        else {
+            return HashMap::new();
+        };
+        deps.into_iter()
+            .filter_map(|(key, dep)| {
+                let package = dep
+                    .get("package")
+                    .and_then(|package| package.as_str())
+                    .unwrap_or(&key)
+                    .replace('-', "_");
+                self.contains(&package)
+                    .then(|| (key.replace('-', "_"), package))
+            })
+            .collect()
+    }
+
+    /// Crates, ordered so that each one comes after the crates it depends on
+    /// (crates with no dependencies between them are ordered by name).
+    pub fn topological_order(&self) -> Vec<(String, PathBuf)> {
+        fn visit(
+            crates: &Crates,
+            name: &str,
+            visited: &mut HashSet<String>,
+            order: &mut Vec<String>,
+        ) {
+            if !visited.insert(name.to_string()) {
+                return;
+            }
+            let mut deps = crates.dependencies(name).into_values().collect::<Vec<_>>();
+            deps.sort();
+            for dep in deps {
+                visit(crates, &dep, visited, order);
+            }
+            order.push(name.to_string());
+        }
+
+        let mut names = self.0.keys().collect::<Vec<_>>();
+        names.sort();
+        let (mut visited, mut order) = (HashSet::new(), Vec::new());
+        for name in names {
+            visit(self, name, &mut visited, &mut order);
+        }
+        order
+            .into_iter()
+            .map(|name| {
+                let path = self.0[&name].clone();
+                (name, path)
+            })
+            .collect()
+    }
 }
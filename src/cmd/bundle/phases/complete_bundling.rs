@@ -1,8 +1,22 @@
 use {
-    crate::cmd::bundle::{Bundler, phases::BunlingPhase},
-    anyhow::Result,
+    crate::cmd::{
+        bundle::{
+            Bundler,
+            minify::minify,
+            phases::{BunlingPhase, utils::normalize_line_endings},
+            source_map::SourceMap,
+            verify::external_references,
+            warnings::Lint,
+        },
+        write_atomic,
+    },
+    anyhow::{Context, Result, anyhow},
+    std::{collections::HashSet, time::Instant},
 };
 
+/// Source size limit of most judges (e.g. Codeforces), in bytes.
+const SIZE_LIMIT: usize = 64 * 1024;
+
 /// Marks the end of the bundling process.
 pub struct CompleteBundling;
 
@@ -10,6 +24,78 @@ impl BunlingPhase for CompleteBundling {}
 
 impl<'a> Bundler<'a, CompleteBundling> {
     pub fn complete_bundling(self) -> Result<()> {
+        let started = Instant::now();
+        let content = String::from_utf8(std::mem::take(&mut self.ctx.out))
+            .context("bundled output is not valid UTF-8")?;
+
+        // Make sure that the output is LF-only, regardless of the inputs.
+        let mut content = normalize_line_endings(&content);
+
+        // Redact personal identifiers, if requested.
+        if let Some(redactions) = &self.ctx.opts.redactions {
+            content = redactions.apply(&self.ctx.root_path, &content);
+            // Replacements may still break literals, e.g. with a `"` in them.
+            syn::parse_file(&content).context(
+                "bundled file is not valid Rust after redaction, check the `--redact-str` \
+                 replacements",
+            )?;
+        }
+
+        // Minified lines no longer correspond to the ones of the source map.
+        if self.ctx.opts.minify {
+            let size = content.len();
+            content = minify(&content, self.ctx.opts.minify_mods)?;
+            self.ctx.source_map = SourceMap::default();
+            println!("Minified: {size} -> {} bytes", content.len());
+        }
+
+        if content.len() > SIZE_LIMIT {
+            self.ctx.warnings.push(
+                Lint::SizeLimit,
+                format!(
+                    "bundled file is {} bytes, most judges accept up to {SIZE_LIMIT} bytes",
+                    content.len()
+                ),
+            );
+        }
+
+        // Judges compile the file on its own, make sure nothing external leaked into
+        // it. Names, defined by macros, are not seen: hence a warning, not an error.
+        let file = syn::parse_file(&content).context("bundled file is not valid Rust")?;
+        let allowed = self
+            .ctx
+            .external_crates
+            .iter()
+            .map(|(name, _)| name.clone())
+            .chain(
+                self.ctx
+                    .opts
+                    .allow_crates
+                    .iter()
+                    .map(|name| name.replace('-', "_")),
+            )
+            .collect::<HashSet<_>>();
+        for reference in external_references(&file, &allowed) {
+            self.ctx.warnings.push(
+                Lint::ExternalReference,
+                format!("{reference} (use `--allow-crate` for crates available on the judge)"),
+            );
+        }
+
+        if !self.ctx.warnings.is_empty() {
+            print!("{}", self.ctx.warnings);
+            if self.ctx.opts.deny_warnings {
+                return Err(anyhow!(
+                    "{} warning(s) emitted, and `--deny-warnings` is set",
+                    self.ctx.warnings.len()
+                ));
+            }
+        }
+
+        write_atomic(&self.ctx.dst, content).context("failed to write output file")?;
+
+        self.ctx.stats.record("complete bundling", started, 0);
+
         println!(
             "Problem {:?} bundled successfully into {:?}",
             self.ctx.problem_id, self.ctx.dst
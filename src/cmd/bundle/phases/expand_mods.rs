@@ -1,25 +1,36 @@
 use {
-    crate::cmd::bundle::{
-        Bundler,
-        context::BundlerContext,
-        phases::{
-            self,
-            BunlingPhase,
-            utils::{
-                extract_imported_paths,
-                flatten_imported_paths,
-                is_pub_use,
-                is_test_module,
-                load_mod,
-                tranform_alias_and_fqn,
+    crate::cmd::{
+        bundle::{
+            Bundler,
+            build_script::{BuildOutput, OutDirInliner, has_build_script},
+            context::BundlerContext,
+            crate_paths::rewrite_crate_paths,
+            escape::escape_non_ascii_literals,
+            include::inline_includes,
+            phases::{
+                self,
+                BunlingPhase,
+                utils::{
+                    extract_imported_names,
+                    flatten_imported_paths,
+                    is_pub_use,
+                    is_test_module,
+                    load_mod,
+                    parse_source,
+                    read_source,
+                    render,
+                    tranform_alias_and_fqn,
+                },
             },
+            prune::ItemPruner,
+            source_map::SourceMap,
+            warnings::Lint,
         },
+        cfg::is_test_only,
     },
-    anyhow::{Context, Result},
-    prettyplease::unparse,
-    regex::Regex,
-    std::{fs, io::Write, path::PathBuf},
-    syn::{parse_file, parse_quote, visit_mut::VisitMut},
+    anyhow::{Context, Result, anyhow},
+    std::{path::PathBuf, time::Instant},
+    syn::{parse_quote, visit_mut::VisitMut},
     tap::Tap,
 };
 
@@ -30,16 +41,35 @@ pub struct ExpandMods {
     pub crate_name: String,
     pub path: PathBuf,
     pub import_path: String,
+
+    /// Path of the module being visited: the module of the file, or an inline
+    /// module within it.
+    pub scope: String,
+
+    /// Source file being processed.
+    pub file: PathBuf,
+
+    /// Number of source files read.
+    pub files: usize,
 }
 
 impl BunlingPhase for ExpandMods {}
 
 impl<'a> Bundler<'a, ExpandMods> {
-    pub fn expand_mods(mut self) -> Result<Bundler<'a, phases::CompleteBundling>> {
+    pub fn expand_mods(self) -> Result<Bundler<'a, phases::CompleteBundling>> {
+        let started = Instant::now();
+        let mut files = 0;
+
+        for (crate_name, _) in &self.ctx.external_crates {
+            println!("Keeping crate as a dependency: {crate_name}");
+        }
+
         // For all crates in `crates` directory, we need to check if they are used in
         // the binary, and if so, process their library files.
-        let crates = self.ctx.crates.clone();
-        for (crate_name, crate_path) in crates.into_iter() {
+        // Crates are bundled after the crates they depend on.
+        let crates = self.ctx.crates.topological_order();
+        let mut crate_files = Vec::new();
+        for (crate_name, crate_path) in crates {
             if !self.ctx.used_paths.contains_path(&crate_name) {
                 println!("Ignoring unused crate: {crate_name}");
                 continue;
@@ -50,16 +80,44 @@ impl<'a> Bundler<'a, ExpandMods> {
                 crate_path.display()
             );
 
-            let file_content = match fs::read_to_string(crate_path.join("src/lib.rs")) {
+            // Effects of build scripts (generated files, `cfg`s) are lost once the crate
+            // is bundled, unless the script is run and its outputs inlined.
+            let build_output = if has_build_script(&crate_path) {
+                if !self.ctx.opts.run_build_script {
+                    return Err(anyhow!(
+                        "crate {crate_name:?} has a build script, which is not run for the \
+                         bundled file: use `--run-build-script` to run it and inline the files it \
+                         generates, or `--exclude-crate {crate_name}` to keep the crate as a \
+                         dependency"
+                    ));
+                }
+                Some(BuildOutput::run(&crate_path).context(format!(
+                    "failed to run build script of crate {crate_name:?}"
+                ))?)
+            } else {
+                None
+            };
+
+            let lib_path = crate_path.join("src/lib.rs");
+            let file_content = match read_source(&lib_path, &mut self.ctx.warnings) {
                 Ok(content) => content,
                 Err(_) => {
-                    println!("Library file for crate {crate_name:?} not found, skipping.");
+                    self.ctx.warnings.push(
+                        Lint::MissingModule,
+                        format!("library file for crate {crate_name:?} is not found, skipping"),
+                    );
                     continue;
                 }
             };
-            let mut ast = parse_file(&file_content).context("failed to parse library file")?;
+            let mut ast = parse_source(&file_content, &lib_path).context(format!(
+                "failed to parse library file for crate {crate_name}"
+            ))?;
+            let source_map = (!self.ctx.opts.no_format).then_some(&mut self.ctx.source_map);
+            let included = inline_includes(&mut ast, &lib_path, source_map).context(format!(
+                "failed to inline files, included by crate {crate_name}"
+            ))?;
 
-            FileProcessor {
+            let mut processor = FileProcessor {
                 ctx: self.ctx,
                 state: ExpandMods {
                     crate_name: crate_name.clone(),
@@ -68,19 +126,75 @@ impl<'a> Bundler<'a, ExpandMods> {
                         .canonicalize()
                         .context("failed to canonicalize src path")?,
                     import_path: crate_name.clone(),
+                    scope: crate_name.clone(),
+                    file: lib_path,
+                    files: 1 + included,
                 },
+                error: None,
+            };
+            processor.visit_file_mut(&mut ast);
+            if let Some(err) = processor.error {
+                return Err(err);
+            }
+            files += processor.state.files;
+
+            if let Some(output) = &build_output {
+                let mut inliner = OutDirInliner::new(&output.out_dir);
+                inliner.visit_file_mut(&mut ast);
+                if !inliner.errors.is_empty() {
+                    return Err(anyhow!(
+                        "failed to inline build script outputs of crate {crate_name:?}:\n  {}",
+                        inliner.errors.join("\n  ")
+                    ));
+                }
+                println!("Inlined {} generated file(s)", inliner.inlined);
+                for (kind, names) in [("cfg", &output.cfgs), ("environment variable", &output.env)]
+                {
+                    for name in names {
+                        self.ctx.warnings.push(
+                            Lint::BuildScript,
+                            format!(
+                                "build script of crate {crate_name:?} sets {kind} `{name}`, which \
+                                 is not set for the bundled file"
+                            ),
+                        );
+                    }
+                }
             }
-            .visit_file_mut(&mut ast);
+
+            // Exported macros end up at the root of the output file, re-export them
+            // from the crate module, so that `crate_name::macro_name!` paths keep working.
+            let mut items = std::mem::take(&mut ast.items);
+            for name in self.ctx.used_paths.retained_macro_exports(&crate_name) {
+                let ident = syn::Ident::new(&name, proc_macro2::Span::call_site());
+                items.push(parse_quote!(pub use super::#ident;));
+            }
+
+            // Crate-level attributes (e.g. `#![no_std]`) make no sense for a module, only
+            // lint levels (and docs, if retained) are carried over to it.
+            let mut attrs: Vec<syn::Attribute> = vec![
+                parse_quote!(#[allow(dead_code)]),
+                parse_quote!(#[allow(unused_imports)]),
+                parse_quote!(#[allow(unused_macros)]),
+            ];
+            attrs.extend(
+                std::mem::take(&mut ast.attrs)
+                    .into_iter()
+                    .filter(|attr| {
+                        ["allow", "warn", "deny", "forbid", "expect", "doc"]
+                            .iter()
+                            .any(|lint| attr.path().is_ident(lint))
+                    })
+                    .map(|mut attr| {
+                        attr.style = syn::AttrStyle::Outer;
+                        attr
+                    }),
+            );
 
             // Wrap the items within crate into the main module name.
-            let items = std::mem::take(&mut ast.items);
             let mod_item = syn::Item::Mod(syn::ItemMod {
                 unsafety: None,
-                attrs: vec![
-                    parse_quote!(#[allow(dead_code)]),
-                    parse_quote!(#[allow(unused_imports)]),
-                    parse_quote!(#[allow(unused_macros)]),
-                ],
+                attrs,
                 vis: syn::Visibility::Inherited,
                 mod_token: Default::default(),
                 ident: syn::Ident::new(&crate_name, proc_macro2::Span::call_site()),
@@ -89,30 +203,52 @@ impl<'a> Bundler<'a, ExpandMods> {
             });
             ast.items = vec![mod_item];
 
-            // Write the modified AST back to the output file.
-            let content = self
-                .post_process_output_string(&crate_name, unparse(&ast))
-                .context("failed to unparse and post-process output string")?;
-            writeln!(self.ctx.out, "{}", content).context("failed to write bundled file")?;
+            crate_files.push((crate_name, ast));
         }
 
+        // Items can only be pruned once all crates are expanded, as crates may refer to
+        // each other.
+        if self.ctx.opts.prune_items {
+            let mut pruner = ItemPruner::new(&self.ctx.used_idents);
+            pruner.prune(
+                &mut crate_files
+                    .iter_mut()
+                    .map(|(_, ast)| ast)
+                    .collect::<Vec<_>>(),
+            );
+            println!("Pruned {} unreachable library item(s)", pruner.removed);
+        }
+
+        for (crate_name, mut ast) in crate_files {
+            // Rename problematic identifiers, if requested.
+            self.ctx
+                .opts
+                .renames
+                .apply(&mut ast, false, &mut self.ctx.warnings);
+            if self.ctx.opts.ascii_literals {
+                escape_non_ascii_literals(&mut ast);
+            }
+
+            // Crates are bundled as modules, within the binary file, so paths rooted at
+            // the crate (or at the project crates it depends on) must go through the
+            // crate modules.
+            let deps = self.ctx.crates.dependencies(&crate_name);
+            rewrite_crate_paths(&mut ast, &crate_name, &deps);
+
+            // Write the modified AST straight into the output, without intermediate
+            // copies (these add up for large libraries).
+            self.ctx
+                .source_map
+                .emit(&mut self.ctx.out, &render(&ast, !self.ctx.opts.no_format));
+        }
+
+        self.ctx.stats.record("expand mods", started, files);
+
         Ok(Bundler {
             ctx: self.ctx,
             state: phases::CompleteBundling {},
         })
     }
-
-    fn post_process_output_string(&mut self, crate_name: &str, content: String) -> Result<String> {
-        // Replace `crate::` with `crate::{self.state.crate_name}::` in use paths.
-        // Basically you just inject `{self.state.crate_name}::` after `crate::`.
-        //
-        // The reason is that we bundle crates as modules, within the binary file,
-        // so we need to adjust the paths accordingly.
-        let re = Regex::new(r"crate::\b").unwrap();
-        let new_content = re.replace_all(&content, format!("crate::{}::", crate_name));
-
-        Ok(new_content.into_owned())
-    }
 }
 
 impl ExpandMods {
@@ -123,37 +259,19 @@ impl ExpandMods {
 
         for item in items.drain(..) {
             match &item {
-                syn::Item::Mod(item) => {
-                    // Only retain modules that are used in the binary.
-                    // Remove test modules.
-                    if is_test_module(item) || !self.is_used_in_binary(ctx, item) {
-                        // Skip test modules.
-                        continue;
-                    }
+                // Only retain modules that are used in the binary.
+                // Remove test modules, unless requested otherwise.
+                syn::Item::Mod(item)
+                    if (is_test_module(item) && !ctx.opts.with_tests)
+                        || (!is_test_module(item) && !self.is_used_in_binary(ctx, item)) =>
+                {
+                    continue;
                 }
-                syn::Item::Use(item) => {
-                    // Transform `pub use` declarations: only retain those that are used in the
-                    // binary (and thus are available in the output file).
-                    if is_pub_use(item) {
-                        // Expand group into individual uses
-                        let use_items = flatten_imported_paths(&item.tree, vec![]);
-
-                        // Filter out unused `pub use` declarations.
-                        for use_item in use_items {
-                            if let Some(path) =
-                                extract_imported_paths(&use_item.tree, Vec::new()).first()
-                            {
-                                let alias =
-                                    path.last().expect("Path must have at least one segment");
-                                let (alias, _fully_qualified) =
-                                    tranform_alias_and_fqn(alias, &self.import_path, &path);
-                                if ctx.used_paths.is_pub_use_used(&alias) {
-                                    new_items.push(syn::Item::Use(use_item));
-                                }
-                            }
-                        }
-                        continue;
-                    }
+                // Transform `pub use` declarations: only retain those that are used in the
+                // binary (and thus are available in the output file).
+                syn::Item::Use(item) if is_pub_use(item) => {
+                    new_items.extend(self.used_reexports(ctx, item).map(syn::Item::Use));
+                    continue;
                 }
                 _ => {}
             }
@@ -162,46 +280,91 @@ impl ExpandMods {
         *items = new_items;
     }
 
-    fn expand_mod(&mut self, ctx: &mut BundlerContext, node: &mut syn::ItemMod) {
+    /// Only retain `pub use` declarations of an inline module, that are used in
+    /// the binary. Other items are retained along with the module.
+    fn filter_inline_items(&mut self, ctx: &mut BundlerContext, items: &mut Vec<syn::Item>) {
+        *items = items
+            .drain(..)
+            .flat_map(|item| match &item {
+                syn::Item::Use(use_item) if is_pub_use(use_item) => self
+                    .used_reexports(ctx, use_item)
+                    .map(syn::Item::Use)
+                    .collect(),
+                _ => vec![item],
+            })
+            .collect();
+    }
+
+    /// `pub use` declarations of the (possibly grouped) declaration, that are
+    /// used in the binary, one per imported name.
+    fn used_reexports<'b>(
+        &'b self,
+        ctx: &'b BundlerContext,
+        item: &syn::ItemUse,
+    ) -> impl Iterator<Item = syn::ItemUse> + 'b {
+        flatten_imported_paths(&item.tree, vec![])
+            .into_iter()
+            .filter(|use_item| {
+                extract_imported_names(&use_item.tree, Vec::new())
+                    .first()
+                    .is_some_and(|(path, alias)| {
+                        let (alias, _) = tranform_alias_and_fqn(alias, &self.scope, path);
+                        ctx.used_paths.is_pub_use_used(&alias)
+                    })
+            })
+    }
+
+    fn expand_mod(&mut self, ctx: &mut BundlerContext, node: &mut syn::ItemMod) -> Result<()> {
         // If the module has content, we don't need to do anything.
         if node.content.is_some() {
-            return;
+            return Ok(());
         }
 
+        // Missing modules are reported when traversing crates.
         let mod_name = node.ident.to_string();
-        let (base_path, code) = load_mod(&self.path, &mod_name).expect("Failed to load module");
-
-        let mut ast = parse_file(&code).expect("Failed to parse module file");
-
-        let crate_src_path = ctx
-            .crates
-            .path(&self.crate_name)
-            .expect("crate path not found")
-            .join("src");
-        let import_path = base_path
-            .display()
-            .to_string()
-            .replace(&ctx.root_path, "")
-            .replace(
-                crate_src_path
-                    .to_str()
-                    .expect("failed to convert crate source path"),
-                &self.crate_name,
-            )
-            .trim_start_matches('/')
-            .to_string();
-        FileProcessor {
+        let Ok((base_path, file, code)) = load_mod(&self.path, node, &mut ctx.warnings) else {
+            return Ok(());
+        };
+
+        let mut ast = parse_source(&code, &file).context(format!(
+            "failed to parse module `{mod_name}` of {}",
+            self.import_path
+        ))?;
+        let source_map = (!ctx.opts.no_format).then_some(&mut ctx.source_map);
+        let included = inline_includes(&mut ast, &file, source_map).context(format!(
+            "failed to inline files, included by module `{mod_name}` of {}",
+            self.import_path
+        ))?;
+
+        // Import path is derived from the declaration, rather than the file location:
+        // files of `#[path]` modules may be located anywhere, and a `foo.rs` file
+        // (e.g. `prelude.rs`) is in the directory of its parent module.
+        let import_path = format!("{}/{mod_name}", self.import_path);
+        let mut processor = FileProcessor {
             ctx,
             state: ExpandMods {
                 crate_name: self.crate_name.clone(),
                 path: base_path,
+                scope: import_path.clone(),
                 import_path,
+                file,
+                files: 1 + included,
             },
+            error: None,
+        };
+        processor.visit_file_mut(&mut ast);
+        if let Some(err) = processor.error {
+            return Err(err);
         }
-        .visit_file_mut(&mut ast);
+        self.files += processor.state.files;
 
-        // Populate the module content with the parsed items.
+        // Populate the module content with the parsed items, and the retained inner
+        // attributes of the file (e.g. `//!` docs). The `#[path]` attribute is no
+        // longer meaningful for an inline module.
         node.content = Some((Default::default(), ast.items));
+        node.attrs.retain(|attr| !attr.path().is_ident("path"));
+        node.attrs.extend(ast.attrs);
+        Ok(())
     }
 
     fn is_used_in_binary(&self, ctx: &BundlerContext, node: &syn::ItemMod) -> bool {
@@ -209,7 +372,7 @@ impl ExpandMods {
         let mod_name = if self.import_path.is_empty() {
             node.ident.to_string()
         } else {
-            format!("{}/{}", self.import_path, node.ident.to_string())
+            format!("{}/{}", self.import_path, node.ident)
         };
 
         ctx.used_paths.contains_path(&mod_name).tap(|&res| {
@@ -225,11 +388,14 @@ impl ExpandMods {
 struct FileProcessor<'a> {
     ctx: &'a mut BundlerContext,
     state: ExpandMods,
+
+    /// First error, the expansion stopped at (visitors can't return errors).
+    error: Option<anyhow::Error>,
 }
 
 impl<'a> FileProcessor<'a> {}
 
-impl<'a> VisitMut for FileProcessor<'_> {
+impl VisitMut for FileProcessor<'_> {
     fn visit_file_mut(&mut self, file: &mut syn::File) {
         self.visit_attributes_mut(&mut file.attrs);
 
@@ -238,19 +404,42 @@ impl<'a> VisitMut for FileProcessor<'_> {
         for it in &mut file.items {
             self.visit_item_mut(it);
         }
+
+        // Only items, that made it into the output, are marked (unless the output is
+        // not formatted, as the items are not on lines of their own then).
+        if !self.ctx.opts.no_format {
+            self.ctx
+                .source_map
+                .mark_items(&mut file.items, &self.state.file);
+        }
     }
 
     fn visit_attributes_mut(&mut self, attrs: &mut Vec<syn::Attribute>) {
         // Drop all attributes that are not relevant for bundling.
-        *attrs = attrs
-            .drain(..)
-            .filter(|attr| {
-                !attr.path().is_ident("doc")
-                    && !attr.path().is_ident("allow")
-                    && !attr.path().is_ident("cfg")
-                    && !attr.path().is_ident("warn")
-            })
-            .collect();
+        let policy = &self.ctx.opts.attr_policy;
+        let with_tests = self.ctx.opts.with_tests;
+        let warnings = &mut self.ctx.warnings;
+        attrs.retain(|attr| {
+            if SourceMap::is_marker(attr) {
+                return true;
+            }
+            // Test-only `cfg`s keep the retained tests out of the solution build.
+            let retained =
+                policy.retains(attr) || (with_tests && is_test_only(std::slice::from_ref(attr)));
+            if !retained
+                && let syn::Meta::List(cfg) = &attr.meta
+                && cfg.path.is_ident("cfg")
+            {
+                warnings.push(
+                    Lint::CfgDropped,
+                    format!(
+                        "`#[cfg({})]` is dropped in {}, the item is included unconditionally",
+                        cfg.tokens, self.state.import_path
+                    ),
+                );
+            }
+            retained
+        });
     }
 
     fn visit_item_mod_mut(&mut self, node: &mut syn::ItemMod) {
@@ -258,12 +447,26 @@ impl<'a> VisitMut for FileProcessor<'_> {
         self.visit_visibility_mut(&mut node.vis);
         self.visit_ident_mut(&mut node.ident);
 
-        self.state.expand_mod(self.ctx, node);
+        if self.error.is_some() {
+            return;
+        }
+        let is_inline = node.content.is_some();
+        if let Err(err) = self.state.expand_mod(self.ctx, node) {
+            self.error = Some(err);
+            return;
+        }
 
+        // Re-exports of inline modules are relative to the modules themselves.
+        let scope = format!("{}/{}", self.state.scope, node.ident);
+        let parent = std::mem::replace(&mut self.state.scope, scope);
         if let Some(it) = &mut node.content {
+            if is_inline {
+                self.state.filter_inline_items(self.ctx, &mut it.1);
+            }
             for it in &mut (it).1 {
                 self.visit_item_mut(it);
             }
         }
+        self.state.scope = parent;
     }
 }
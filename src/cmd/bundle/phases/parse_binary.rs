@@ -1,34 +1,195 @@
 use {
     crate::cmd::bundle::{
         Bundler,
-        context::BundlerContext,
-        phases::{self, BunlingPhase, utils::extract_imported_paths},
+        context::{BundlerContext, BundlerOptions},
+        crate_paths::rewrite_dependency_paths,
+        escape::escape_non_ascii_literals,
+        local_gate::{LOCAL_FEATURE, LocalGateStripper, collect_idents},
+        phases::{
+            self,
+            BunlingPhase,
+            utils::{
+                extract_imported_names,
+                extract_imported_paths,
+                glob_imported_paths,
+                has_macro_use,
+                is_debug_module,
+                is_test_module,
+                load_mod,
+                macro_paths_in_tokens,
+                parse_source,
+                paths_in_tokens,
+                read_source,
+                render,
+                retain_imports,
+            },
+        },
+        warnings::Lint,
     },
-    anyhow::{Context, Result},
-    prettyplease::unparse,
-    std::{fs, io::Write},
-    syn::{parse_file, visit::Visit},
+    anyhow::{Context, Result, anyhow},
+    quote::ToTokens,
+    std::{
+        collections::{BTreeSet, HashMap, HashSet},
+        io::Write,
+        path::Path,
+        time::Instant,
+    },
+    syn::visit::Visit,
 };
 
 /// Extract all used modules used in problem's binary file.
-pub struct ParseBinary {}
+/// Crates, always available to the solution, as well as path keywords.
+const BUILTIN_CRATES: [&str; 7] = [
+    "std",
+    "core",
+    "alloc",
+    "proc_macro",
+    "crate",
+    "self",
+    "super",
+];
+
+#[derive(Default)]
+pub struct ParseBinary {
+    /// Crates imported with `#[macro_use] extern crate`.
+    macro_use_crates: HashSet<String>,
+
+    /// Names of macros invoked without a path prefix, e.g. `wln!(...)`.
+    macro_names: HashSet<String>,
+
+    /// Names, library crates (or their items) are imported under with `use
+    /// ... as`, mapped to the imported paths, e.g. `alg` to `algorist` for `use
+    /// algorist as alg`.
+    aliases: HashMap<String, Vec<String>>,
+}
 
 impl BunlingPhase for ParseBinary {}
 
 impl<'a> Bundler<'a, ParseBinary> {
     pub fn parse_binary(mut self) -> Result<Bundler<'a, phases::ExpandMods>> {
+        let started = Instant::now();
         let src = self.ctx.src.display().to_string();
         let dst = self.ctx.dst.display().to_string();
         println!("Bundling {src} -> {dst}");
 
         // Read the executable source file to find used modules.
-        let file_content =
-            fs::read_to_string(&self.ctx.src).context("failed to read source file")?;
-        let mut ast = parse_file(&file_content).context("failed to parse source file")?;
-        self.visit_file(&mut ast);
+        let file_content = read_source(&self.ctx.src, &mut self.ctx.warnings)
+            .context("failed to read source file")?;
+        let mut ast =
+            parse_source(&file_content, &self.ctx.src).context("failed to parse source file")?;
+
+        // File modules of the solution (e.g. the companion module with `solve()`) won't
+        // be next to the bundled file.
+        let base_path = self
+            .ctx
+            .src
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        let files = 1 + inline_file_mods(self.ctx, &mut ast.items, &base_path)?;
+
+        // Solution's own test (and, optionally, debug-only) modules are of no use on
+        // the judge. They go first, so that `local`-gated items, only their tests use,
+        // do not count as dangling.
+        strip_modules(&mut ast.items, &self.ctx.opts);
+
+        // Code gated with the local feature must not reach the judge, nor should it
+        // pull library modules into the output.
+        let mut stripper = LocalGateStripper::default();
+        let dangling = stripper.strip(&mut ast);
+        if !dangling.is_empty() {
+            return Err(anyhow!(
+                "solution depends on `{LOCAL_FEATURE}`-gated items: {}, gate their usages with \
+                 `#[cfg(feature = \"{LOCAL_FEATURE}\")]` as well",
+                dangling.join(", ")
+            ));
+        }
+        if stripper.count > 0 {
+            println!(
+                "Stripped {} `{LOCAL_FEATURE}`-gated item(s) from the solution",
+                stripper.count
+            );
+        }
+
+        // Aliases are collected upfront, as they may be referred to before they are
+        // declared. Imports are looked for in modules and function bodies alike.
+        let mut nested = NestedItems::default();
+        nested.visit_file(&ast);
+        // Names are known upfront as well, for glob imports to only bring in the
+        // re-exports the solution uses.
+        let mut names = HashSet::new();
+        collect_idents(ast.to_token_stream(), &mut names);
+        self.ctx.used_paths.set_solution_names(names);
+        self.state.collect_aliases(self.ctx, &nested.uses);
+        self.visit_file(&ast);
+        self.state.resolve_macro_names(self.ctx);
+        self.ctx.used_paths.include_dependencies();
+        self.warn_external_crates(&nested);
+
+        // Library crates are bundled as modules, so `extern crate` declarations for
+        // them must not make it into the output file. Exported macros end up at the
+        // root of the output file, so importing them there would define them twice.
+        let crates = &self.ctx.crates;
+        let used_paths = &self.ctx.used_paths;
+        let is_root_macro = |path: &[String]| {
+            matches!(path, [first, _] if crates.contains(first))
+                && used_paths.is_macro_export(&path.join("/"))
+        };
+        ast.items.retain_mut(|item| match item {
+            syn::Item::ExternCrate(item) => !crates.contains(&item.ident.to_string()),
+            syn::Item::Use(item) => retain_imports(&mut item.tree, &mut Vec::new(), &|path| {
+                !is_root_macro(path)
+            }),
+            _ => true,
+        });
 
-        // Write the source file -- unmodified -- to the output file.
-        writeln!(self.ctx.out, "{}", unparse(&ast)).context("failed to write source file")?;
+        // Library items, referred to by the solution, are the roots of item pruning
+        // (collected before renames, as library items are pruned before them as well).
+        if self.ctx.opts.prune_items {
+            collect_idents(ast.to_token_stream(), &mut self.ctx.used_idents);
+        }
+
+        // Rename problematic identifiers, if requested.
+        self.ctx
+            .opts
+            .renames
+            .apply(&mut ast, true, &mut self.ctx.warnings);
+        if self.ctx.opts.ascii_literals {
+            escape_non_ascii_literals(&mut ast);
+        }
+
+        // Crate modules are only in scope at the root of the bundled file, paths within
+        // the modules of the solution must go through them.
+        let deps = self
+            .ctx
+            .crates
+            .names()
+            .map(|name| (name.clone(), name.clone()))
+            .collect();
+        for item in &mut ast.items {
+            if let syn::Item::Mod(module) = item {
+                rewrite_dependency_paths(module, &deps);
+            }
+        }
+
+        // Prepend the submission header, if requested.
+        if let Some(header) = &self.ctx.opts.header {
+            let header = header.render(self.ctx);
+            write!(self.ctx.out, "{header}").context("failed to write header")?;
+        }
+
+        // Write the source file -- sans local-only code -- to the output file.
+        let format = !self.ctx.opts.no_format;
+        if format {
+            self.ctx
+                .source_map
+                .mark_items(&mut ast.items, &self.ctx.src);
+        }
+        self.ctx
+            .source_map
+            .emit(&mut self.ctx.out, &render(&ast, format));
+
+        self.ctx.stats.record("parse binary", started, files);
 
         Ok(Bundler {
             ctx: self.ctx,
@@ -37,19 +198,86 @@ impl<'a> Bundler<'a, ParseBinary> {
     }
 }
 
+impl Bundler<'_, ParseBinary> {
+    /// Report crates the solution depends on, which are not bundled: they must
+    /// be available on the judge.
+    fn warn_external_crates(&mut self, nested: &NestedItems) {
+        // Names defined in the solution may be imported from as well.
+        let local_names = nested
+            .local_names
+            .iter()
+            .cloned()
+            .chain(self.state.aliases.keys().cloned())
+            .collect::<HashSet<_>>();
+
+        let roots = nested
+            .uses
+            .iter()
+            .flat_map(|item| extract_imported_paths(&item.tree, Vec::new()))
+            .filter_map(|path| path.first().cloned())
+            .chain(
+                nested
+                    .extern_crates
+                    .iter()
+                    .map(|item| item.ident.to_string()),
+            );
+        let external = roots
+            .filter(|root| {
+                !BUILTIN_CRATES.contains(&root.as_str())
+                    && !self.ctx.crates.contains(root)
+                    && !local_names.contains(root)
+            })
+            .collect::<BTreeSet<_>>();
+        for name in external {
+            self.ctx.warnings.push(
+                Lint::ExternalCrate,
+                format!("solution depends on crate `{name}`, which is not bundled"),
+            );
+        }
+    }
+}
+
 impl ParseBinary {
+    /// Collects renamed imports from library crates, e.g. `use algorist as
+    /// alg` or `use algorist::math as m`, in the solution and its modules.
+    fn collect_aliases(&mut self, ctx: &BundlerContext, uses: &[&syn::ItemUse]) {
+        for item in uses {
+            for (path, name) in extract_imported_names(&item.tree, Vec::new()) {
+                let path = self.resolve_alias(&path);
+                if path.last() != Some(&name)
+                    && path.first().is_some_and(|first| ctx.crates.contains(first))
+                {
+                    self.aliases.insert(name, path);
+                }
+            }
+        }
+    }
+
+    /// Resolves a path, starting with an alias, to the imported one, e.g.
+    /// `alg::math::gcd` to `algorist::math::gcd` for `use algorist as alg`.
+    fn resolve_alias(&self, segments: &[String]) -> Vec<String> {
+        match segments.split_first() {
+            Some((first, rest)) if self.aliases.contains_key(first) => {
+                self.aliases[first].iter().chain(rest).cloned().collect()
+            }
+            _ => segments.to_vec(),
+        }
+    }
+
     /// Extracts used modules from the `use` tree and saves them for later
     /// stages.
     fn extract_used_mods(&mut self, ctx: &mut BundlerContext, node: &syn::ItemUse) {
         // Ignore all imports except those from the available crates.
-        if let syn::UseTree::Path(path) = &node.tree {
-            if !ctx.crates.contains(&path.ident.to_string()) {
-                return;
-            }
+        if let syn::UseTree::Path(path) = &node.tree
+            && !ctx.crates.contains(&path.ident.to_string())
+            && !self.aliases.contains_key(&path.ident.to_string())
+        {
+            return;
         }
 
         let paths = extract_imported_paths(&node.tree, Vec::new());
         for path in paths {
+            let path = self.resolve_alias(&path);
             if path.is_empty() {
                 // Skip empty paths
                 continue;
@@ -62,6 +290,187 @@ impl ParseBinary {
 
             ctx.used_paths.insert_path(&path.join("/"));
         }
+
+        // Glob imports bring in everything within the module.
+        for path in glob_imported_paths(&node.tree, Vec::new()) {
+            let path = self.resolve_alias(&path);
+            if path.first().is_some_and(|name| ctx.crates.contains(name)) {
+                ctx.used_paths.insert_glob(&path.join("/"));
+            }
+        }
+    }
+
+    /// Extracts used modules from fully qualified paths in expressions and
+    /// types, e.g. `algorist::math::gcd(a, b)`, used without a `use`
+    /// declaration.
+    fn extract_path_usage(&mut self, ctx: &mut BundlerContext, path: &syn::Path) {
+        let segments = path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        self.register_qualified_path(ctx, &segments);
+    }
+
+    fn register_qualified_path(&mut self, ctx: &mut BundlerContext, segments: &[String]) {
+        let segments = self.resolve_alias(segments);
+        if let [first, _, ..] = segments.as_slice()
+            && ctx.crates.contains(first)
+        {
+            ctx.used_paths.insert_path(&segments.join("/"));
+        }
+    }
+
+    /// Extracts used modules from macro invocations.
+    ///
+    /// Invocations with a path prefix (e.g. `algorist::io::wln!`) are
+    /// registered immediately, while bare invocations (e.g. `wln!`) are
+    /// resolved once the whole file is processed, see
+    /// [`Self::resolve_macro_names`].
+    fn extract_macro_usage(&mut self, ctx: &mut BundlerContext, node: &syn::Macro) {
+        let segments = node
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+
+        // Macro arguments may contain nested invocations, as well as fully qualified
+        // paths (e.g. `algorist::math::gcd(a, b)` in `println!`).
+        let nested = macro_paths_in_tokens(node.tokens.clone());
+        for path in paths_in_tokens(node.tokens.clone()) {
+            self.register_qualified_path(ctx, &path);
+        }
+
+        for segments in std::iter::once(segments).chain(nested) {
+            let segments = self.resolve_alias(&segments);
+            match segments.as_slice() {
+                [name] => {
+                    self.macro_names.insert(name.clone());
+                }
+                [first, ..] if ctx.crates.contains(first) => {
+                    ctx.used_paths.insert_path(&segments.join("/"));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Registers crates imported with `#[macro_use] extern crate`.
+    fn extract_macro_use(&mut self, ctx: &mut BundlerContext, node: &syn::ItemExternCrate) {
+        let crate_name = node.ident.to_string();
+        if !ctx.crates.contains(&crate_name) {
+            return;
+        }
+
+        ctx.used_paths.insert_path(&crate_name);
+        if has_macro_use(&node.attrs) {
+            self.macro_use_crates.insert(crate_name);
+        }
+    }
+
+    /// Resolves bare macro invocations against macros exported by crates
+    /// imported with `#[macro_use]`.
+    fn resolve_macro_names(&mut self, ctx: &mut BundlerContext) {
+        for crate_name in &self.macro_use_crates {
+            for name in &self.macro_names {
+                let root_path = format!("{crate_name}/{name}");
+                if ctx.used_paths.is_macro_export(&root_path) {
+                    ctx.used_paths.insert_path(&root_path);
+                }
+            }
+        }
+    }
+}
+
+/// Inline file modules, declared by the solution (e.g. `#[path = "a/solve.rs"]
+/// mod solve;`), marking their items in the source map.
+///
+/// Returns the number of inlined files.
+fn inline_file_mods(
+    ctx: &mut BundlerContext,
+    items: &mut [syn::Item],
+    base_path: &Path,
+) -> Result<usize> {
+    let mut inlined = 0;
+    for item in items {
+        let syn::Item::Mod(node) = item else {
+            continue;
+        };
+        if let Some((_, items)) = &mut node.content {
+            // Modules, declared within inline ones, are in their directories.
+            let base_path = base_path.join(node.ident.to_string());
+            inlined += inline_file_mods(ctx, items, &base_path)?;
+            continue;
+        }
+        let (mod_base, file, code) = load_mod(base_path, node, &mut ctx.warnings).context(
+            format!("failed to read module `{}` of the solution", node.ident),
+        )?;
+        let mut ast = parse_source(&code, &file)?;
+        if !ctx.opts.no_format {
+            ctx.source_map.mark_items(&mut ast.items, &file);
+        }
+        inlined += 1 + inline_file_mods(ctx, &mut ast.items, &mod_base)?;
+
+        // The `#[path]` attribute is no longer meaningful for an inline module.
+        node.content = Some((Default::default(), ast.items));
+        node.attrs.retain(|attr| !attr.path().is_ident("path"));
+        node.attrs.extend(ast.attrs);
+    }
+    Ok(inlined)
+}
+
+/// Strip test (and, optionally, debug-only) modules of the solution, at any
+/// depth: they are of no use on the judge.
+fn strip_modules(items: &mut Vec<syn::Item>, opts: &BundlerOptions) {
+    items.retain_mut(|item| match item {
+        syn::Item::Mod(item) if !opts.keep_tests && is_test_module(item) => {
+            println!("Stripping test module: {}", item.ident);
+            false
+        }
+        syn::Item::Mod(item) if opts.strip_debug && is_debug_module(item) => {
+            println!("Stripping debug-only module: {}", item.ident);
+            false
+        }
+        syn::Item::Mod(syn::ItemMod {
+            content: Some((_, items)),
+            ..
+        }) => {
+            strip_modules(items, opts);
+            true
+        }
+        _ => true,
+    });
+}
+
+/// Items of the solution, which may be nested in modules, function bodies and
+/// other blocks.
+#[derive(Default)]
+struct NestedItems<'ast> {
+    uses: Vec<&'ast syn::ItemUse>,
+    extern_crates: Vec<&'ast syn::ItemExternCrate>,
+
+    /// Names of the modules and enums, defined in the solution.
+    local_names: Vec<String>,
+}
+
+impl<'ast> Visit<'ast> for NestedItems<'ast> {
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        self.uses.push(node);
+    }
+
+    fn visit_item_extern_crate(&mut self, node: &'ast syn::ItemExternCrate) {
+        self.extern_crates.push(node);
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
+        self.local_names.push(node.ident.to_string());
+        syn::visit::visit_item_mod(self, node);
+    }
+
+    fn visit_item_enum(&mut self, node: &'ast syn::ItemEnum) {
+        self.local_names.push(node.ident.to_string());
+        syn::visit::visit_item_enum(self, node);
     }
 }
 
@@ -70,4 +479,24 @@ impl<'ast> Visit<'ast> for Bundler<'_, phases::ParseBinary> {
         self.state.extract_used_mods(self.ctx, node);
         syn::visit::visit_item_use(self, node);
     }
+
+    fn visit_item_extern_crate(&mut self, node: &'ast syn::ItemExternCrate) {
+        self.state.extract_macro_use(self.ctx, node);
+        syn::visit::visit_item_extern_crate(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        self.state.extract_macro_usage(self.ctx, node);
+        syn::visit::visit_macro(self, node);
+    }
+
+    fn visit_expr_path(&mut self, node: &'ast syn::ExprPath) {
+        self.state.extract_path_usage(self.ctx, &node.path);
+        syn::visit::visit_expr_path(self, node);
+    }
+
+    fn visit_type_path(&mut self, node: &'ast syn::TypePath) {
+        self.state.extract_path_usage(self.ctx, &node.path);
+        syn::visit::visit_type_path(self, node);
+    }
 }
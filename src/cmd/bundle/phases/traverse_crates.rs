@@ -2,21 +2,35 @@ use {
     crate::cmd::bundle::{
         Bundler,
         context::BundlerContext,
+        include::inline_includes,
         phases::{
             self,
             BunlingPhase,
             utils::{
+                extract_imported_names,
                 extract_imported_paths,
+                glob_imported_paths,
+                is_exported_macro,
                 is_pub_use,
                 is_test_module,
                 load_mod,
+                macro_paths_in_tokens,
+                parse_source,
+                paths_in_tokens,
+                read_source,
+                resolve_module_path,
                 tranform_alias_and_fqn,
             },
         },
+        warnings::Lint,
     },
     anyhow::{Context, Result},
-    std::{fs, path::PathBuf},
-    syn::{parse_file, visit::Visit},
+    std::{
+        collections::{HashMap, HashSet},
+        path::PathBuf,
+        time::Instant,
+    },
+    syn::visit::Visit,
 };
 
 /// Traverses all the crates in the project, recursively processing all
@@ -26,23 +40,48 @@ pub struct TraverseCrates {
     crate_name: String,
     path: PathBuf,
     import_path: String,
+
+    /// Path of the module being visited: the module of the file, or an inline
+    /// module within it (e.g. `algorist/prelude` for `pub mod prelude { ... }`
+    /// within `lib.rs`).
+    scope: String,
+
+    /// Project crates, the crate depends on, by the names it refers to them.
+    deps: HashMap<String, String>,
+
+    /// Modules, declared in the file being processed.
+    submodules: HashSet<String>,
+
+    /// Number of source files read.
+    files: usize,
 }
 
 impl BunlingPhase for TraverseCrates {}
 
 impl<'a> Bundler<'a, TraverseCrates> {
     pub fn traverse_crates(self) -> Result<Bundler<'a, phases::ParseBinary>> {
+        let started = Instant::now();
+        let mut files = 0;
+
         // For all crates in `crates` directory, start traversal of their files.
         let crates = self.ctx.crates.clone();
-        for (crate_name, crate_path) in crates.into_iter() {
-            let file_content = fs::read_to_string(crate_path.join("src/lib.rs")).context(
-                format!("failed to read library file for crate {crate_name}"),
-            )?;
-            let ast = parse_file(&file_content).context(format!(
+        for (crate_name, crate_path) in crates.clone().into_iter() {
+            let lib_path = crate_path.join("src/lib.rs");
+            let file_content = read_source(&lib_path, &mut self.ctx.warnings).context(format!(
+                "failed to read library file for crate {crate_name}"
+            ))?;
+            let mut ast = parse_source(&file_content, &lib_path).context(format!(
                 "failed to parse library file for crate {crate_name}"
             ))?;
+            // Included items are analyzed along with the rest of the file.
+            let included = inline_includes(&mut ast, &lib_path, None).context(format!(
+                "failed to inline files, included by crate {crate_name}"
+            ))?;
+            self.ctx
+                .used_paths
+                .insert_module(&crate_name, file_content.len());
 
-            FileProcessor {
+            let mut processor = FileProcessor {
                 ctx: self.ctx,
                 state: TraverseCrates {
                     crate_name: crate_name.clone(),
@@ -51,14 +90,51 @@ impl<'a> Bundler<'a, TraverseCrates> {
                         .canonicalize()
                         .context("failed to canonicalize src path")?,
                     import_path: crate_name.clone(),
+                    scope: crate_name.clone(),
+                    deps: crates.dependencies(&crate_name),
+                    submodules: HashSet::new(),
+                    files: 1 + included,
                 },
+                error: None,
+            };
+            processor.visit_file(&ast);
+            if let Some(err) = processor.error {
+                return Err(err);
             }
-            .visit_file(&ast);
+            files += processor.state.files;
         }
 
+        // Names, glob re-exports bring in, are only known once all modules are
+        // traversed.
+        let unknown = self
+            .ctx
+            .used_paths
+            .expand_glob_reexports()
+            .into_iter()
+            .filter(|(_, module)| {
+                !matches!(module.split('/').next(), Some("std" | "core" | "alloc"))
+            })
+            .map(|(alias, module)| {
+                format!(
+                    "`pub use {}::*` in {} re-exports a module, which is not found in the project \
+                     crates: the re-export is retained as is",
+                    module.replace('/', "::"),
+                    alias
+                        .rsplit_once('/')
+                        .map_or(alias, |(scope, _)| scope)
+                        .replace('/', "::")
+                )
+            })
+            .collect::<Vec<_>>();
+        for message in unknown {
+            self.ctx.warnings.push(Lint::GlobReexport, message);
+        }
+
+        self.ctx.stats.record("traverse crates", started, files);
+
         Ok(Bundler {
             ctx: self.ctx,
-            state: phases::ParseBinary {},
+            state: phases::ParseBinary::default(),
         })
     }
 }
@@ -75,57 +151,226 @@ impl TraverseCrates {
             return;
         }
 
-        let paths = extract_imported_paths(&node.tree, Vec::new());
-        for path in paths {
-            if let Some(alias) = path.last() {
-                let (alias, fully_qualified) =
-                    tranform_alias_and_fqn(alias, &self.import_path, &path);
+        let globs = glob_imported_paths(&node.tree, Vec::new());
+        for (path, alias) in extract_imported_names(&node.tree, Vec::new()) {
+            if !path.is_empty() && !globs.contains(&path) {
+                let (alias, fully_qualified) = tranform_alias_and_fqn(&alias, &self.scope, &path);
                 ctx.used_paths.insert_pub_use_decl(&alias, &fully_qualified);
             }
         }
+        // Names, re-exported with a glob, are unknown until all the modules are
+        // traversed, see `ParsedPaths::expand_glob_reexports`.
+        for path in globs {
+            let Some(name) = path.last() else {
+                continue;
+            };
+            let (alias, _) = tranform_alias_and_fqn(name, &self.scope, &path);
+            let path = self.resolve_dependency(path);
+            let module = match path[0].as_str() {
+                _ if ctx.crates.contains(&path[0]) => path.join("/"),
+                "std" | "core" | "alloc" => path.join("/"),
+                _ => resolve_module_path(&self.scope, &path),
+            };
+            ctx.used_paths.insert_glob_reexport(&alias, &module);
+        }
     }
 
-    fn traverse_mod(&mut self, ctx: &mut BundlerContext, node: &syn::ItemMod) {
-        if node.content.is_some() {
-            return;
+    /// Record public names of the module (and of its inline submodules), which
+    /// glob re-exports of the module bring in.
+    fn extract_module_items(&self, ctx: &mut BundlerContext, module: &str, items: &[syn::Item]) {
+        use syn::{Item, Visibility};
+        for item in items {
+            let (vis, ident) = match item {
+                Item::Const(item) => (&item.vis, &item.ident),
+                Item::Enum(item) => (&item.vis, &item.ident),
+                Item::Fn(item) => (&item.vis, &item.sig.ident),
+                Item::Mod(item) => (&item.vis, &item.ident),
+                Item::Static(item) => (&item.vis, &item.ident),
+                Item::Struct(item) => (&item.vis, &item.ident),
+                Item::Trait(item) => (&item.vis, &item.ident),
+                Item::TraitAlias(item) => (&item.vis, &item.ident),
+                Item::Type(item) => (&item.vis, &item.ident),
+                Item::Union(item) => (&item.vis, &item.ident),
+                Item::Use(item) if !matches!(item.vis, Visibility::Inherited) => {
+                    // Names, re-exported with a glob, are added on expansion.
+                    let globs = glob_imported_paths(&item.tree, Vec::new());
+                    for (path, name) in extract_imported_names(&item.tree, Vec::new()) {
+                        let name = match name.as_str() {
+                            "self" => path.iter().nth_back(1).cloned().unwrap_or_default(),
+                            _ => name,
+                        };
+                        if !globs.contains(&path) && !name.is_empty() {
+                            ctx.used_paths.insert_module_item(module, &name);
+                        }
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            // Inline modules are private more often than not (e.g. `mod inner {
+            // ... } pub use inner::*`).
+            if let Item::Mod(item) = item
+                && let Some((_, items)) = &item.content
+            {
+                self.extract_module_items(ctx, &format!("{module}/{ident}"), items);
+            }
+            if matches!(vis, Visibility::Inherited) {
+                continue;
+            }
+            ctx.used_paths
+                .insert_module_item(module, &ident.to_string());
+            if let Item::Enum(item) = item {
+                let path = format!("{module}/{ident}");
+                for variant in &item.variants {
+                    ctx.used_paths
+                        .insert_module_item(&path, &variant.ident.to_string());
+                }
+            }
         }
+    }
 
-        if is_test_module(node) {
+    /// Build an index of macros exported with `#[macro_export]`.
+    ///
+    /// Such macros can be invoked without any `use` declaration, so the module
+    /// they are defined in needs to be known to retain it in the output.
+    fn extract_macro_exports(&mut self, ctx: &mut BundlerContext, node: &syn::ItemMacro) {
+        if !is_exported_macro(node) {
             return;
         }
 
+        if let Some(ident) = &node.ident {
+            ctx.used_paths
+                .insert_macro_export(&format!("{}/{}", self.crate_name, ident), &self.import_path);
+        }
+    }
+
+    /// Record macros, invoked within the module (directly, in macro arguments,
+    /// or in bodies of macro definitions).
+    ///
+    /// Exported macros are invoked without any `use` declaration, so the
+    /// modules they are defined in are only known to be needed from this index.
+    fn extract_macro_invocations(&mut self, ctx: &mut BundlerContext, node: &syn::Macro) {
+        let path = node
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        for path in std::iter::once(path).chain(macro_paths_in_tokens(node.tokens.clone())) {
+            let path = self.resolve_dependency(path);
+            // Macros of the same crate may be invoked as `wln!`, `crate::wln!` or
+            // `$crate::wln!`.
+            let root_path = match path.as_slice() {
+                [name] => format!("{}/{name}", self.crate_name),
+                [first, name] if first == "crate" => format!("{}/{name}", self.crate_name),
+                [first, name] if ctx.crates.contains(first) => format!("{first}/{name}"),
+                _ => continue,
+            };
+            ctx.used_paths
+                .insert_macro_invocation(&self.import_path, &root_path);
+        }
+    }
+
+    /// Record paths to other library modules, the module refers to in `use`
+    /// declarations (e.g. `use super::math::gcd`), or in paths within code
+    /// and macro arguments (e.g. `crate::math::gcd(a, b)`).
+    ///
+    /// Such modules are retained along with the module, even if the solution
+    /// doesn't refer to them.
+    fn extract_module_deps(
+        &mut self,
+        ctx: &mut BundlerContext,
+        paths: Vec<Vec<String>>,
+        is_use: bool,
+    ) {
+        for path in paths {
+            let path = self.resolve_dependency(path);
+            let Some(first) = path.first() else {
+                continue;
+            };
+            let dep = match first.as_str() {
+                "crate" | "self" | "super" => resolve_module_path(&self.import_path, &path),
+                _ if ctx.crates.contains(first) => path.join("/"),
+                "std" | "core" | "alloc" => continue,
+                // Imports are relative to the module, other paths may as well refer to
+                // local items, unless they start with a submodule (e.g.
+                // `private::Sealed`).
+                _ if is_use || self.submodules.contains(first) => {
+                    resolve_module_path(&self.import_path, &path)
+                }
+                _ => continue,
+            };
+            ctx.used_paths.insert_module_dep(&self.import_path, &dep);
+        }
+    }
+
+    /// Resolves a path, starting with a renamed dependency on another project
+    /// crate, to the crate name, e.g. `alg::math::gcd` to `algorist::math::gcd`
+    /// for `alg = { package = "algorist", ... }`.
+    fn resolve_dependency(&self, mut path: Vec<String>) -> Vec<String> {
+        if let Some(first) = path.first_mut()
+            && let Some(name) = self.deps.get(first)
+        {
+            *first = name.clone();
+        }
+        path
+    }
+
+    fn traverse_mod(&mut self, ctx: &mut BundlerContext, node: &syn::ItemMod) -> Result<()> {
+        if node.content.is_some() {
+            return Ok(());
+        }
+
+        if is_test_module(node) && !ctx.opts.with_tests {
+            return Ok(());
+        }
+
         let mod_name = node.ident.to_string();
-        let (base_path, code) = load_mod(&self.path, &mod_name).expect("Failed to load module");
-
-        let ast = parse_file(&code).expect("Failed to parse module file");
-
-        let crate_src_path = ctx
-            .crates
-            .path(&self.crate_name)
-            .expect("crate path not found")
-            .join("src");
-        let import_path = base_path
-            .display()
-            .to_string()
-            .replace(&ctx.root_path, "")
-            .replace(
-                crate_src_path
-                    .to_str()
-                    .expect("failed to convert crate source path"),
-                &self.crate_name,
-            )
-            .trim_start_matches('/')
-            .to_string();
-
-        FileProcessor {
+        let Ok((base_path, file, code)) = load_mod(&self.path, node, &mut ctx.warnings) else {
+            ctx.warnings.push(
+                Lint::MissingModule,
+                format!(
+                    "module `{mod_name}` is not found in {}",
+                    self.path.display()
+                ),
+            );
+            return Ok(());
+        };
+
+        let mut ast = parse_source(&code, &file).context(format!(
+            "failed to parse module `{mod_name}` of {}",
+            self.import_path
+        ))?;
+        let included = inline_includes(&mut ast, &file, None).context(format!(
+            "failed to inline files, included by module `{mod_name}` of {}",
+            self.import_path
+        ))?;
+
+        // Import path is derived from the declaration, rather than the file location:
+        // files of `#[path]` modules may be located anywhere, and a `foo.rs` file
+        // (e.g. `prelude.rs`) is in the directory of its parent module.
+        let import_path = format!("{}/{mod_name}", self.import_path);
+        ctx.used_paths.insert_module(&import_path, code.len());
+
+        let mut processor = FileProcessor {
             ctx,
             state: TraverseCrates {
                 crate_name: self.crate_name.clone(),
                 path: base_path,
+                scope: import_path.clone(),
                 import_path,
+                deps: self.deps.clone(),
+                submodules: HashSet::new(),
+                files: 1 + included,
             },
+            error: None,
+        };
+        processor.visit_file(&ast);
+        if let Some(err) = processor.error {
+            return Err(err);
         }
-        .visit_file(&ast);
+        self.files += processor.state.files;
+        Ok(())
     }
 }
 
@@ -133,20 +378,96 @@ impl TraverseCrates {
 struct FileProcessor<'a> {
     ctx: &'a mut BundlerContext,
     state: TraverseCrates,
+
+    /// First error, the traversal stopped at (visitors can't return errors).
+    error: Option<anyhow::Error>,
 }
 
 impl<'a> FileProcessor<'a> {}
 
 impl<'ast> Visit<'ast> for FileProcessor<'_> {
+    fn visit_file(&mut self, node: &'ast syn::File) {
+        self.state.submodules = node
+            .items
+            .iter()
+            .filter_map(|item| match item {
+                syn::Item::Mod(item) => Some(item.ident.to_string()),
+                _ => None,
+            })
+            .collect();
+        self.state
+            .extract_module_items(self.ctx, &self.state.import_path, &node.items);
+
+        syn::visit::visit_file(self, node);
+    }
+
     fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
-        self.state.traverse_mod(self.ctx, node);
+        // Test modules don't make it into the output (unless requested), neither
+        // should whatever they refer to.
+        if self.error.is_some() || (is_test_module(node) && !self.ctx.opts.with_tests) {
+            return;
+        }
+        if let Err(err) = self.state.traverse_mod(self.ctx, node) {
+            self.error = Some(err);
+            return;
+        }
 
+        // Re-exports of inline modules are relative to the modules themselves.
+        let scope = match node.content {
+            Some(_) => format!("{}/{}", self.state.scope, node.ident),
+            None => self.state.scope.clone(),
+        };
+        let parent = std::mem::replace(&mut self.state.scope, scope);
         syn::visit::visit_item_mod(self, node);
+        self.state.scope = parent;
     }
 
     fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
-        self.state.extract_pub_use_decls(self.ctx, node);
+        // Re-exports are retained only when used, see `extract_pub_use_decls`.
+        if is_pub_use(node) {
+            self.state.extract_pub_use_decls(self.ctx, node);
+        } else {
+            let paths = extract_imported_paths(&node.tree, Vec::new());
+            self.state.extract_module_deps(self.ctx, paths, true);
+        }
 
         syn::visit::visit_item_use(self, node);
     }
+
+    fn visit_path(&mut self, node: &'ast syn::Path) {
+        if node.segments.len() > 1 {
+            let path = node
+                .segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect();
+            self.state.extract_module_deps(self.ctx, vec![path], false);
+        }
+
+        syn::visit::visit_path(self, node);
+    }
+
+    fn visit_item_trait(&mut self, node: &'ast syn::ItemTrait) {
+        // Trait methods are called without naming the trait, see
+        // `ParsedPaths::insert_glob`.
+        self.ctx
+            .used_paths
+            .insert_trait(&format!("{}/{}", self.state.scope, node.ident));
+
+        syn::visit::visit_item_trait(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast syn::ItemMacro) {
+        self.state.extract_macro_exports(self.ctx, node);
+
+        syn::visit::visit_item_macro(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        self.state.extract_macro_invocations(self.ctx, node);
+        let paths = paths_in_tokens(node.tokens.clone());
+        self.state.extract_module_deps(self.ctx, paths, false);
+
+        syn::visit::visit_macro(self, node);
+    }
 }
@@ -1,21 +1,44 @@
 use {
-    anyhow::{Context, Result},
+    crate::cmd::{
+        bundle::warnings::{Lint, Warnings},
+        cfg::is_test_only,
+    },
+    anyhow::{Context, Result, anyhow},
+    prettyplease::unparse,
+    quote::ToTokens,
     std::{
         fs,
         path::{Path, PathBuf},
     },
 };
 
+/// Source code of the file: pretty-printed, or, if formatting is skipped, its
+/// tokens as they are, with each top-level item on a line of its own.
+pub fn render(ast: &syn::File, format: bool) -> String {
+    if format {
+        return unparse(ast);
+    }
+    let mut out = String::new();
+    let attrs = ast.attrs.iter().map(ToTokens::to_token_stream);
+    for tokens in attrs.chain(ast.items.iter().map(ToTokens::to_token_stream)) {
+        out.push_str(&tokens.to_string());
+        out.push('\n');
+    }
+    out
+}
+
 pub fn is_test_module(item_mod: &syn::ItemMod) -> bool {
-    // locate `#[cfg(test)]` attribute
+    // `#[cfg(test)]`, along with any predicate that can't hold outside of tests.
+    is_test_only(&item_mod.attrs)
+}
+
+pub fn is_debug_module(item_mod: &syn::ItemMod) -> bool {
+    // locate `#[cfg(debug_assertions)]` attribute
     item_mod.attrs.iter().any(|attr| {
-        if attr.path().is_ident("cfg") {
-            let cfg_args: syn::Expr = attr.parse_args().unwrap();
-            if let syn::Expr::Path(syn::ExprPath { path, .. }) = cfg_args {
-                return path.is_ident("test");
-            }
-        }
-        false
+        attr.path().is_ident("cfg")
+            && attr
+                .parse_args::<syn::Path>()
+                .is_ok_and(|path| path.is_ident("debug_assertions"))
     })
 }
 
@@ -23,35 +46,183 @@ pub fn is_pub_use(item: &syn::ItemUse) -> bool {
     matches!(item.vis, syn::Visibility::Public(_))
 }
 
-/// Load a module file from the source directory.
+pub fn is_exported_macro(item: &syn::ItemMacro) -> bool {
+    item.mac.path.is_ident("macro_rules")
+        && item
+            .attrs
+            .iter()
+            .any(|attr| attr.path().is_ident("macro_export"))
+}
+
+pub fn has_macro_use(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| attr.path().is_ident("macro_use"))
+}
+
+/// Collect paths of macros invoked within the given token stream.
 ///
-/// Return a tuple containing the base path of the module and its source code.
-pub fn load_mod(base_path: &Path, mod_name: &str) -> Result<(PathBuf, String)> {
-    // Load the module file from the source directory.
-    // Module may be EITHER in the form of `src/foo.rs` or `src/foo/mod.rs`.
-    // Try both, and since only one works, we can use `find` to get the first one.
-    vec![
-        format!("{}/{}.rs", base_path.display(), mod_name),
-        format!("{}/{}/mod.rs", base_path.display(), mod_name),
-    ]
-    .into_iter()
-    .map(PathBuf::from)
-    .find(|p| p.exists())
-    .map(|p| {
-        let base_path = p
-            .clone()
-            .parent()
-            .expect("Failed to get parent directory")
-            .to_path_buf();
-        (base_path, p)
+/// Macro arguments are not parsed by `syn`, so nested invocations (e.g.
+/// `wln!(w, "{}", gcd_of!(a, b))`) are only visible as raw tokens: an
+/// (optionally `::`-separated) path, followed by `!` and a delimited group.
+pub fn macro_paths_in_tokens(tokens: proc_macro2::TokenStream) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    scan_token_paths(tokens, &mut paths);
+    paths
+        .into_iter()
+        .filter_map(|(path, is_macro)| is_macro.then_some(path))
+        .collect()
+}
+
+/// Collect `::`-separated paths (of at least two segments) within the given
+/// token stream, e.g. `algorist::math::gcd` in `println!("{}",
+/// algorist::math::gcd(a, b))`.
+pub fn paths_in_tokens(tokens: proc_macro2::TokenStream) -> Vec<Vec<String>> {
+    let mut paths = Vec::new();
+    scan_token_paths(tokens, &mut paths);
+    paths
+        .into_iter()
+        .filter_map(|(path, _)| (path.len() > 1).then_some(path))
+        .collect()
+}
+
+/// Collect paths within the token stream, along with whether they are macro
+/// invocations.
+fn scan_token_paths(tokens: proc_macro2::TokenStream, paths: &mut Vec<(Vec<String>, bool)>) {
+    use proc_macro2::{Spacing, TokenTree};
+
+    let tokens = tokens.into_iter().collect::<Vec<_>>();
+    let is_path_sep = |i: usize| {
+        matches!(tokens.get(i), Some(TokenTree::Punct(p)) if p.as_char() == ':' && p.spacing() == Spacing::Joint)
+            && matches!(tokens.get(i + 1), Some(TokenTree::Punct(p)) if p.as_char() == ':')
+    };
+
+    let mut i = 0;
+    while i < tokens.len() {
+        match &tokens[i] {
+            TokenTree::Group(group) => scan_token_paths(group.stream(), paths),
+            TokenTree::Ident(_) => {
+                // Collect the whole `a::b::c` path, starting at the current token.
+                let mut path = Vec::new();
+                while let Some(TokenTree::Ident(ident)) = tokens.get(i) {
+                    path.push(ident.to_string());
+                    if !is_path_sep(i + 1) {
+                        i += 1;
+                        break;
+                    }
+                    i += 3;
+                }
+
+                let is_macro = matches!(tokens.get(i), Some(TokenTree::Punct(p)) if p.as_char() == '!')
+                    && matches!(tokens.get(i + 1), Some(TokenTree::Group(_)));
+                paths.push((path, is_macro));
+                continue;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+}
+
+/// Parse a source file, pointing at the location of the syntax error, if any.
+pub fn parse_source(code: &str, path: &Path) -> Result<syn::File> {
+    syn::parse_file(code).map_err(|err| {
+        let start = err.span().start();
+        anyhow!(
+            "{}:{}:{}: {err}",
+            path.display(),
+            start.line,
+            start.column + 1
+        )
     })
-    .and_then(|(base_path, mod_path)| {
-        fs::read_to_string(mod_path)
-            .context("failed to read source file")
-            .ok()
-            .and_then(|code| Some((base_path, code)))
+}
+
+/// Read a source file, normalizing its encoding and line endings.
+///
+/// Editors may save files with a byte order mark, in UTF-16, or with CRLF line
+/// endings. The returned content is always BOM-free, LF-only UTF-8. Invalid
+/// UTF-8 sequences are replaced (with a warning), instead of failing.
+pub fn read_source(path: &Path, warnings: &mut Warnings) -> Result<String> {
+    let bytes = fs::read(path).context(format!("failed to read {}", path.display()))?;
+
+    let decode_utf16 = |bytes: &[u8], from_bytes: fn([u8; 2]) -> u16| {
+        let units = bytes
+            .chunks_exact(2)
+            .map(|c| from_bytes([c[0], c[1]]))
+            .collect::<Vec<_>>();
+        String::from_utf16_lossy(&units)
+    };
+    let content = match bytes.as_slice() {
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).into_owned(),
+        [0xFF, 0xFE, rest @ ..] => decode_utf16(rest, u16::from_le_bytes),
+        [0xFE, 0xFF, rest @ ..] => decode_utf16(rest, u16::from_be_bytes),
+        bytes => match std::str::from_utf8(bytes) {
+            Ok(content) => content.to_string(),
+            Err(_) => {
+                warnings.push(
+                    Lint::InvalidUtf8,
+                    format!(
+                        "{} is not valid UTF-8, invalid sequences are replaced",
+                        path.display()
+                    ),
+                );
+                String::from_utf8_lossy(bytes).into_owned()
+            }
+        },
+    };
+
+    Ok(normalize_line_endings(&content))
+}
+
+/// Convert CRLF (and lone CR) line endings to LF.
+pub fn normalize_line_endings(content: &str) -> String {
+    content.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Path from the `#[path = "..."]` attribute of a module declaration, if any.
+pub fn mod_path_attr(item_mod: &syn::ItemMod) -> Option<String> {
+    item_mod.attrs.iter().find_map(|attr| match &attr.meta {
+        syn::Meta::NameValue(nv) if nv.path.is_ident("path") => match &nv.value {
+            syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(path),
+                ..
+            }) => Some(path.value()),
+            _ => None,
+        },
+        _ => None,
     })
-    .context("Module file not found")
+}
+
+/// Load a module file from the source directory.
+///
+/// Return a tuple containing the base path of the module, path to its file
+/// and its source code.
+pub fn load_mod(
+    base_path: &Path,
+    item_mod: &syn::ItemMod,
+    warnings: &mut Warnings,
+) -> Result<(PathBuf, PathBuf, String)> {
+    // Module file, declared with `#[path = "..."]`, is relative to the current
+    // module's directory. Otherwise, module may be EITHER in the form of
+    // `src/foo.rs` or `src/foo/mod.rs`. Try both, and since only one works, we
+    // can use `find` to get the first one.
+    let mod_name = item_mod.ident.to_string();
+    let candidates = match mod_path_attr(item_mod) {
+        Some(path) => vec![base_path.join(path)],
+        None => vec![
+            base_path.join(format!("{mod_name}.rs")),
+            base_path.join(&mod_name).join("mod.rs"),
+        ],
+    };
+    candidates
+        .into_iter()
+        .find(|p| p.exists())
+        .and_then(|p| Some((p.parent()?.to_path_buf(), p)))
+        .and_then(|(base_path, mod_path)| {
+            read_source(&mod_path, warnings)
+                .context("failed to read source file")
+                .ok()
+                .map(|code| (base_path, mod_path, code))
+        })
+        .context("Module file not found")
 }
 
 pub fn tranform_alias_and_fqn(
@@ -65,59 +236,153 @@ pub fn tranform_alias_and_fqn(
 
     let alias = format!("{}/{}", import_path, alias);
     let fully_qualified = if segments[0] != "std" {
-        format!("{}/{}", import_path, segments.join("/"))
+        resolve_module_path(import_path, segments)
     } else {
         segments.join("/")
     };
     (alias, fully_qualified)
 }
 
+/// Resolve a path, used within the module with the given import path (e.g.
+/// `algorist/io`), into a path from the crate root: `crate`, `self` and
+/// `super` segments are resolved, and other paths are taken as relative to
+/// the module (as in `use` declarations).
+///
+/// For example, `super::math::gcd` within `algorist/io` resolves to
+/// `algorist/math/gcd`.
+pub fn resolve_module_path(import_path: &str, segments: &[String]) -> String {
+    let mut path = import_path.split('/').map(String::from).collect::<Vec<_>>();
+    for (i, segment) in segments.iter().enumerate() {
+        match segment.as_str() {
+            "crate" if i == 0 => path.truncate(1),
+            "self" => {}
+            // Crate root has no parent, such paths don't compile anyway.
+            "super" if path.len() > 1 => {
+                path.pop();
+            }
+            "super" => {}
+            _ => path.push(segment.clone()),
+        }
+    }
+    path.join("/")
+}
+
+/// Paths of the imported items, e.g. `algorist::math::gcd` for both `use
+/// algorist::math::gcd` and `use algorist::math::gcd as g`.
 pub fn extract_imported_paths(tree: &syn::UseTree, prefix: Vec<String>) -> Vec<Vec<String>> {
+    extract_imported_names(tree, prefix)
+        .into_iter()
+        .map(|(path, _)| path)
+        .collect()
+}
+
+/// Paths of the imported items, along with the names they are bound to in
+/// the importing scope (which differ for `use ... as` renames).
+///
+/// Glob imports have no specific names: the path of the module is returned,
+/// along with its last segment.
+pub fn extract_imported_names(
+    tree: &syn::UseTree,
+    prefix: Vec<String>,
+) -> Vec<(Vec<String>, String)> {
     use syn::{UseGlob, UseGroup, UseName, UsePath, UseRename, UseTree};
     match tree {
         UseTree::Path(UsePath { ident, tree, .. }) => {
             let mut new_prefix = prefix.clone();
             new_prefix.push(ident.to_string());
-            extract_imported_paths(tree, new_prefix)
+            extract_imported_names(tree, new_prefix)
+        }
+        UseTree::Name(UseName { ident }) => {
+            let mut path = prefix;
+            path.push(ident.to_string());
+            vec![(path, ident.to_string())]
         }
-        UseTree::Name(UseName { ident, .. }) | UseTree::Rename(UseRename { rename: ident, .. }) => {
+        UseTree::Rename(UseRename { ident, rename, .. }) => {
             let mut path = prefix;
             path.push(ident.to_string());
-            vec![path]
+            vec![(path, rename.to_string())]
         }
         UseTree::Group(UseGroup { items, .. }) => items
             .iter()
-            .flat_map(|item| extract_imported_paths(item, prefix.clone()))
+            .flat_map(|item| extract_imported_names(item, prefix.clone()))
             .collect(),
         UseTree::Glob(UseGlob { .. }) => {
             // If it's a glob import, we don't have specific paths, so we return
             // the current prefix as a single path.
-            vec![prefix]
+            let name = prefix.last().cloned().unwrap_or_default();
+            vec![(prefix, name)]
         }
     }
 }
 
-pub fn flatten_imported_paths(tree: &syn::UseTree, prefix: Vec<syn::UseTree>) -> Vec<syn::ItemUse> {
+/// Paths of modules, imported with a glob (e.g. `algorist::graph` for `use
+/// algorist::graph::*`).
+///
+/// [`extract_imported_paths`] returns such paths as well, but can't tell them
+/// apart from the regular ones: everything within the module is imported, not
+/// just the module itself.
+pub fn glob_imported_paths(tree: &syn::UseTree, prefix: Vec<String>) -> Vec<Vec<String>> {
+    use syn::UseTree;
+    match tree {
+        UseTree::Path(path) => {
+            let mut prefix = prefix;
+            prefix.push(path.ident.to_string());
+            glob_imported_paths(&path.tree, prefix)
+        }
+        UseTree::Group(group) => group
+            .items
+            .iter()
+            .flat_map(|item| glob_imported_paths(item, prefix.clone()))
+            .collect(),
+        UseTree::Glob(_) => vec![prefix],
+        UseTree::Name(_) | UseTree::Rename(_) => Vec::new(),
+    }
+}
+
+/// Drop imported names, for which `keep` (given the full path of the name)
+/// returns `false`. Returns whether anything is left.
+pub fn retain_imports(
+    tree: &mut syn::UseTree,
+    prefix: &mut Vec<String>,
+    keep: &impl Fn(&[String]) -> bool,
+) -> bool {
+    use syn::UseTree;
+    match tree {
+        UseTree::Path(path) => {
+            prefix.push(path.ident.to_string());
+            let retained = retain_imports(&mut path.tree, prefix, keep);
+            prefix.pop();
+            retained
+        }
+        UseTree::Name(name) => {
+            prefix.push(name.ident.to_string());
+            let retained = keep(prefix);
+            prefix.pop();
+            retained
+        }
+        UseTree::Group(group) => {
+            group.items = std::mem::take(&mut group.items)
+                .into_pairs()
+                .map(|pair| pair.into_value())
+                .filter_map(|mut tree| retain_imports(&mut tree, prefix, keep).then_some(tree))
+                .collect();
+            !group.items.is_empty()
+        }
+        UseTree::Rename(_) | UseTree::Glob(_) => true,
+    }
+}
+
+pub fn flatten_imported_paths(tree: &syn::UseTree, prefix: Vec<syn::UsePath>) -> Vec<syn::ItemUse> {
     use syn::{UseGroup, UseTree};
 
-    fn wrap(segments: Vec<syn::UseTree>, last: syn::UseTree) -> syn::ItemUse {
-        let tree = segments
-            .into_iter()
-            .rev()
-            .fold(last, |tree, segment| match segment {
-                UseTree::Path(path) => {
-                    let new_path = syn::UsePath {
-                        ident: path.ident,
-                        colon2_token: path.colon2_token,
-                        tree: Box::new(tree),
-                    };
-                    UseTree::Path(new_path)
-                }
-                UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => segment,
-                UseTree::Group(group) => {
-                    panic!("Unexpected group in flatten_imported_paths: {:?}", group)
-                }
-            });
+    fn wrap(segments: Vec<syn::UsePath>, last: syn::UseTree) -> syn::ItemUse {
+        let tree = segments.into_iter().rev().fold(last, |tree, path| {
+            UseTree::Path(syn::UsePath {
+                ident: path.ident,
+                colon2_token: path.colon2_token,
+                tree: Box::new(tree),
+            })
+        });
         syn::ItemUse {
             attrs: Vec::new(),
             vis: syn::Visibility::Public(syn::parse_quote!(pub)),
@@ -135,7 +400,7 @@ pub fn flatten_imported_paths(tree: &syn::UseTree, prefix: Vec<syn::UseTree>) ->
     match tree {
         UseTree::Path(path) => {
             let mut new_prefix = prefix.clone();
-            new_prefix.push(tree.clone());
+            new_prefix.push(path.clone());
             flatten_imported_paths(&path.tree, new_prefix)
         }
         UseTree::Name(_) | UseTree::Rename(_) | UseTree::Glob(_) => {
@@ -0,0 +1,297 @@
+use {crate::cmd::bundle::local_gate::collect_idents, quote::ToTokens, std::collections::HashSet};
+
+/// Removes library items, which are not reachable from the solution.
+///
+/// Modules are bundled as a whole, so using a single function of a module
+/// drags all of its items in. The pruner finds items (functions, types,
+/// constants, traits, impls and inherent methods) reachable from identifiers
+/// of the solution, and drops the rest.
+///
+/// Reachability is name-based, and thus conservative: an item is kept if its
+/// name is referred to by any reachable code, regardless of the scope.
+/// Macros are always kept, and so is everything they refer to.
+#[derive(Debug)]
+pub struct ItemPruner {
+    /// Names of the prunable items, defined in the bundled crates.
+    defined: HashSet<String>,
+
+    /// Names, referred to by reachable code.
+    live: HashSet<String>,
+
+    /// Renamed imports, as `(name, alias)`: if the alias is referred to, so is
+    /// the name.
+    renames: Vec<(String, String)>,
+
+    /// Number of removed items.
+    pub removed: usize,
+}
+
+/// Item (or inherent method), reachable only under a condition.
+#[derive(Debug)]
+struct Node {
+    cond: Cond,
+
+    /// Identifiers, the item refers to.
+    idents: HashSet<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Cond {
+    /// Any of the names is referred to, e.g. the name of a function, or the
+    /// name of an enum or any of its variants.
+    AnyName(Vec<String>),
+
+    /// Both the implementing type and the trait are reachable (or not defined
+    /// in the bundled crates).
+    Impl {
+        self_ty: Option<String>,
+        trait_: Option<String>,
+    },
+
+    /// Method of an inherent impl: the impl is reachable, and the method name
+    /// is referred to.
+    Method { impl_cond: Box<Cond>, name: String },
+}
+
+impl ItemPruner {
+    /// Create a pruner, with items referred to by the given identifiers (of
+    /// the solution) being reachable.
+    pub fn new(roots: &HashSet<String>) -> Self {
+        Self {
+            defined: HashSet::new(),
+            live: roots.clone(),
+            renames: Vec::new(),
+            removed: 0,
+        }
+    }
+
+    /// Remove unreachable items from the bundled crates.
+    pub fn prune(&mut self, files: &mut [&mut syn::File]) {
+        let mut nodes = Vec::new();
+        for file in files.iter() {
+            self.collect(&file.items, &mut nodes);
+        }
+
+        // Propagate reachability, until nothing changes.
+        let mut reached = vec![false; nodes.len()];
+        loop {
+            let mut changed = false;
+            for (name, alias) in &self.renames {
+                if self.live.contains(alias) && !self.live.contains(name) {
+                    self.live.insert(name.clone());
+                    changed = true;
+                }
+            }
+            for (node, reached) in nodes.iter().zip(reached.iter_mut()) {
+                if !*reached && self.holds(&node.cond) {
+                    *reached = true;
+                    changed = true;
+                    self.live.extend(node.idents.iter().cloned());
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        for file in files.iter_mut() {
+            self.retain(&mut file.items);
+        }
+    }
+
+    /// Collect conditions of the items, along with the identifiers they refer
+    /// to. Items, that are always kept, refer to their identifiers
+    /// unconditionally.
+    fn collect(&mut self, items: &[syn::Item], nodes: &mut Vec<Node>) {
+        for item in items {
+            match item {
+                syn::Item::Mod(item) => {
+                    if let Some((_, items)) = &item.content {
+                        self.collect(items, nodes);
+                    }
+                }
+                syn::Item::Use(item) => collect_renames(&item.tree, &mut self.renames),
+                syn::Item::Impl(item) => {
+                    let cond = impl_cond(item);
+                    if item.trait_.is_some() {
+                        nodes.push(Node {
+                            cond,
+                            idents: idents(item),
+                        });
+                        continue;
+                    }
+
+                    // Methods of inherent impls are reachable on their own.
+                    let mut header = item.clone();
+                    header.items.retain(|item| prunable_name(item).is_none());
+                    nodes.push(Node {
+                        cond: cond.clone(),
+                        idents: idents(&header),
+                    });
+                    for impl_item in &item.items {
+                        if let Some(name) = prunable_name(impl_item) {
+                            self.defined.insert(name.clone());
+                            nodes.push(Node {
+                                cond: Cond::Method {
+                                    impl_cond: Box::new(cond.clone()),
+                                    name,
+                                },
+                                idents: idents(impl_item),
+                            });
+                        }
+                    }
+                }
+                item => match names(item) {
+                    Some(names) => {
+                        self.defined.insert(names[0].clone());
+                        nodes.push(Node {
+                            cond: Cond::AnyName(names),
+                            idents: idents(item),
+                        });
+                    }
+                    None => self.live.extend(idents(item)),
+                },
+            }
+        }
+    }
+
+    fn holds(&self, cond: &Cond) -> bool {
+        match cond {
+            Cond::AnyName(names) => names.iter().any(|name| self.live.contains(name)),
+            Cond::Impl { self_ty, trait_ } => [self_ty, trait_]
+                .into_iter()
+                .flatten()
+                .all(|name| !self.defined.contains(name) || self.live.contains(name)),
+            Cond::Method { impl_cond, name } => self.holds(impl_cond) && self.live.contains(name),
+        }
+    }
+
+    fn retain(&mut self, items: &mut Vec<syn::Item>) {
+        let before = items.len();
+        items.retain_mut(|item| match item {
+            syn::Item::Mod(item) => {
+                if let Some((_, items)) = &mut item.content {
+                    self.retain(items);
+                }
+                true
+            }
+            syn::Item::Use(item) => self.retain_use(&mut item.tree),
+            syn::Item::Impl(item) => {
+                if !self.holds(&impl_cond(item)) {
+                    return false;
+                }
+                if item.trait_.is_none() {
+                    let before = item.items.len();
+                    item.items.retain(|impl_item| {
+                        prunable_name(impl_item).is_none_or(|name| self.live.contains(&name))
+                    });
+                    self.removed += before - item.items.len();
+                }
+                true
+            }
+            item => names(item).is_none_or(|names| self.holds(&Cond::AnyName(names))),
+        });
+        self.removed += before - items.len();
+    }
+
+    /// Drop imports of removed items, returns whether anything is left.
+    fn retain_use(&self, tree: &mut syn::UseTree) -> bool {
+        match tree {
+            syn::UseTree::Path(path) => self.retain_use(&mut path.tree),
+            syn::UseTree::Name(name) => self.is_kept(&name.ident.to_string()),
+            syn::UseTree::Rename(rename) => self.is_kept(&rename.ident.to_string()),
+            syn::UseTree::Glob(_) => true,
+            syn::UseTree::Group(group) => {
+                group.items = std::mem::take(&mut group.items)
+                    .into_pairs()
+                    .map(|pair| pair.into_value())
+                    .filter_map(|mut tree| self.retain_use(&mut tree).then_some(tree))
+                    .collect();
+                !group.items.is_empty()
+            }
+        }
+    }
+
+    /// Whether the name does not refer to a removed item.
+    fn is_kept(&self, name: &str) -> bool {
+        name == "self" || !self.defined.contains(name) || self.live.contains(name)
+    }
+}
+
+/// Names, the item is referred to by, if it can be removed: the first one is
+/// the name of the item itself.
+fn names(item: &syn::Item) -> Option<Vec<String>> {
+    let ident = match item {
+        syn::Item::Const(item) => &item.ident,
+        syn::Item::Fn(item) => &item.sig.ident,
+        syn::Item::Static(item) => &item.ident,
+        syn::Item::Struct(item) => &item.ident,
+        syn::Item::Type(item) => &item.ident,
+        syn::Item::Union(item) => &item.ident,
+        // Variants may be imported, and referred to without the enum name.
+        syn::Item::Enum(item) => {
+            return Some(
+                std::iter::once(&item.ident)
+                    .chain(item.variants.iter().map(|variant| &variant.ident))
+                    .map(|ident| ident.to_string())
+                    .collect(),
+            );
+        }
+        // Methods are called without naming the trait.
+        syn::Item::Trait(item) => {
+            return Some(
+                std::iter::once(item.ident.to_string())
+                    .chain(item.items.iter().filter_map(|item| match item {
+                        syn::TraitItem::Fn(method) => Some(method.sig.ident.to_string()),
+                        _ => None,
+                    }))
+                    .collect(),
+            );
+        }
+        _ => return None,
+    };
+    Some(vec![ident.to_string()])
+}
+
+/// Name of an inherent impl item, if it can be removed.
+fn prunable_name(item: &syn::ImplItem) -> Option<String> {
+    match item {
+        syn::ImplItem::Fn(method) => Some(method.sig.ident.to_string()),
+        syn::ImplItem::Const(item) => Some(item.ident.to_string()),
+        _ => None,
+    }
+}
+
+fn impl_cond(item: &syn::ItemImpl) -> Cond {
+    let self_ty = match &*item.self_ty {
+        syn::Type::Path(ty) => ty.path.segments.last().map(|s| s.ident.to_string()),
+        _ => None,
+    };
+    let trait_ = item
+        .trait_
+        .as_ref()
+        .and_then(|(_, path, _)| path.segments.last())
+        .map(|segment| segment.ident.to_string());
+    Cond::Impl { self_ty, trait_ }
+}
+
+fn collect_renames(tree: &syn::UseTree, renames: &mut Vec<(String, String)>) {
+    match tree {
+        syn::UseTree::Path(path) => collect_renames(&path.tree, renames),
+        syn::UseTree::Rename(rename) => {
+            renames.push((rename.ident.to_string(), rename.rename.to_string()))
+        }
+        syn::UseTree::Group(group) => {
+            for tree in &group.items {
+                collect_renames(tree, renames);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn idents(tokens: &impl ToTokens) -> HashSet<String> {
+    let mut idents = HashSet::new();
+    collect_idents(tokens.to_token_stream(), &mut idents);
+    idents
+}
@@ -0,0 +1,136 @@
+use std::env;
+
+/// Replacement for redacted strings that have no explicit replacement.
+const REDACTED: &str = "[redacted]";
+
+/// Strings to be redacted from the bundled output, before it is written.
+///
+/// Local absolute paths (project root and home directory) are always
+/// redacted, while additional strings (e.g. usernames in comments) can be
+/// configured as `from` (replaced with `[redacted]`) or `from=to` pairs.
+///
+/// Only comments and string literals are redacted, so that code (e.g. an
+/// identifier, containing a redacted string) is left intact.
+#[derive(Debug, Clone, Default)]
+pub struct Redactions {
+    custom: Vec<(String, String)>,
+}
+
+impl Redactions {
+    pub fn new(custom: &[String]) -> Self {
+        let custom = custom
+            .iter()
+            .filter(|s| !s.is_empty())
+            .map(|s| match s.split_once('=') {
+                Some((from, to)) => (from.to_string(), to.to_string()),
+                None => (s.to_string(), REDACTED.to_string()),
+            })
+            .collect();
+        Self { custom }
+    }
+
+    /// Apply all redactions to the comments and string literals of the given
+    /// content.
+    ///
+    /// Longer strings are replaced first, so that the project root is
+    /// redacted before the home directory it is (normally) located in.
+    pub fn apply(&self, root_path: &str, content: &str) -> String {
+        let mut replacements = self.custom.clone();
+        replacements.push((root_path.to_string(), ".".to_string()));
+        if let Some(home) = env::var_os("HOME").filter(|h| h.len() > 1) {
+            replacements.push((home.to_string_lossy().into_owned(), "~".to_string()));
+        }
+        replacements.sort_by_key(|(from, _)| std::cmp::Reverse(from.len()));
+
+        map_comments_and_literals(content, |text| {
+            replacements
+                .iter()
+                .fold(text.to_string(), |text, (from, to)| text.replace(from, to))
+        })
+    }
+}
+
+/// Replace comments and string (and char) literals of the Rust source with
+/// the results of `f`, leaving the code in between as is.
+fn map_comments_and_literals(source: &str, f: impl Fn(&str) -> String) -> String {
+    let bytes = source.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_' || !b.is_ascii();
+    let mut out = String::with_capacity(source.len());
+    let (mut code, mut i) = (0, 0);
+    while i < bytes.len() {
+        let start = i;
+        let end = match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                source[i..].find('\n').map_or(bytes.len(), |len| i + len)
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                // Block comments nest.
+                let (mut depth, mut j) = (0, i);
+                while j < bytes.len() {
+                    if bytes[j..].starts_with(b"/*") {
+                        depth += 1;
+                        j += 2;
+                    } else if bytes[j..].starts_with(b"*/") {
+                        depth -= 1;
+                        j += 2;
+                        if depth == 0 {
+                            break;
+                        }
+                    } else {
+                        j += 1;
+                    }
+                }
+                j
+            }
+            b'"' => quoted_end(bytes, i, b'"'),
+            // Char literals, rather than lifetimes (e.g. `'a`).
+            b'\'' if bytes.get(i + 1) == Some(&b'\\') || bytes.get(i + 2) == Some(&b'\'') => {
+                quoted_end(bytes, i, b'\'')
+            }
+            b if is_ident(b) && (i == 0 || !is_ident(bytes[i - 1])) => {
+                let mut j = i;
+                while j < bytes.len() && is_ident(bytes[j]) {
+                    j += 1;
+                }
+                // Raw strings, e.g. `r#"..."#` or `br"..."`.
+                let hashes = bytes[j..].iter().take_while(|&&b| b == b'#').count();
+                if matches!(&source[i..j], "r" | "br" | "cr")
+                    && bytes.get(j + hashes) == Some(&b'"')
+                {
+                    let closing = [b"\"".as_slice(), &vec![b'#'; hashes]].concat();
+                    let body = j + hashes + 1;
+                    bytes[body..]
+                        .windows(closing.len())
+                        .position(|window| window == closing)
+                        .map_or(bytes.len(), |pos| body + pos + closing.len())
+                } else {
+                    i = j;
+                    continue;
+                }
+            }
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        out.push_str(&source[code..start]);
+        out.push_str(&f(&source[start..end]));
+        (code, i) = (end, end);
+    }
+    out.push_str(&source[code..]);
+    out
+}
+
+/// End of the literal, starting with the quote at `start` (escaped quotes
+/// don't end it).
+fn quoted_end(bytes: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 2,
+            b if b == quote => return i + 1,
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
@@ -0,0 +1,112 @@
+use {
+    crate::cmd::bundle::warnings::{Lint, Warnings},
+    anyhow::{Context, Result, anyhow},
+    proc_macro2::{Group, TokenStream, TokenTree},
+    std::collections::{BTreeSet, HashMap},
+    syn::visit_mut::VisitMut,
+};
+
+/// Identifiers to be renamed in the bundled output.
+///
+/// Some judges (or their checkers) choke on certain identifiers, e.g. `main`
+/// within modules, or non-ASCII identifiers. Renames are configured as
+/// `from=to` pairs, and applied consistently to both the solution and the
+/// bundled library code, including macro bodies.
+#[derive(Debug, Clone, Default)]
+pub struct Renames {
+    map: HashMap<String, String>,
+}
+
+impl Renames {
+    pub fn new(specs: &[String]) -> Result<Self> {
+        let mut map = HashMap::new();
+        for spec in specs {
+            let (from, to) = spec
+                .split_once('=')
+                .ok_or_else(|| anyhow!("invalid rename {spec:?}, expected `from=to`"))?;
+            for ident in [from, to] {
+                syn::parse_str::<syn::Ident>(ident)
+                    .context(format!("invalid identifier {ident:?} in rename {spec:?}"))?;
+            }
+            map.insert(from.to_string(), to.to_string());
+        }
+        Ok(Self { map })
+    }
+
+    /// Rename identifiers in the file.
+    ///
+    /// The entry point (`fn main` at the root of the solution) is never
+    /// renamed, even if other `main` identifiers are.
+    ///
+    /// Non-ASCII identifiers, left in the file after renaming, are reported.
+    pub fn apply(&self, file: &mut syn::File, is_binary: bool, warnings: &mut Warnings) {
+        let mut renamer = Renamer {
+            map: &self.map,
+            non_ascii: BTreeSet::new(),
+        };
+        for item in &mut file.items {
+            match item {
+                syn::Item::Fn(item) if is_binary && item.sig.ident == "main" => {
+                    renamer.visit_block_mut(&mut item.block);
+                }
+                item => renamer.visit_item_mut(item),
+            }
+        }
+        for ident in renamer.non_ascii {
+            warnings.push(
+                Lint::NonAsciiIdent,
+                format!("`{ident}` is not ASCII, use `--rename` if the judge rejects it"),
+            );
+        }
+    }
+}
+
+struct Renamer<'a> {
+    map: &'a HashMap<String, String>,
+    non_ascii: BTreeSet<String>,
+}
+
+impl Renamer<'_> {
+    fn rename(&mut self, ident: &mut proc_macro2::Ident) {
+        let name = ident.to_string();
+        match self.map.get(&name) {
+            Some(to) => *ident = proc_macro2::Ident::new(to, ident.span()),
+            None if !name.is_ascii() => {
+                self.non_ascii.insert(name);
+            }
+            None => {}
+        }
+    }
+
+    /// Macro arguments and bodies are not parsed, so identifiers within them
+    /// are renamed on the token level.
+    fn rename_tokens(&mut self, tokens: TokenStream) -> TokenStream {
+        tokens
+            .into_iter()
+            .map(|token| match token {
+                TokenTree::Ident(mut ident) => {
+                    self.rename(&mut ident);
+                    TokenTree::Ident(ident)
+                }
+                TokenTree::Group(group) => {
+                    let mut renamed =
+                        Group::new(group.delimiter(), self.rename_tokens(group.stream()));
+                    renamed.set_span(group.span());
+                    TokenTree::Group(renamed)
+                }
+                token => token,
+            })
+            .collect()
+    }
+}
+
+impl VisitMut for Renamer<'_> {
+    fn visit_ident_mut(&mut self, ident: &mut proc_macro2::Ident) {
+        self.rename(ident);
+    }
+
+    fn visit_macro_mut(&mut self, node: &mut syn::Macro) {
+        syn::visit_mut::visit_macro_mut(self, node);
+        node.tokens = self.rename_tokens(std::mem::take(&mut node.tokens));
+    }
+}
@@ -0,0 +1,134 @@
+use {
+    std::path::{Path, PathBuf},
+    syn::{parse_quote, spanned::Spanned},
+};
+
+/// Prefix of the marker doc comments, injected before items prior to
+/// unparsing, to find out where the items ended up in the output.
+const MARKER: &str = "@@algorist-src:";
+
+/// Maps lines of the bundled file back to the original sources.
+///
+/// Items (and methods) are mapped precisely, while lines within them are
+/// mapped by their offset from the item start (which is approximate, as the
+/// output is reformatted).
+#[derive(Debug, Default)]
+pub struct SourceMap {
+    /// Original location (file and line) of each marked item.
+    origins: Vec<(PathBuf, usize)>,
+
+    /// Output lines (1-based) where marked items start, along with the
+    /// indices of their origins.
+    lines: Vec<(usize, usize)>,
+
+    /// Bytes of the output, scanned for line breaks so far, and the number of
+    /// lines in them (so that large outputs are not re-scanned on every write).
+    scanned: usize,
+    scanned_lines: usize,
+}
+
+impl SourceMap {
+    /// Mark items, parsed from the given file (items, marked already, e.g. the
+    /// included ones, are skipped).
+    pub fn mark_items(&mut self, items: &mut [syn::Item], file: &Path) {
+        for item in items {
+            let attrs = item_attrs(item);
+            if attrs.is_some_and(|attrs| attrs.first().is_some_and(Self::is_marker)) {
+                continue;
+            }
+            match item {
+                // Module content is marked when its own file is processed.
+                syn::Item::Mod(_) => continue,
+                syn::Item::Impl(item) => {
+                    for impl_item in &mut item.items {
+                        if let syn::ImplItem::Fn(method) = impl_item {
+                            let marker = self.marker(file, method.span().start().line);
+                            method.attrs.insert(0, marker);
+                        }
+                    }
+                }
+                _ => {}
+            }
+            let marker = self.marker(file, item.span().start().line);
+            if let Some(attrs) = item_attrs(item) {
+                attrs.insert(0, marker);
+            }
+        }
+    }
+
+    /// Write unparsed code with marked items into the output, stripping the
+    /// markers and recording the lines the items start at.
+    ///
+    /// Code is followed by an empty line, separating it from whatever is
+    /// written next.
+    pub fn emit(&mut self, out: &mut Vec<u8>, code: &str) {
+        // Account for anything written directly into the output (e.g. header).
+        let mut line = self.scanned_lines
+            + out[self.scanned.min(out.len())..]
+                .iter()
+                .filter(|&&b| b == b'\n')
+                .count();
+        out.reserve(code.len() + 1);
+        for text in code.lines() {
+            if let Some(origin) = text
+                .trim_start()
+                .strip_prefix("///")
+                .and_then(|doc| doc.trim_start().strip_prefix(MARKER))
+                .and_then(|idx| idx.parse::<usize>().ok())
+            {
+                self.lines.push((line + 1, origin));
+                continue;
+            }
+            out.extend_from_slice(text.as_bytes());
+            out.push(b'\n');
+            line += 1;
+        }
+        out.push(b'\n');
+        self.scanned = out.len();
+        self.scanned_lines = line + 1;
+    }
+
+    /// Original location of the given (1-based) line of the bundled file.
+    pub fn resolve(&self, line: usize) -> Option<(&Path, usize)> {
+        let idx = self.lines.partition_point(|&(start, _)| start <= line);
+        let (start, origin) = self.lines.get(idx.checked_sub(1)?)?;
+        let (file, origin_line) = &self.origins[*origin];
+        Some((file, origin_line + (line - start)))
+    }
+
+    /// Whether the attribute is a marker (which must survive attribute
+    /// filtering of the enclosing modules).
+    pub fn is_marker(attr: &syn::Attribute) -> bool {
+        matches!(
+            &attr.meta,
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: syn::Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(doc), .. }),
+                ..
+            }) if attr.path().is_ident("doc") && doc.value().starts_with(MARKER)
+        )
+    }
+
+    fn marker(&mut self, file: &Path, line: usize) -> syn::Attribute {
+        let marker = format!("{MARKER}{}", self.origins.len());
+        self.origins.push((file.to_path_buf(), line));
+        parse_quote!(#[doc = #marker])
+    }
+}
+
+fn item_attrs(item: &mut syn::Item) -> Option<&mut Vec<syn::Attribute>> {
+    Some(match item {
+        syn::Item::Const(item) => &mut item.attrs,
+        syn::Item::Enum(item) => &mut item.attrs,
+        syn::Item::ExternCrate(item) => &mut item.attrs,
+        syn::Item::Fn(item) => &mut item.attrs,
+        syn::Item::Impl(item) => &mut item.attrs,
+        syn::Item::Macro(item) => &mut item.attrs,
+        syn::Item::Static(item) => &mut item.attrs,
+        syn::Item::Struct(item) => &mut item.attrs,
+        syn::Item::Trait(item) => &mut item.attrs,
+        syn::Item::Type(item) => &mut item.attrs,
+        syn::Item::Union(item) => &mut item.attrs,
+        syn::Item::Use(item) => &mut item.attrs,
+        _ => return None,
+    })
+}
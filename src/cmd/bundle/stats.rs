@@ -0,0 +1,49 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Timings and file counts of the bundling phases.
+#[derive(Debug, Default)]
+pub struct BundleStats {
+    phases: Vec<PhaseStats>,
+}
+
+#[derive(Debug)]
+struct PhaseStats {
+    name: &'static str,
+    elapsed: Duration,
+    files: usize,
+}
+
+impl BundleStats {
+    /// Record a completed phase, started at `started`, which has read `files`
+    /// source files.
+    pub fn record(&mut self, name: &'static str, started: Instant, files: usize) {
+        self.phases.push(PhaseStats {
+            name,
+            elapsed: started.elapsed(),
+            files,
+        });
+    }
+}
+
+impl fmt::Display for BundleStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let phases = self
+            .phases
+            .iter()
+            .map(|phase| match phase.files {
+                0 => format!("{} {:.1?}", phase.name, phase.elapsed),
+                1 => format!("{} {:.1?} (1 file)", phase.name, phase.elapsed),
+                n => format!("{} {:.1?} ({n} files)", phase.name, phase.elapsed),
+            })
+            .collect::<Vec<_>>();
+        let total = self
+            .phases
+            .iter()
+            .map(|phase| phase.elapsed)
+            .sum::<Duration>();
+        write!(f, "Timings: {}; total {total:.1?}", phases.join(", "))
+    }
+}
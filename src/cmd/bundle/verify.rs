@@ -0,0 +1,319 @@
+use {
+    crate::cmd::bundle::phases::utils::{
+        extract_imported_names,
+        extract_imported_paths,
+        glob_imported_paths,
+        paths_in_tokens,
+    },
+    std::collections::{BTreeSet, HashSet},
+    syn::visit::Visit,
+};
+
+/// Crates, always available on the judge.
+const BUILTIN_CRATES: [&str; 3] = ["std", "core", "alloc"];
+
+/// Names, always in scope: primitive types, and the types and traits of the
+/// standard prelude (which paths may start with, e.g. `Vec::new()`).
+const PRELUDE: [&str; 55] = [
+    "bool",
+    "char",
+    "str",
+    "u8",
+    "u16",
+    "u32",
+    "u64",
+    "u128",
+    "usize",
+    "i8",
+    "i16",
+    "i32",
+    "i64",
+    "i128",
+    "isize",
+    "f32",
+    "f64",
+    "Option",
+    "Some",
+    "None",
+    "Result",
+    "Ok",
+    "Err",
+    "Vec",
+    "String",
+    "Box",
+    "ToString",
+    "ToOwned",
+    "Iterator",
+    "IntoIterator",
+    "DoubleEndedIterator",
+    "ExactSizeIterator",
+    "Extend",
+    "FromIterator",
+    "Default",
+    "Clone",
+    "Copy",
+    "Send",
+    "Sync",
+    "Sized",
+    "Unpin",
+    "Drop",
+    "Fn",
+    "FnMut",
+    "FnOnce",
+    "From",
+    "Into",
+    "TryFrom",
+    "TryInto",
+    "AsRef",
+    "AsMut",
+    "PartialEq",
+    "Eq",
+    "PartialOrd",
+    "Ord",
+];
+
+/// Scope, names of which are unknown: glob imports bring in whatever the
+/// imported module has.
+const GLOB: &str = "*";
+
+/// Find references to code, that is not part of the bundled file.
+///
+/// Judges compile the file on its own, so everything it refers to must either
+/// be defined within it, or come from `std`, `core` or `alloc` (or be one of
+/// the explicitly `allowed` crates). Imports, `extern crate` declarations,
+/// and paths in code and macro invocations (e.g. `rand::random::<u64>()`, or
+/// `crate::`-rooted ones, which are rewritten when crates are bundled) are
+/// checked.
+///
+/// Returns descriptions of the offending references.
+pub fn external_references(file: &syn::File, allowed: &HashSet<String>) -> Vec<String> {
+    let mut verifier = Verifier {
+        allowed,
+        root: item_names(&file.items),
+        scopes: Vec::new(),
+        generics: HashSet::new(),
+        violations: BTreeSet::new(),
+    };
+    verifier.visit_file(file);
+    verifier.violations.into_iter().collect()
+}
+
+struct Verifier<'a> {
+    allowed: &'a HashSet<String>,
+
+    /// Names, defined at the root of the file.
+    root: HashSet<String>,
+
+    /// Names in scope, along with whether the scope is a module (names of the
+    /// outer scopes are not visible in nested modules).
+    scopes: Vec<(HashSet<String>, bool)>,
+
+    /// Generic parameters (e.g. `T` of `T::default()`), declared so far.
+    generics: HashSet<String>,
+
+    violations: BTreeSet<String>,
+}
+
+impl Verifier<'_> {
+    fn is_builtin(&self, name: &str) -> bool {
+        BUILTIN_CRATES.contains(&name) || self.allowed.contains(name)
+    }
+
+    /// Whether the name is visible from the current scope, up to the nearest
+    /// module.
+    fn in_scope(&self, name: &str) -> bool {
+        for (names, is_module) in self.scopes.iter().rev() {
+            if names.contains(name) || names.contains(GLOB) {
+                return true;
+            }
+            if *is_module {
+                break;
+            }
+        }
+        false
+    }
+
+    /// Check the path of code (or of a macro invocation): its first segment
+    /// must be in scope, when followed by `::`.
+    fn check_path(&mut self, segments: &[String]) {
+        if let [first, _, ..] = segments
+            && !matches!(first.as_str(), "crate" | "self" | "super" | "Self")
+            && !self.is_builtin(first)
+            && !PRELUDE.contains(&first.as_str())
+            && !self.generics.contains(first)
+            && !self.in_scope(first)
+        {
+            self.violations.insert(format!(
+                "`{}` refers to `{first}`, which is neither bundled nor in scope",
+                segments.join("::")
+            ));
+        }
+        self.check_crate_path(segments);
+    }
+
+    fn check_crate_path(&mut self, segments: &[String]) {
+        if let [first, second, ..] = segments
+            && first == "crate"
+            && !self.root.contains(second)
+        {
+            self.violations.insert(format!(
+                "`crate::{second}` is not defined in the bundled file"
+            ));
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Verifier<'_> {
+    fn visit_file(&mut self, node: &'ast syn::File) {
+        self.scopes.push((self.root.clone(), true));
+        syn::visit::visit_file(self, node);
+        self.scopes.pop();
+    }
+
+    fn visit_item_mod(&mut self, node: &'ast syn::ItemMod) {
+        let names = node
+            .content
+            .as_ref()
+            .map(|(_, items)| item_names(items))
+            .unwrap_or_default();
+        self.scopes.push((names, true));
+        syn::visit::visit_item_mod(self, node);
+        self.scopes.pop();
+    }
+
+    fn visit_block(&mut self, node: &'ast syn::Block) {
+        let items = node
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                syn::Stmt::Item(item) => Some(item.clone()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        self.scopes.push((item_names(&items), false));
+        syn::visit::visit_block(self, node);
+        self.scopes.pop();
+    }
+
+    fn visit_item_extern_crate(&mut self, node: &'ast syn::ItemExternCrate) {
+        let name = node.ident.to_string();
+        if !self.is_builtin(&name) && name != "self" {
+            self.violations.insert(format!(
+                "`extern crate {name}` refers to a crate, which is not bundled"
+            ));
+        }
+    }
+
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        for path in extract_imported_paths(&node.tree, Vec::new()) {
+            let Some(first) = path.first() else {
+                continue;
+            };
+            let resolved = if node.leading_colon.is_some() {
+                self.is_builtin(first)
+            } else {
+                matches!(first.as_str(), "crate" | "self" | "super")
+                    || self.is_builtin(first)
+                    || self.in_scope(first)
+            };
+            if !resolved {
+                self.violations.insert(format!(
+                    "`use {}` refers to `{first}`, which is neither bundled nor in scope",
+                    path.join("::")
+                ));
+            }
+            self.check_crate_path(&path);
+        }
+    }
+
+    fn visit_path(&mut self, node: &'ast syn::Path) {
+        let segments = node
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        if node.leading_colon.is_some() {
+            if let Some(first) = segments.first()
+                && !self.is_builtin(first)
+            {
+                self.violations.insert(format!(
+                    "`::{}` refers to a crate, which is not bundled",
+                    segments.join("::")
+                ));
+            }
+        } else {
+            self.check_path(&segments);
+        }
+        syn::visit::visit_path(self, node);
+    }
+
+    fn visit_macro(&mut self, node: &'ast syn::Macro) {
+        // Arguments of macros are not parsed, paths within them are checked as
+        // tokens.
+        for path in paths_in_tokens(node.tokens.clone()) {
+            self.check_path(&path);
+        }
+        syn::visit::visit_macro(self, node);
+    }
+
+    fn visit_item_macro(&mut self, node: &'ast syn::ItemMacro) {
+        // Bodies of macro definitions refer to metavariables and `$crate`, they are
+        // checked where the macros are invoked.
+        if !node.mac.path.is_ident("macro_rules") {
+            syn::visit::visit_item_macro(self, node);
+        }
+    }
+
+    fn visit_attribute(&mut self, _node: &'ast syn::Attribute) {
+        // Attributes refer to tools (e.g. `rustfmt::skip`), rather than code.
+    }
+
+    fn visit_type_param(&mut self, node: &'ast syn::TypeParam) {
+        self.generics.insert(node.ident.to_string());
+        syn::visit::visit_type_param(self, node);
+    }
+}
+
+/// Names, introduced into the scope by the items.
+fn item_names(items: &[syn::Item]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    for item in items {
+        let ident = match item {
+            syn::Item::Const(item) => &item.ident,
+            syn::Item::Enum(item) => &item.ident,
+            syn::Item::Fn(item) => &item.sig.ident,
+            syn::Item::Mod(item) => &item.ident,
+            syn::Item::Static(item) => &item.ident,
+            syn::Item::Struct(item) => &item.ident,
+            syn::Item::Trait(item) => &item.ident,
+            syn::Item::TraitAlias(item) => &item.ident,
+            syn::Item::Type(item) => &item.ident,
+            syn::Item::Union(item) => &item.ident,
+            syn::Item::Macro(syn::ItemMacro {
+                ident: Some(ident), ..
+            }) => ident,
+            syn::Item::ExternCrate(item) => match &item.rename {
+                Some((_, rename)) => rename,
+                None => &item.ident,
+            },
+            syn::Item::Use(item) => {
+                if !glob_imported_paths(&item.tree, Vec::new()).is_empty() {
+                    names.insert(GLOB.to_string());
+                }
+                // `use std::io::{self}` brings `io` into scope.
+                names.extend(
+                    extract_imported_names(&item.tree, Vec::new())
+                        .into_iter()
+                        .filter_map(|(path, name)| match name.as_str() {
+                            "self" => path.iter().nth_back(1).cloned(),
+                            _ => Some(name),
+                        }),
+                );
+                continue;
+            }
+            _ => continue,
+        };
+        names.insert(ident.to_string());
+    }
+    names
+}
@@ -0,0 +1,82 @@
+use std::fmt;
+
+/// Kinds of issues, reported by the bundler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lint {
+    /// Source file is not valid UTF-8.
+    InvalidUtf8,
+
+    /// Module (or library) file, declared in the crate, is not found.
+    MissingModule,
+
+    /// `#[cfg(...)]` attribute is dropped from the library code, so the item
+    /// is included unconditionally.
+    CfgDropped,
+
+    /// Solution depends on a crate, which is not bundled.
+    ExternalCrate,
+
+    /// Identifier is not ASCII.
+    NonAsciiIdent,
+
+    /// Bundled file is larger than most judges accept.
+    SizeLimit,
+
+    /// Build script sets `cfg`s or environment variables, which are not
+    /// carried over into the bundled file.
+    BuildScript,
+
+    /// Library re-exports a module with a glob (`pub use module::*`), which
+    /// is not found in the project crates, so the names it brings in are not
+    /// tracked.
+    GlobReexport,
+
+    /// Bundled file seems to refer to code, which is not bundled (the check
+    /// is heuristic, e.g. it doesn't see macro-generated items).
+    ExternalReference,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Lint::InvalidUtf8 => "invalid-utf8",
+            Lint::MissingModule => "missing-module",
+            Lint::CfgDropped => "cfg-dropped",
+            Lint::ExternalCrate => "external-crate",
+            Lint::NonAsciiIdent => "non-ascii-ident",
+            Lint::SizeLimit => "size-limit",
+            Lint::BuildScript => "build-script",
+            Lint::GlobReexport => "glob-reexport",
+            Lint::ExternalReference => "external-reference",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Warnings, collected during bundling, and reported at the end of it.
+#[derive(Debug, Default)]
+pub struct Warnings(Vec<(Lint, String)>);
+
+impl Warnings {
+    pub fn push(&mut self, lint: Lint, message: impl Into<String>) {
+        self.0.push((lint, message.into()));
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Display for Warnings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Warnings ({}):", self.0.len())?;
+        for (lint, message) in &self.0 {
+            writeln!(f, "  warning[{lint}]: {message}")?;
+        }
+        Ok(())
+    }
+}
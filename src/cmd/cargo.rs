@@ -0,0 +1,51 @@
+use {
+    crate::cmd::jobs::Jobs,
+    std::{env, ffi::OsString, process::Command, sync::OnceLock},
+};
+
+/// Flags, passed to every cargo invocation (e.g. `--offline`), as given to the
+/// tool.
+static FLAGS: OnceLock<Vec<&'static str>> = OnceLock::new();
+
+/// Pass `--offline` and/or `--locked` to every cargo invocation of the running
+/// command.
+pub fn set_flags(offline: bool, locked: bool) {
+    let flags = [(offline, "--offline"), (locked, "--locked")]
+        .into_iter()
+        .filter_map(|(on, flag)| on.then_some(flag))
+        .collect();
+    FLAGS.set(flags).ok();
+}
+
+/// Cargo binary: the one of `CARGO` (set by cargo for its subcommands, and by
+/// wrappers, e.g. `cross`), or `cargo` otherwise.
+pub fn program() -> OsString {
+    env::var_os("CARGO")
+        .filter(|cargo| !cargo.is_empty())
+        .unwrap_or_else(|| "cargo".into())
+}
+
+/// Command, running the cargo subcommand (e.g. `build`), with the flags given
+/// to the tool, and with the limits applied (`--jobs` included).
+pub fn command(subcommand: &str, jobs: Jobs) -> Command {
+    let mut cmd = jobs.command(program());
+    cmd.arg(subcommand);
+    with_flags(cmd, jobs)
+}
+
+/// Like [`command`], but with the nightly toolchain, for subcommands that
+/// require it (e.g. `miri run`).
+///
+/// The toolchain is selected by rustup's `cargo` proxy: `CARGO` refers to the
+/// binary of a specific toolchain, so it is not used.
+pub fn nightly_command(subcommand: &[&str], jobs: Jobs) -> Command {
+    let mut cmd = jobs.command("cargo");
+    cmd.arg("+nightly").args(subcommand);
+    with_flags(cmd, jobs)
+}
+
+fn with_flags(mut cmd: Command, jobs: Jobs) -> Command {
+    cmd.args(FLAGS.get().into_iter().flatten())
+        .args(jobs.cargo_args());
+    cmd
+}
@@ -0,0 +1,210 @@
+use syn::{Token, punctuated::Punctuated};
+
+/// Configuration predicate, as in `#[cfg(...)]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Cfg {
+    /// Configuration option, e.g. `test`.
+    Name(String),
+
+    /// Key-value configuration option, e.g. `feature = "slow"`.
+    KeyValue(String, String),
+
+    All(Vec<Cfg>),
+    Any(Vec<Cfg>),
+    Not(Box<Cfg>),
+}
+
+impl Cfg {
+    /// Parse the predicate, `None` if it is malformed.
+    pub fn parse(meta: &syn::Meta) -> Option<Self> {
+        match meta {
+            syn::Meta::Path(path) => Some(Cfg::Name(path.get_ident()?.to_string())),
+            syn::Meta::NameValue(nv) => match &nv.value {
+                syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(value),
+                    ..
+                }) => Some(Cfg::KeyValue(
+                    nv.path.get_ident()?.to_string(),
+                    value.value(),
+                )),
+                _ => None,
+            },
+            syn::Meta::List(list) => {
+                let args = list
+                    .parse_args_with(Punctuated::<syn::Meta, Token![,]>::parse_terminated)
+                    .ok()?
+                    .iter()
+                    .map(Cfg::parse)
+                    .collect::<Option<Vec<_>>>()?;
+                match list.path.get_ident()?.to_string().as_str() {
+                    "all" => Some(Cfg::All(args)),
+                    "any" => Some(Cfg::Any(args)),
+                    "not" if args.len() == 1 => Some(Cfg::Not(Box::new(args.into_iter().next()?))),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// Predicate, an item with the given attributes is compiled under: all of
+    /// its `cfg` attributes, including the ones applied by `cfg_attr`.
+    ///
+    /// Malformed predicates are left out, so they never decide the outcome.
+    pub fn of_attrs(attrs: &[syn::Attribute]) -> Self {
+        Cfg::All(
+            attrs
+                .iter()
+                .flat_map(|attr| conditions(&attr.meta))
+                .collect(),
+        )
+    }
+
+    /// Evaluate the predicate, given values of (some of) the options.
+    ///
+    /// Returns `None`, if the outcome depends on options of unknown value.
+    pub fn eval(&self, value: &impl Fn(&Cfg) -> Option<bool>) -> Option<bool> {
+        match self {
+            Cfg::Name(_) | Cfg::KeyValue(..) => value(self),
+            Cfg::All(cfgs) => {
+                let mut holds = Some(true);
+                for cfg in cfgs {
+                    match cfg.eval(value) {
+                        Some(false) => return Some(false),
+                        None => holds = None,
+                        Some(true) => {}
+                    }
+                }
+                holds
+            }
+            Cfg::Any(cfgs) => {
+                let mut holds = Some(false);
+                for cfg in cfgs {
+                    match cfg.eval(value) {
+                        Some(true) => return Some(true),
+                        None => holds = None,
+                        Some(false) => {}
+                    }
+                }
+                holds
+            }
+            Cfg::Not(cfg) => cfg.eval(value).map(|holds| !holds),
+        }
+    }
+}
+
+/// Whether an item with the given attributes is only compiled in tests, e.g.
+/// `#[cfg(test)]` or `#[cfg(all(test, feature = "slow"))]`.
+pub fn is_test_only(attrs: &[syn::Attribute]) -> bool {
+    let not_testing = |cfg: &Cfg| matches!(cfg, Cfg::Name(name) if name == "test").then_some(false);
+    Cfg::of_attrs(attrs).eval(&not_testing) == Some(false)
+}
+
+/// Conditions, imposed by the attribute.
+///
+/// `#[cfg_attr(predicate, cfg(condition))]` only imposes the condition if the
+/// predicate holds, i.e. it is equivalent to `#[cfg(any(not(predicate),
+/// condition))]`.
+fn conditions(meta: &syn::Meta) -> Vec<Cfg> {
+    let syn::Meta::List(list) = meta else {
+        return Vec::new();
+    };
+    if list.path.is_ident("cfg") {
+        return list
+            .parse_args::<syn::Meta>()
+            .ok()
+            .and_then(|meta| Cfg::parse(&meta))
+            .into_iter()
+            .collect();
+    }
+    if !list.path.is_ident("cfg_attr") {
+        return Vec::new();
+    }
+    let Ok(args) = list.parse_args_with(Punctuated::<syn::Meta, Token![,]>::parse_terminated)
+    else {
+        return Vec::new();
+    };
+    let mut args = args.iter();
+    let Some(predicate) = args.next().and_then(Cfg::parse) else {
+        return Vec::new();
+    };
+    args.flat_map(conditions)
+        .map(|condition| Cfg::Any(vec![Cfg::Not(Box::new(predicate.clone())), condition]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use {super::*, syn::parse_quote};
+
+    fn test_only(attr: syn::Attribute) -> bool {
+        is_test_only(&[attr])
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(
+            Cfg::parse(&parse_quote!(all(test, not(feature = "slow")))),
+            Some(Cfg::All(vec![
+                Cfg::Name("test".into()),
+                Cfg::Not(Box::new(Cfg::KeyValue("feature".into(), "slow".into()))),
+            ]))
+        );
+        assert_eq!(Cfg::parse(&parse_quote!(not(test, unix))), None);
+        assert_eq!(Cfg::parse(&parse_quote!(feature = 1)), None);
+        assert_eq!(Cfg::parse(&parse_quote!(nand(test))), None);
+        assert_eq!(Cfg::parse(&parse_quote!(std::test)), None);
+    }
+
+    #[test]
+    fn eval() {
+        let value = |cfg: &Cfg| match cfg {
+            Cfg::Name(name) if name == "unix" => Some(true),
+            Cfg::Name(name) if name == "test" => Some(false),
+            _ => None,
+        };
+        let eval = |meta: syn::Meta| Cfg::parse(&meta).unwrap().eval(&value);
+        assert_eq!(eval(parse_quote!(all(unix, not(test)))), Some(true));
+        assert_eq!(eval(parse_quote!(all(unix, miri))), None);
+        assert_eq!(eval(parse_quote!(all(test, miri))), Some(false));
+        assert_eq!(eval(parse_quote!(any(unix, miri))), Some(true));
+        assert_eq!(eval(parse_quote!(any(test, miri))), None);
+        assert_eq!(eval(parse_quote!(not(miri))), None);
+    }
+
+    #[test]
+    fn test_only_items() {
+        assert!(test_only(parse_quote!(#[cfg(test)])));
+        assert!(test_only(parse_quote!(#[cfg(all(test, feature = "slow"))])));
+        assert!(test_only(parse_quote!(#[cfg(not(not(test)))])));
+        assert!(!test_only(parse_quote!(#[cfg(not(test))])));
+        assert!(!test_only(
+            parse_quote!(#[cfg(any(test, feature = "slow"))])
+        ));
+        assert!(!test_only(parse_quote!(#[cfg(feature = "slow")])));
+        assert!(!test_only(parse_quote!(#[inline])));
+    }
+
+    #[test]
+    fn test_only_cfg_attr() {
+        // The condition is only imposed in tests, so the item is compiled otherwise.
+        assert!(!test_only(
+            parse_quote!(#[cfg_attr(test, cfg(feature = "slow"))])
+        ));
+        assert!(test_only(parse_quote!(#[cfg_attr(not(test), cfg(test))])));
+        // Depends on an option of unknown value, so the item may be compiled.
+        assert!(!test_only(parse_quote!(#[cfg_attr(unix, cfg(test))])));
+        assert!(!test_only(parse_quote!(#[cfg_attr(test, derive(Debug))])));
+    }
+
+    #[test]
+    fn malformed_predicates_are_ignored() {
+        assert!(!test_only(parse_quote!(#[cfg(not(test, unix))])));
+        assert!(!test_only(
+            parse_quote!(#[cfg_attr(not(test, unix), cfg(test))])
+        ));
+        assert!(is_test_only(&[
+            parse_quote!(#[cfg(nand(test))]),
+            parse_quote!(#[cfg(test)]),
+        ]));
+    }
+}
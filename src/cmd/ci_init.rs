@@ -0,0 +1,49 @@
+use {
+    crate::cmd::{SubCmd, TPL_DIR, config::setting},
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{fs, path::Path},
+};
+
+/// Workflow file, relative to the project root.
+const WORKFLOW_FILE: &str = ".github/workflows/algorist.yml";
+
+/// Generate a GitHub Actions workflow, testing and bundling all problems on
+/// every push.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "ci-init")]
+pub struct CiInitSubCmd {
+    #[argh(switch)]
+    /// overwrite the existing workflow file
+    force: bool,
+}
+
+impl SubCmd for CiInitSubCmd {
+    fn run(&self) -> Result<()> {
+        let target = Path::new(WORKFLOW_FILE);
+        if target.exists() && !self.force {
+            return Err(anyhow!(
+                "workflow file {target:?} already exists, use `--force` to overwrite it"
+            ));
+        }
+
+        // Workflow comes from the configured template (or the built-in one).
+        let template = match setting("ci_template")? {
+            Some(template) => fs::read_to_string(&template)
+                .context(format!("failed to read workflow template {template}"))?,
+            None => TPL_DIR
+                .get_file("ci.yml")
+                .and_then(|file| file.contents_utf8())
+                .context("workflow template should exist")?
+                .to_string(),
+        };
+        let content = template.replace("{{TOOL_VERSION}}", env!("CARGO_PKG_VERSION"));
+
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).context("failed to create workflows directory")?;
+        }
+        fs::write(target, content).context(format!("failed to write {WORKFLOW_FILE}"))?;
+        println!("Workflow written into {WORKFLOW_FILE}, commit and push it to enable CI");
+        Ok(())
+    }
+}
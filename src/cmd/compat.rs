@@ -0,0 +1,110 @@
+use {
+    anyhow::{Context, Result},
+    std::{fs, path::Path},
+};
+
+/// Project file, stamped with the version of the tool that created the
+/// project, relative to the project root.
+pub const PROJECT_FILE: &str = "algorist.toml";
+
+/// Comment, explaining the purpose of the project file.
+const STAMP_COMMENT: &str =
+    "# Created by cargo-algorist, used to detect projects with an outdated layout.";
+
+/// Version of this tool.
+const TOOL_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Write the version stamp into a newly created project.
+pub fn write_stamp(root: &Path) -> Result<()> {
+    let content = format!("{STAMP_COMMENT}\n[tool]\nversion = \"{TOOL_VERSION}\"\n");
+    fs::write(root.join(PROJECT_FILE), content).context(format!("failed to write {PROJECT_FILE}"))
+}
+
+/// Report, if the project at the given root was created by a significantly
+/// different version of the tool, along with the steps to migrate it.
+///
+/// Incompatibilities are only reported, commands are still run.
+pub fn check(root: &Path) {
+    // Not in a project (e.g. `create` is run elsewhere), nothing to check.
+    if !root.join("Cargo.toml").exists() {
+        return;
+    }
+
+    let stamp = fs::read_to_string(root.join(PROJECT_FILE))
+        .ok()
+        .and_then(|content| content.parse::<toml::Value>().ok())
+        .and_then(|value| {
+            value
+                .get("tool")?
+                .get("version")?
+                .as_str()
+                .map(String::from)
+        });
+    let Some(version) = stamp else {
+        report_older(root, "an older version");
+        return;
+    };
+    let (Some(project), Some(tool)) = (release(&version), release(TOOL_VERSION)) else {
+        return;
+    };
+    if project > tool {
+        println!(
+            "Warning: project was created by cargo-algorist {version}, which is newer than this \
+             one ({TOOL_VERSION}), some of its files may not be understood. Upgrade with `cargo \
+             install cargo-algorist`."
+        );
+    } else if project < tool {
+        report_older(root, &format!("cargo-algorist {version}"));
+    }
+}
+
+fn report_older(root: &Path, created_by: &str) {
+    let mut steps = migration_steps(root);
+    steps.push(format!(
+        "set `version = \"{TOOL_VERSION}\"` in the `[tool]` section of `{PROJECT_FILE}` (create \
+         it if missing)"
+    ));
+    println!(
+        "Warning: project was created by {created_by}, its layout may differ from the one \
+         expected by cargo-algorist {TOOL_VERSION}. To upgrade the project:"
+    );
+    for (i, step) in steps.iter().enumerate() {
+        println!("  {}. {step}", i + 1);
+    }
+}
+
+/// Steps, required to bring the project layout up to date.
+fn migration_steps(root: &Path) -> Vec<String> {
+    let mut steps = Vec::new();
+    let manifest = fs::read_to_string(root.join("Cargo.toml"))
+        .ok()
+        .and_then(|content| content.parse::<toml::Value>().ok());
+    let has_local_feature = manifest
+        .as_ref()
+        .and_then(|value| value.get("features")?.get("local"))
+        .is_some();
+    if !has_local_feature {
+        steps.push(
+            "add the `local` feature to `Cargo.toml`: `[features]` section with `default = \
+             [\"local\"]` and `local = []`"
+                .to_string(),
+        );
+    }
+    if !fs::read_to_string(root.join(".gitignore")).is_ok_and(|content| {
+        content
+            .lines()
+            .any(|line| line.trim().trim_end_matches('/') == ".algorist")
+    }) {
+        steps.push("add `.algorist/` to `.gitignore`".to_string());
+    }
+    steps
+}
+
+/// Release of a version: major and minor numbers for `0.x` versions, and only
+/// the major one otherwise (per semver, patches are always compatible).
+fn release(version: &str) -> Option<(u64, u64)> {
+    let mut parts = version.split('.').map(|part| part.parse::<u64>().ok());
+    let major = parts.next()??;
+    let minor = parts.next()??;
+    Some(if major == 0 { (0, minor) } else { (major, 0) })
+}
@@ -0,0 +1,444 @@
+use {
+    crate::cmd::{SubCmd, compat::PROJECT_FILE},
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        env,
+        fmt,
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Location of the user configuration, relative to the configuration
+/// directory (`$XDG_CONFIG_HOME`, or `~/.config`).
+const USER_CONFIG: &str = "algorist/config.toml";
+
+/// Configurable setting.
+#[derive(Debug)]
+pub struct Setting {
+    pub key: &'static str,
+    pub help: &'static str,
+
+    /// Allowed values, any value is allowed if empty.
+    pub values: &'static [&'static str],
+
+    /// Built-in default, if any.
+    pub default: Option<&'static str>,
+}
+
+/// Known settings, in the order they are asked for by `setup`.
+pub const SETTINGS: &[Setting] = &[
+    Setting {
+        key: "judge",
+        help: "preferred judge, e.g. `codeforces` or `atcoder`",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "judge_profile",
+        help: "`[profile.release]` overrides of the bundled project, on top of the judge's \
+               defaults, e.g. `opt-level=3,lto=fat,codegen-units=1`",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "handle",
+        help: "your handle on the judge, used as the author in bundle headers (passwords are \
+               never stored)",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "template",
+        help: "path to the problem file template, used by `create` and `add` instead of the \
+               built-in one",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "solve_module",
+        help: "whether `create` and `add` put the solution into a companion module, \
+               `src/bin/<id>/solve.rs`, with a `solve(scan, w)` function to unit test, the \
+               problem file only calling it: `on` or `off`",
+        values: &["on", "off"],
+        default: Some("off"),
+    },
+    Setting {
+        key: "library",
+        help: "path to `Cargo.toml` of your algorithms library, used by `create` when no \
+               `--manifest-path` is given",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "inline_deps",
+        help: "comma-separated crates, vendored from crates.io, that `bundle` inlines like the \
+               library crates, e.g. `rand,itoa`",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "keep_attrs",
+        help: "comma-separated attributes, `bundle` retains in the library code, as with \
+               `--keep-attr`, e.g. `doc,cfg`",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "drop_attrs",
+        help: "comma-separated attributes, `bundle` drops from the library code, as with \
+               `--drop-attr`, e.g. `inline,must_use`",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "bundle_check",
+        help: "whether `bundle` verifies that bundled files compile, as with `--check` (`check`) \
+               or `--check-release` (`release`): `check`, `release` or `off`",
+        values: &["check", "release", "off"],
+        default: Some("off"),
+    },
+    Setting {
+        key: "bundle_header",
+        help: "whether `bundle` prepends the submission header, as with `--header`: `on` or `off`",
+        values: &["on", "off"],
+        default: Some("off"),
+    },
+    Setting {
+        key: "jobs",
+        help: "maximum number of parallel jobs of `test` and `bundle`: compiler processes, and \
+               test cases run at once (by default, builds use all CPUs, and cases run one at a \
+               time)",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "nice",
+        help: "niceness of the processes `test` and `bundle` spawn, from -20 to 19, e.g. `10` to \
+               keep the machine responsive during long runs",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "target",
+        help: "target `run` and `test` build solutions for, e.g. `x86_64-unknown-linux-musl` (as \
+               on the judge), or `i686-unknown-linux-gnu` (to test 32-bit overflow behavior)",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "target_runner",
+        help: "command, binaries of a non-native `target` are run with, e.g. `qemu-i386` (cargo's \
+               `CARGO_TARGET_<TRIPLE>_RUNNER` is used, if not set)",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "vcs",
+        help: "version control system, queried e.g. for the author and library commits in bundle \
+               headers: `auto` (detected, Jujutsu is preferred in repositories colocated with \
+               git), `git` or `jj`",
+        values: &["auto", "git", "jj"],
+        default: Some("auto"),
+    },
+    Setting {
+        key: "editor",
+        help: "command to open problem files with, once they are added, e.g. `code` or `vim`",
+        values: &[],
+        default: None,
+    },
+    Setting {
+        key: "clipboard",
+        help: "whether to copy bundled files into the clipboard: `copy` or `off`",
+        values: &["copy", "off"],
+        default: Some("off"),
+    },
+    Setting {
+        key: "save_output",
+        help: "whether `run` saves the output into `outputs/{id}.txt`, as with `--save-output`: \
+               `on` or `off`",
+        values: &["on", "off"],
+        default: Some("off"),
+    },
+    Setting {
+        key: "telemetry",
+        help: "whether to record durations and failures of commands into a local metrics file \
+               (nothing is sent anywhere), see `stats --tool`: `on` or `off`",
+        values: &["on", "off"],
+        default: Some("off"),
+    },
+    Setting {
+        key: "ci_template",
+        help: "path to the GitHub Actions workflow template, used by `ci-init` instead of the \
+               built-in one (`{{TOOL_VERSION}}` is replaced with the tool version)",
+        values: &[],
+        default: None,
+    },
+];
+
+impl Setting {
+    pub fn find(key: &str) -> Result<&'static Setting> {
+        SETTINGS
+            .iter()
+            .find(|setting| setting.key == key)
+            .ok_or_else(|| {
+                anyhow!(
+                    "unknown setting {key:?}, known ones are: {}",
+                    SETTINGS
+                        .iter()
+                        .map(|setting| setting.key)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )
+            })
+    }
+
+    pub fn validate(&self, value: &str) -> Result<()> {
+        if !self.values.is_empty() && !self.values.contains(&value) {
+            return Err(anyhow!(
+                "invalid value {value:?} for {:?}, expected one of: {}",
+                self.key,
+                self.values.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Layer of the configuration, a setting value comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// Project's `algorist.toml`.
+    Project,
+
+    /// User's `config.toml`.
+    User,
+
+    /// Built-in default.
+    Default,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Project => write!(f, "project"),
+            Source::User => write!(f, "user"),
+            Source::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// Configuration file, with settings as top-level string keys.
+///
+/// Other keys and tables (e.g. the version stamp of `algorist.toml`) are kept
+/// intact, as are the leading comments.
+#[derive(Debug)]
+pub struct ConfigFile {
+    path: PathBuf,
+    comments: String,
+    values: toml::Table,
+}
+
+impl ConfigFile {
+    /// Open the user configuration (an empty one, if it doesn't exist yet).
+    pub fn user() -> Result<Self> {
+        let path = user_config_path().context("neither XDG_CONFIG_HOME nor HOME is set")?;
+        Self::open(&path)
+    }
+
+    /// Open the configuration of the project at the given root (an empty one,
+    /// if it doesn't exist yet).
+    pub fn project(root: &Path) -> Result<Self> {
+        Self::open(&root.join(PROJECT_FILE))
+    }
+
+    /// Open the configuration file (an empty one, if it doesn't exist yet).
+    pub fn open(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).unwrap_or_default();
+        let values = content
+            .parse::<toml::Table>()
+            .context(format!("failed to parse {}", path.display()))?;
+        let comments = content
+            .lines()
+            .take_while(|line| line.starts_with('#'))
+            .map(|line| format!("{line}\n"))
+            .collect();
+        Ok(Self {
+            path: path.to_path_buf(),
+            comments,
+            values,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key)?.as_str()
+    }
+
+    pub fn set(&mut self, key: &str, value: Option<&str>) {
+        match value {
+            Some(value) => self
+                .values
+                .insert(key.to_string(), toml::Value::from(value)),
+            None => self.values.remove(key),
+        };
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+        }
+        let content = toml::to_string(&self.values).context("failed to serialize config")?;
+        fs::write(&self.path, format!("{}{content}", self.comments))
+            .context(format!("failed to write {}", self.path.display()))
+    }
+}
+
+/// Value of a setting, if configured.
+///
+/// Settings are resolved from the project's `algorist.toml`, then from the
+/// user configuration, and then from the built-in defaults (command line
+/// flags, where available, take precedence over all of them).
+pub fn setting(key: &str) -> Result<Option<String>> {
+    Ok(resolve(Path::new("."), key)?.map(|(value, _)| value))
+}
+
+/// Value of a setting, along with the layer it comes from.
+pub fn resolve(root: &Path, key: &str) -> Result<Option<(String, Source)>> {
+    let project = root.join(PROJECT_FILE);
+    if project.exists()
+        && let Some(value) = ConfigFile::open(&project)?.get(key)
+    {
+        return Ok(Some((value.to_string(), Source::Project)));
+    }
+    if let Some(path) = user_config_path()
+        && let Some(value) = ConfigFile::open(&path)?.get(key)
+    {
+        return Ok(Some((value.to_string(), Source::User)));
+    }
+    let default = Setting::find(key)?.default;
+    Ok(default.map(|value| (value.to_string(), Source::Default)))
+}
+
+fn user_config_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(dir.join(USER_CONFIG))
+}
+
+/// Inspect and edit settings.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "config")]
+pub struct ConfigSubCmd {
+    #[argh(subcommand)]
+    nested: ConfigCmd,
+}
+
+#[derive(FromArgs)]
+#[argh(subcommand)]
+enum ConfigCmd {
+    Get(GetSubCmd),
+    Set(SetSubCmd),
+    Unset(UnsetSubCmd),
+    List(ListSubCmd),
+}
+
+/// Print the value of a setting.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "get")]
+struct GetSubCmd {
+    #[argh(positional)]
+    /// setting key
+    key: String,
+}
+
+/// Set the value of a setting.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "set")]
+struct SetSubCmd {
+    #[argh(positional)]
+    /// setting key
+    key: String,
+
+    #[argh(positional)]
+    /// setting value
+    value: String,
+
+    #[argh(switch)]
+    /// set in the project's `algorist.toml`, instead of the user
+    /// configuration
+    project: bool,
+}
+
+/// Remove a setting.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "unset")]
+struct UnsetSubCmd {
+    #[argh(positional)]
+    /// setting key
+    key: String,
+
+    #[argh(switch)]
+    /// remove from the project's `algorist.toml`, instead of the user
+    /// configuration
+    project: bool,
+}
+
+/// List all settings, along with where their values come from.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "list")]
+struct ListSubCmd {}
+
+impl SubCmd for ConfigSubCmd {
+    fn run(&self) -> Result<()> {
+        let root = Path::new(".");
+        match &self.nested {
+            ConfigCmd::Get(cmd) => match resolve(root, &cmd.key)? {
+                Some((value, _)) => println!("{value}"),
+                None => return Err(anyhow!("{:?} is not set", cmd.key)),
+            },
+            ConfigCmd::Set(cmd) => {
+                Setting::find(&cmd.key)?.validate(&cmd.value)?;
+                let mut config = layer(cmd.project)?;
+                config.set(&cmd.key, Some(&cmd.value));
+                config.save()?;
+                println!("Set {:?} in {}", cmd.key, config.path().display());
+            }
+            ConfigCmd::Unset(cmd) => {
+                Setting::find(&cmd.key)?;
+                let mut config = layer(cmd.project)?;
+                config.set(&cmd.key, None);
+                config.save()?;
+                println!("Unset {:?} in {}", cmd.key, config.path().display());
+            }
+            ConfigCmd::List(_) => {
+                for setting in SETTINGS {
+                    match resolve(root, setting.key)? {
+                        Some((value, source)) => {
+                            println!("{} = {value:?} ({source})", setting.key)
+                        }
+                        None => println!("{} is not set", setting.key),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Configuration file to edit: either the project or the user one.
+fn layer(project: bool) -> Result<ConfigFile> {
+    if !project {
+        return ConfigFile::user();
+    }
+    if !Path::new("Cargo.toml").exists() {
+        return Err(anyhow!("not in a project directory"));
+    }
+    ConfigFile::project(Path::new("."))
+}
@@ -0,0 +1,229 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        api::library_crates,
+        problem::resolve_id,
+        target,
+        test::runner::{BuildOptions, TestCase, TestRunner, Verdict, build},
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// Report which modules of the library are exercised by the test cases of a
+/// problem.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "coverage")]
+pub struct CoverageSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+}
+
+/// Line coverage of a library module.
+struct ModuleCoverage {
+    /// Module path, e.g. `mylib::math::primes`.
+    path: String,
+    lines: u64,
+    covered: u64,
+}
+
+impl SubCmd for CoverageSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+        let cases = TestCase::discover(&PathBuf::from("tests").join(&id))?;
+        if cases.is_empty() {
+            return Err(anyhow!(
+                "no test cases found, add `<case>.in` and `<case>.out` files to tests/{id}"
+            ));
+        }
+        let (profdata, cov) = (llvm_tool("llvm-profdata")?, llvm_tool("llvm-cov")?);
+
+        // Profiles of the previous runs would be merged into the report otherwise.
+        let profiles = Path::new("target")
+            .join("coverage")
+            .join("profiles")
+            .join(&id);
+        if profiles.exists() {
+            fs::remove_dir_all(&profiles).context("failed to remove old profiles")?;
+        }
+        fs::create_dir_all(&profiles).context("failed to create profiles directory")?;
+
+        let exe = build(&id, &BuildOptions {
+            coverage: Some(profiles.clone()),
+            ..Default::default()
+        })?;
+        println!("Running {} test case(s) for problem {id:?}", cases.len());
+        let results = TestRunner::new(&id)
+            .cases(cases)
+            .coverage(profiles.clone())
+            .run(|_| {})?;
+        let failed = results
+            .iter()
+            .filter(|result| result.verdict != Verdict::Passed)
+            .count();
+        if failed > 0 {
+            println!("Note: {failed} case(s) failed, their runs are counted nevertheless");
+        }
+
+        let mut raw = Vec::new();
+        for entry in fs::read_dir(&profiles).context("failed to read profiles directory")? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "profraw") {
+                raw.push(path);
+            }
+        }
+        if raw.is_empty() {
+            return Err(anyhow!("no coverage profiles written by the runs"));
+        }
+        let merged = profiles.join("merged.profdata");
+        run_tool(
+            Command::new(&profdata)
+                .args(["merge", "-sparse", "-o"])
+                .arg(&merged)
+                .args(&raw),
+        )?;
+        let export = run_tool(
+            Command::new(&cov)
+                .args(["export", "-summary-only", "-instr-profile"])
+                .arg(&merged)
+                .arg(&exe),
+        )?;
+
+        let modules = library_coverage(&export)?;
+        if modules.is_empty() {
+            println!("No library code is compiled into the solution of problem {id:?}");
+            return Ok(());
+        }
+        print_report(&modules);
+        Ok(())
+    }
+}
+
+/// Coverage of the library modules, as found in the report of `llvm-cov
+/// export`.
+fn library_coverage(export: &str) -> Result<Vec<ModuleCoverage>> {
+    let report: serde_json::Value =
+        serde_json::from_str(export).context("failed to parse coverage report")?;
+    let crates = library_crates(Path::new("."))?
+        .into_iter()
+        .filter_map(|(name, dir)| Some((name, dir.join("src").canonicalize().ok()?)))
+        .collect::<Vec<_>>();
+
+    let mut modules = Vec::new();
+    let files = report["data"][0]["files"].as_array().into_iter().flatten();
+    for file in files {
+        let Some(filename) = file["filename"].as_str() else {
+            continue;
+        };
+        let filename = Path::new(filename);
+        let Some((name, relative)) = crates
+            .iter()
+            .find_map(|(name, src)| Some((name, filename.strip_prefix(src).ok()?)))
+        else {
+            continue;
+        };
+        let lines = &file["summary"]["lines"];
+        modules.push(ModuleCoverage {
+            path: module_path(name, relative),
+            lines: lines["count"].as_u64().unwrap_or_default(),
+            covered: lines["covered"].as_u64().unwrap_or_default(),
+        });
+    }
+    modules.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(modules)
+}
+
+/// Module path of the source file, relative to the `src` directory of the
+/// crate, e.g. `math/primes.rs` (or `math/primes/mod.rs`) is `math::primes`.
+fn module_path(crate_name: &str, relative: &Path) -> String {
+    let mut path = vec![crate_name.to_string()];
+    for component in relative.with_extension("").components() {
+        path.push(component.as_os_str().to_string_lossy().into_owned());
+    }
+    if matches!(path.last().map(String::as_str), Some("lib" | "mod")) && path.len() > 1 {
+        path.pop();
+    }
+    path.join("::")
+}
+
+fn print_report(modules: &[ModuleCoverage]) {
+    let width = modules.iter().map(|module| module.path.len()).max();
+    let width = width.unwrap_or_default().max("module".len());
+    println!("\n{:<width$}{:>16}{:>8}", "module", "lines", "%");
+    for module in modules {
+        let note = if module.covered == 0 {
+            "  untested"
+        } else {
+            ""
+        };
+        println!(
+            "{:<width$}{:>16}{:>8}{note}",
+            module.path,
+            format!("{}/{}", module.covered, module.lines),
+            percent(module.covered, module.lines)
+        );
+    }
+
+    let lines = modules.iter().map(|module| module.lines).sum();
+    let covered = modules.iter().map(|module| module.covered).sum();
+    let untested = modules.iter().filter(|module| module.covered == 0).count();
+    println!(
+        "\n{covered} of {lines} library line(s) exercised ({}%), {untested} of {} module(s) \
+         untested",
+        percent(covered, lines),
+        modules.len()
+    );
+}
+
+fn percent(part: u64, total: u64) -> u64 {
+    part * 100 / total.max(1)
+}
+
+/// Path to the LLVM tool: the one of the `llvm-tools` component of the active
+/// toolchain (matching the LLVM version of the compiler), or the one in `PATH`.
+fn llvm_tool(name: &str) -> Result<PathBuf> {
+    let sysroot = Command::new("rustc")
+        .args(["--print", "sysroot"])
+        .output()
+        .ok()
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string());
+    if let (Some(sysroot), Some(host)) = (sysroot, target::host()) {
+        let path = Path::new(&sysroot)
+            .join("lib")
+            .join("rustlib")
+            .join(host)
+            .join("bin")
+            .join(name);
+        if path.exists() {
+            return Ok(path);
+        }
+    }
+    // Older versions of `llvm-profdata` fail with `--version`, so only the lookup
+    // counts.
+    if Command::new(name).arg("--version").output().is_err() {
+        return Err(anyhow!(
+            "{name} is not installed, install it with `rustup component add llvm-tools`"
+        ));
+    }
+    Ok(PathBuf::from(name))
+}
+
+/// Run the LLVM tool, returning its output.
+fn run_tool(cmd: &mut Command) -> Result<String> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let output = cmd.output().context(format!("failed to run {program}"))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{program} failed (its LLVM version should match the compiler's, see `rustup \
+             component add llvm-tools`):\n{}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
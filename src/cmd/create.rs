@@ -1,5 +1,19 @@
 use {
-    crate::cmd::{GITIGNORE, RUSTFMT_TOML, SubCmd, TPL_DIR, copy, copy_to},
+    crate::cmd::{
+        GITIGNORE,
+        RUSTFMT_TOML,
+        SubCmd,
+        TPL_DIR,
+        cargo,
+        compat::write_stamp,
+        config::setting,
+        copy,
+        copy_to,
+        jobs::Jobs,
+        manifest::{Dependency, Manifest, enclosing_workspace},
+        meta::{Event, record_events},
+        problem::write_template,
+    },
     anyhow::{Context, Result, anyhow},
     argh::FromArgs,
     serde_json::json,
@@ -31,6 +45,12 @@ pub struct CreateContestSubCmd {
     /// no problems will be added to the contest, use `add` command to add
     /// problems later
     empty: bool,
+
+    #[argh(switch)]
+    /// pin the published `algorist` dependency to the exact vendored version,
+    /// so that the project builds (and bundles) the same way offline, even if
+    /// the crate is updated or yanked later
+    offline_template_snapshot: bool,
 }
 
 impl SubCmd for CreateContestSubCmd {
@@ -49,51 +69,77 @@ impl SubCmd for CreateContestSubCmd {
         fs::create_dir_all(src_dir)?;
 
         // Copy template files into the contest directory.
-        self.create_project(&target_dir)
+        // Library crate is either given explicitly, or configured user-wide.
+        let manifest_path = match &self.manifest_path {
+            Some(path) => Some(path.clone()),
+            None => setting("library")?,
+        };
+        self.create_project(&target_dir, manifest_path.as_deref())
             .context("failed to copy template files")?;
+        write_stamp(&target_dir)?;
 
         // Vendor dependencies using `cargo vendor`.
         self.cargo_vendor(&target_dir)
             .context("failed to run cargo vendor")?;
 
+        // Pin the exact version of the vendored `algorist` crate, if requested.
+        if self.offline_template_snapshot {
+            if manifest_path.is_some() {
+                println!("Custom library crate is used, no `algorist` version to pin.");
+            } else {
+                pin_algorist_version(&target_dir).context("failed to pin algorist version")?;
+            }
+        }
+
         println!("New contest created at {target_dir:?}");
         Ok(())
     }
 }
 
 impl CreateContestSubCmd {
-    fn create_project(&self, target: &Path) -> std::io::Result<()> {
+    fn create_project(&self, target: &Path, manifest_path: Option<&str>) -> Result<()> {
         // Copy the necessary library files for contest project.
         println!("Copying template files to the contest directory...");
         copy(&TPL_DIR, ".cargo/**/*", &target.join(""))?;
-        copy_to(&TPL_DIR, "Cargo.toml.tpl", &target.join("Cargo.toml"))?;
 
         // Update the Cargo.toml, inject either path to crate with algorithms and data
         // structures, or select the version of `algorist` crate to use.
         println!("Injecting algorithms library crate into Cargo.toml...");
-        let cargo_toml = target.join("Cargo.toml");
-        let mut content = fs::read_to_string(&cargo_toml)?;
-        if let Some((crate_name, crate_path)) =
-            external_crate(target, self.manifest_path.as_deref())?
-        {
+        let mut manifest = Manifest::template()?;
+        if let Some((crate_name, crate_path)) = external_crate(target, manifest_path)? {
             println!(
                 "- Using external crate: {:?} ({:?})",
                 crate_name, crate_path
             );
-            let import_line = format!(
-                "{crate_name} = {{ path = \"{}\" }}",
-                // if/when `cargo vendor` supports paths, use `crate_path.to_string_lossy()`
-                format!("crates/{}", crate_name)
-            );
-            content = content.replace("{{EXTERNAL_CRATE}}", &import_line);
+            // if/when `cargo vendor` supports paths, use `crate_path.to_string_lossy()`
+            manifest
+                .set_dependency(
+                    &crate_name,
+                    &Dependency::path(format!("crates/{crate_name}")),
+                )
+                .context("failed to update Cargo.toml template")?;
         } else {
             println!("- Using `algorist` crate from crates.io.");
-            content = content.replace(
-                "{{EXTERNAL_CRATE}}",
-                format!("algorist = \"{}\"", ALGORIST_VERSION).as_str(),
+            manifest
+                .set_dependency(
+                    "algorist",
+                    &Dependency::Version(ALGORIST_VERSION.to_string()),
+                )
+                .context("failed to update Cargo.toml template")?;
+        }
+        // Within a workspace, cargo would treat the contest project as a (missing)
+        // member, failing to build or vendor it.
+        if let Some(workspace) = enclosing_workspace(target) {
+            println!(
+                "- Contest is created within the cargo workspace at {workspace:?}, isolating it \
+                 with an empty `[workspace]` table."
+            );
+            manifest.isolate_workspace(
+                "Contest project is not a member of the enclosing workspace, but a workspace of \
+                 its own.",
             );
         }
-        fs::write(cargo_toml, content)?;
+        manifest.write(&target.join("Cargo.toml"))?;
 
         // Copy files from root directory.
         fs::write(target.join(".gitignore"), GITIGNORE)?;
@@ -102,16 +148,17 @@ impl CreateContestSubCmd {
         // Create files for problems a-h.
         if self.empty {
             // If `empty` flag is set, create a single `main.rs` file.
-            copy_to(&TPL_DIR, "main.rs", &target.join(format!("src/main.rs")))?;
+            copy_to(&TPL_DIR, "main.rs", &target.join("src/main.rs"))?;
         } else {
             println!("Adding problems a-h to the contest...");
             for letter in 'a'..='h' {
-                copy_to(
-                    &TPL_DIR,
-                    "problem.rs",
-                    &target.join(format!("src/bin/{letter}.rs")),
-                )?;
+                write_template(target, &letter.to_string(), None)?;
             }
+            record_events(
+                target,
+                &["a", "b", "c", "d", "e", "f", "g", "h"],
+                Event::Created,
+            );
         }
 
         // Create empty `inputs/{a-h}.txt` or `inputs/input.txt` (when `--empty` flag is
@@ -133,8 +180,7 @@ impl CreateContestSubCmd {
 
     fn cargo_vendor(&self, target: &Path) -> Result<()> {
         println!("Running `cargo vendor` to vendor dependencies...");
-        let status = std::process::Command::new("cargo")
-            .arg("vendor")
+        let status = cargo::command("vendor", Jobs::default())
             .arg("crates")
             .arg("--no-delete")
             .arg("--quiet")
@@ -152,6 +198,31 @@ impl CreateContestSubCmd {
     }
 }
 
+/// Pins the `algorist` dependency in the project's `Cargo.toml` to the exact
+/// version vendored into `crates`.
+fn pin_algorist_version(target: &Path) -> Result<()> {
+    let version = fs::read_dir(target.join("crates"))?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| fs::read_to_string(entry.path().join("Cargo.toml")).ok())
+        .filter_map(|content| content.parse::<toml::Value>().ok())
+        .find_map(|value| {
+            let package = value.get("package")?;
+            (package.get("name")?.as_str()? == "algorist")
+                .then(|| package.get("version")?.as_str().map(|v| v.to_string()))
+                .flatten()
+        })
+        .context("vendored `algorist` crate is not found")?;
+
+    let cargo_toml = target.join("Cargo.toml");
+    let mut manifest = Manifest::read(&cargo_toml)?;
+    manifest
+        .set_dependency("algorist", &Dependency::Version(format!("={version}")))
+        .context(format!("failed to update {}", cargo_toml.display()))?;
+    manifest.write(&cargo_toml)?;
+    println!("Pinned `algorist` to version {version}.");
+    Ok(())
+}
+
 /// Checks the provided manifest path, and copies external crate into the
 /// contest project.
 ///
@@ -264,10 +335,10 @@ fn copy_crate(source: &Path, target: &Path) -> std::io::Result<()> {
         let entry = entry?;
         let path = entry.path();
         let file_name = entry.file_name();
-        if let Some(file_name) = file_name.to_str() {
-            if IGNORED_FILES.contains(&file_name) {
-                continue;
-            }
+        if let Some(file_name) = file_name.to_str()
+            && IGNORED_FILES.contains(&file_name)
+        {
+            continue;
         }
         let target_path = target.join(file_name);
         if path.is_dir() {
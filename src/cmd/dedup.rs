@@ -0,0 +1,289 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        problem::{problem_ids, solution_path},
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    proc_macro2::{Delimiter, TokenStream, TokenTree},
+    quote::ToTokens,
+    std::{
+        collections::{BTreeMap, HashSet, hash_map::DefaultHasher},
+        fs,
+        hash::{Hash, Hasher},
+        path::Path,
+    },
+    syn::{spanned::Spanned, visit::Visit},
+};
+
+/// Length of the token sequences (shingles), blocks are compared by.
+const SHINGLE_LEN: usize = 5;
+
+/// Detect near-identical functions, copied between solutions, worth extracting
+/// into the library.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "dedup")]
+pub struct DedupSubCmd {
+    #[argh(option, default = "80")]
+    /// minimum similarity of the functions, in percent (defaults to 80)
+    threshold: u32,
+
+    #[argh(option, default = "50")]
+    /// minimum size of the functions, in tokens, to compare (defaults to 50)
+    min_tokens: usize,
+}
+
+/// Function (or method) of a solution.
+struct Block {
+    /// Problem ID of the solution.
+    id: String,
+
+    /// Name, e.g. `dfs` or `Dsu::union`.
+    name: String,
+    line: usize,
+    tokens: usize,
+    shingles: HashSet<u64>,
+}
+
+impl SubCmd for DedupSubCmd {
+    fn run(&self) -> Result<()> {
+        if self.threshold > 100 {
+            return Err(anyhow!("threshold is a percentage, got {}", self.threshold));
+        }
+        let mut blocks = Vec::new();
+        for id in problem_ids(Path::new("."))? {
+            let path = solution_path(Path::new("."), &id);
+            let content =
+                fs::read_to_string(&path).context(format!("failed to read {}", path.display()))?;
+            let Ok(file) = syn::parse_file(&content) else {
+                println!("Skipping {}, as it doesn't parse", path.display());
+                continue;
+            };
+            let mut collector = Collector {
+                id: &id,
+                self_ty: None,
+                blocks: Vec::new(),
+            };
+            collector.visit_file(&file);
+            blocks.extend(
+                collector
+                    .blocks
+                    .into_iter()
+                    .filter(|block| block.tokens >= self.min_tokens),
+            );
+        }
+
+        // Only the copies between solutions count: a solution may repeat itself.
+        let mut groups = Groups::new(blocks.len());
+        for i in 0..blocks.len() {
+            for j in i + 1..blocks.len() {
+                if blocks[i].id == blocks[j].id {
+                    continue;
+                }
+                let similarity = similarity(&blocks[i], &blocks[j]);
+                if similarity >= self.threshold {
+                    groups.join(i, j, similarity);
+                }
+            }
+        }
+
+        let groups = groups.into_groups();
+        if groups.is_empty() {
+            println!(
+                "No near-identical functions found across {} solution function(s)",
+                blocks.len()
+            );
+            return Ok(());
+        }
+        for (members, similarity) in &groups {
+            let first = &blocks[members[0]];
+            println!(
+                "\nfn {} ({similarity}% similar or more, {} tokens):",
+                first.name, first.tokens
+            );
+            for &member in members {
+                let block = &blocks[member];
+                println!(
+                    "  {}:{}  fn {}",
+                    solution_path(Path::new("."), &block.id).display(),
+                    block.line,
+                    block.name
+                );
+            }
+        }
+        println!(
+            "\n{} group(s) of near-identical functions found. Consider moving them into the \
+             library (in `crates`), so that the bundler inlines them where used.",
+            groups.len()
+        );
+        Ok(())
+    }
+}
+
+/// Collects the functions of a solution, except for `main` (it follows the
+/// problem template).
+struct Collector<'a> {
+    id: &'a str,
+
+    /// Type of the `impl` block, being visited, if any.
+    self_ty: Option<String>,
+    blocks: Vec<Block>,
+}
+
+impl Collector<'_> {
+    fn push<'ast>(
+        &mut self,
+        name: String,
+        item: &'ast (impl ToTokens + Spanned),
+        visit: impl FnOnce(&mut Bindings<'ast>),
+    ) {
+        let mut bindings = Bindings::default();
+        visit(&mut bindings);
+        let tokens = flatten(item.to_token_stream(), &bindings.names);
+        let shingles = tokens
+            .windows(SHINGLE_LEN.min(tokens.len()).max(1))
+            .map(|window| {
+                let mut hasher = DefaultHasher::new();
+                window.hash(&mut hasher);
+                hasher.finish()
+            })
+            .collect();
+        self.blocks.push(Block {
+            id: self.id.to_string(),
+            name,
+            line: item.span().start().line,
+            tokens: tokens.len(),
+            shingles,
+        });
+    }
+}
+
+impl<'ast> Visit<'ast> for Collector<'_> {
+    fn visit_item_fn(&mut self, node: &'ast syn::ItemFn) {
+        if node.sig.ident != "main" {
+            self.push(node.sig.ident.to_string(), node, |bindings| {
+                bindings.visit_item_fn(node)
+            });
+        }
+        syn::visit::visit_item_fn(self, node);
+    }
+
+    fn visit_item_impl(&mut self, node: &'ast syn::ItemImpl) {
+        let self_ty = node.self_ty.to_token_stream().to_string().replace(' ', "");
+        let outer = self.self_ty.replace(self_ty);
+        syn::visit::visit_item_impl(self, node);
+        self.self_ty = outer;
+    }
+
+    fn visit_impl_item_fn(&mut self, node: &'ast syn::ImplItemFn) {
+        let name = match &self.self_ty {
+            Some(self_ty) => format!("{self_ty}::{}", node.sig.ident),
+            None => node.sig.ident.to_string(),
+        };
+        self.push(name, node, |bindings| bindings.visit_impl_item_fn(node));
+        syn::visit::visit_impl_item_fn(self, node);
+    }
+}
+
+/// Names of the variables (and parameters), bound within a function, in the
+/// order of their bindings.
+#[derive(Default)]
+struct Bindings<'ast> {
+    names: Vec<&'ast syn::Ident>,
+}
+
+impl<'ast> Visit<'ast> for Bindings<'ast> {
+    fn visit_pat_ident(&mut self, node: &'ast syn::PatIdent) {
+        if !self.names.contains(&&node.ident) {
+            self.names.push(&node.ident);
+        }
+        syn::visit::visit_pat_ident(self, node);
+    }
+}
+
+/// Tokens of the stream, with variables replaced by their binding order, and
+/// literals by a placeholder: copies often differ in variable names and
+/// constants only (e.g. limits or moduli).
+fn flatten(stream: TokenStream, bindings: &[&syn::Ident]) -> Vec<String> {
+    let mut tokens = Vec::new();
+    for tree in stream {
+        match tree {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    Delimiter::Parenthesis => ("(", ")"),
+                    Delimiter::Brace => ("{", "}"),
+                    Delimiter::Bracket => ("[", "]"),
+                    Delimiter::None => ("", ""),
+                };
+                tokens.push(open.to_string());
+                tokens.extend(flatten(group.stream(), bindings));
+                tokens.push(close.to_string());
+            }
+            TokenTree::Ident(ident) => match bindings.iter().position(|name| **name == ident) {
+                Some(i) => tokens.push(format!("${i}")),
+                None => tokens.push(ident.to_string()),
+            },
+            TokenTree::Punct(punct) => tokens.push(punct.as_char().to_string()),
+            TokenTree::Literal(_) => tokens.push("<literal>".to_string()),
+        }
+    }
+    tokens
+}
+
+/// Jaccard similarity of the shingles of the blocks, in percent.
+fn similarity(a: &Block, b: &Block) -> u32 {
+    let common = a.shingles.intersection(&b.shingles).count();
+    let all = a.shingles.len() + b.shingles.len() - common;
+    (common * 100 / all.max(1)) as u32
+}
+
+/// Groups of similar blocks: blocks, similar to any member of a group, join it.
+struct Groups {
+    parents: Vec<usize>,
+
+    /// Lowest similarity within the group, by its root.
+    similarity: BTreeMap<usize, u32>,
+}
+
+impl Groups {
+    fn new(len: usize) -> Self {
+        Self {
+            parents: (0..len).collect(),
+            similarity: BTreeMap::new(),
+        }
+    }
+
+    fn root(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
+
+    fn join(&mut self, a: usize, b: usize, similarity: u32) {
+        let (a, b) = (self.root(a), self.root(b));
+        let mut lowest = similarity;
+        for root in [a, b] {
+            if let Some(other) = self.similarity.remove(&root) {
+                lowest = lowest.min(other);
+            }
+        }
+        self.parents[b] = a;
+        self.similarity.insert(a, lowest);
+    }
+
+    /// Members of the groups (of two or more blocks), along with their lowest
+    /// similarity.
+    fn into_groups(mut self) -> Vec<(Vec<usize>, u32)> {
+        let mut members = BTreeMap::<usize, Vec<usize>>::new();
+        for i in 0..self.parents.len() {
+            let root = self.root(i);
+            members.entry(root).or_default().push(i);
+        }
+        members
+            .into_iter()
+            .filter_map(|(root, members)| Some((members, *self.similarity.get(&root)?)))
+            .collect()
+    }
+}
@@ -0,0 +1,274 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        api::{PublicApi, library_crates},
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    proc_macro2::{TokenStream, TokenTree},
+    quote::ToTokens,
+    std::{collections::HashSet, path::Path},
+};
+
+/// Search the library crates by item names, doc comments or signatures.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "doc")]
+pub struct DocSubCmd {
+    #[argh(positional)]
+    /// keywords (e.g. `lca`), or a function signature (e.g.
+    /// `fn(u64, u64) -> u64`)
+    query: String,
+
+    #[argh(option, default = "10")]
+    /// maximum number of results to show (10 by default)
+    limit: usize,
+}
+
+impl SubCmd for DocSubCmd {
+    fn run(&self) -> Result<()> {
+        let index = Index::build(Path::new("."))?;
+        let results = match parse_signature(&self.query) {
+            Some(query) => index.search_signature(&query),
+            None => index.search_keywords(&self.query),
+        };
+        if results.is_empty() {
+            println!("No items match {:?}", self.query);
+            return Ok(());
+        }
+        for entry in results.iter().take(self.limit) {
+            println!("{}", entry.path);
+            println!("    {}", entry.signature);
+            if let Some(line) = entry.doc.lines().next().filter(|line| !line.is_empty()) {
+                println!("    {line}");
+            }
+        }
+        if results.len() > self.limit {
+            println!(
+                "... and {} more (use `--limit` to see them)",
+                results.len() - self.limit
+            );
+        }
+        Ok(())
+    }
+}
+
+/// Public item of a library crate.
+#[derive(Debug)]
+struct Entry {
+    /// Path of the item, with the crate name, e.g. `algorist::math::gcd`.
+    path: String,
+    signature: String,
+    doc: String,
+
+    /// Shape of the signature, for functions and methods.
+    shape: Option<FnShape>,
+}
+
+/// Search index over the public API of the library crates, the project
+/// depends on.
+#[derive(Debug)]
+struct Index(Vec<Entry>);
+
+impl Index {
+    fn build(root: &Path) -> Result<Self> {
+        let crates = library_crates(root)?;
+        if crates.is_empty() {
+            return Err(anyhow!(
+                "the project depends on no library crates in `crates`"
+            ));
+        }
+        let mut entries = Vec::new();
+        for (name, dir) in &crates {
+            let api = PublicApi::collect(dir)
+                .context(format!("failed to collect API of crate {name:?}"))?;
+            for (path, signature, doc) in api.items() {
+                entries.push(Entry {
+                    path: format!("{name}::{path}"),
+                    signature: signature.to_string(),
+                    doc: doc.to_string(),
+                    shape: syn::parse_str::<syn::ItemFn>(&format!("{signature} {{}}"))
+                        .ok()
+                        .map(|item| FnShape::from_signature(&item.sig)),
+                });
+            }
+        }
+        Ok(Self(entries))
+    }
+
+    /// Items, matching all the keywords (in their names, paths, signatures or
+    /// doc comments), the best matches first: name matches weigh more than
+    /// mentions in the docs.
+    fn search_keywords(&self, query: &str) -> Vec<&Entry> {
+        let keywords = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+        let scored = self.0.iter().filter_map(|entry| {
+            let path = entry.path.to_lowercase();
+            let name = path.rsplit("::").next().unwrap_or_default();
+            let name = name.trim_end_matches('!');
+            let signature = entry.signature.to_lowercase();
+            let doc = entry.doc.to_lowercase();
+            keywords
+                .iter()
+                .map(|keyword| {
+                    let keyword = keyword.as_str();
+                    match () {
+                        _ if name == keyword => 8,
+                        _ if name.contains(keyword) => 4,
+                        _ if path.contains(keyword) => 2,
+                        _ if signature.contains(keyword) || doc.contains(keyword) => 1,
+                        _ => 0,
+                    }
+                })
+                .try_fold(0, |total, score| (score > 0).then_some(total + score))
+                .map(|score| (score, entry))
+        });
+        rank(scored)
+    }
+
+    /// Functions and methods, the signature of which matches the query.
+    fn search_signature(&self, query: &FnShape) -> Vec<&Entry> {
+        let scored = self.0.iter().filter_map(|entry| {
+            let shape = entry.shape.as_ref()?;
+            shape.matches(query).map(|score| (score, entry))
+        });
+        rank(scored)
+    }
+}
+
+/// Best scores first, then shorter (i.e. more prominent) paths.
+fn rank<'a>(scored: impl Iterator<Item = (u32, &'a Entry)>) -> Vec<&'a Entry> {
+    let mut scored = scored.collect::<Vec<_>>();
+    scored.sort_by(|(a_score, a), (b_score, b)| {
+        b_score
+            .cmp(a_score)
+            .then(a.path.len().cmp(&b.path.len()))
+            .then(a.path.cmp(&b.path))
+    });
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Rough shape of a function signature: types of the arguments (the receiver
+/// of a method is not counted) and of the result.
+#[derive(Debug)]
+struct FnShape {
+    inputs: Vec<Vec<String>>,
+    output: Vec<String>,
+
+    /// Generic parameters of the function, which match any type.
+    generics: HashSet<String>,
+}
+
+impl FnShape {
+    fn from_signature(sig: &syn::Signature) -> Self {
+        let inputs = sig
+            .inputs
+            .iter()
+            .filter_map(|arg| match arg {
+                syn::FnArg::Typed(arg) => Some(type_tokens(arg.ty.to_token_stream())),
+                syn::FnArg::Receiver(_) => None,
+            })
+            .collect();
+        let generics = sig
+            .generics
+            .type_params()
+            .map(|param| param.ident.to_string())
+            .collect();
+        Self {
+            inputs,
+            output: output_tokens(&sig.output),
+            generics,
+        }
+    }
+
+    /// Score of the match against the query (higher for exact matches), if the
+    /// signature matches it.
+    ///
+    /// Generic parameters (of the function, or of the type the method is
+    /// implemented for, which are recognized as single capital letters) and
+    /// `Self` match any type, and so do `_` and single capital letters in the
+    /// query.
+    fn matches(&self, query: &FnShape) -> Option<u32> {
+        if self.inputs.len() != query.inputs.len() {
+            return None;
+        }
+        let is_generic =
+            |token: &str| token.len() == 1 && token.chars().all(|c| c.is_ascii_uppercase());
+        let is_wildcard =
+            |token: &str| token == "Self" || self.generics.contains(token) || is_generic(token);
+        let type_score = |ty: &[String], query: &[String]| {
+            if ty == query {
+                return Some(2);
+            }
+            let matches = ty.len() == query.len()
+                && ty.iter().zip(query).all(|(ty, query)| {
+                    ty == query || query == "_" || is_generic(query) || is_wildcard(ty)
+                });
+            (matches || query == ["_"]).then_some(1)
+        };
+        self.inputs
+            .iter()
+            .zip(&query.inputs)
+            .chain(std::iter::once((&self.output, &query.output)))
+            .map(|(ty, query)| type_score(ty, query))
+            .sum()
+    }
+}
+
+/// Signature query, e.g. `fn(u64, u64) -> u64`, if the query is one.
+fn parse_signature(query: &str) -> Option<FnShape> {
+    let query = query.trim();
+    if !query.starts_with("fn") {
+        return None;
+    }
+    let bare = syn::parse_str::<syn::TypeBareFn>(query).ok()?;
+    Some(FnShape {
+        inputs: bare
+            .inputs
+            .iter()
+            .map(|arg| type_tokens(arg.ty.to_token_stream()))
+            .collect(),
+        output: output_tokens(&bare.output),
+        generics: HashSet::new(),
+    })
+}
+
+fn output_tokens(output: &syn::ReturnType) -> Vec<String> {
+    match output {
+        syn::ReturnType::Default => vec!["()".to_string()],
+        syn::ReturnType::Type(_, ty) => type_tokens(ty.to_token_stream()),
+    }
+}
+
+/// Tokens of a type, with groups flattened, e.g. `Vec < ( u64 , u64 ) >`.
+fn type_tokens(tokens: TokenStream) -> Vec<String> {
+    let mut flat = Vec::new();
+    for token in tokens {
+        match token {
+            TokenTree::Group(group) => {
+                let (open, close) = match group.delimiter() {
+                    proc_macro2::Delimiter::Parenthesis => ("(", ")"),
+                    proc_macro2::Delimiter::Bracket => ("[", "]"),
+                    proc_macro2::Delimiter::Brace => ("{", "}"),
+                    proc_macro2::Delimiter::None => ("", ""),
+                };
+                flat.push(open.to_string());
+                flat.extend(type_tokens(group.stream()));
+                flat.push(close.to_string());
+            }
+            token => flat.push(token.to_string()),
+        }
+    }
+    flat.retain(|token| !token.is_empty());
+    // `()` is a single token, whichever way it is written.
+    flat.dedup_by(|b, a| {
+        if a == "(" && b == ")" {
+            *a = "()".to_string();
+            true
+        } else {
+            false
+        }
+    });
+    flat
+}
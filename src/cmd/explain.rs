@@ -0,0 +1,238 @@
+use {
+    crate::cmd::SubCmd,
+    anyhow::{Result, anyhow},
+    argh::FromArgs,
+};
+
+/// Explain a judge verdict or a common compile error.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "explain")]
+pub struct ExplainSubCmd {
+    #[argh(positional, greedy)]
+    /// verdict code (e.g. `TLE`, `RE`), or (part of) the error message
+    query: Vec<String>,
+}
+
+impl SubCmd for ExplainSubCmd {
+    fn run(&self) -> Result<()> {
+        let query = self.query.join(" ");
+        let entries = lookup(&query);
+        if entries.is_empty() {
+            let codes = KNOWLEDGE_BASE
+                .iter()
+                .filter_map(|entry| entry.codes.first())
+                .copied()
+                .collect::<Vec<_>>();
+            return Err(anyhow!(
+                "nothing known about {query:?}, known verdicts: {}",
+                codes.join(", ")
+            ));
+        }
+
+        for (i, entry) in entries.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            println!("{}", entry.title);
+            println!("{}", entry.explanation);
+            if !entry.actions.is_empty() {
+                println!("\nWhat to try:");
+                for action in entry.actions {
+                    println!("- {action}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Knowledge base entry.
+#[derive(Debug)]
+pub struct Entry {
+    /// Verdict codes (and their aliases), matched exactly, ignoring case.
+    pub codes: &'static [&'static str],
+
+    /// Fragments of error messages, matched as substrings, ignoring case.
+    pub patterns: &'static [&'static str],
+
+    pub title: &'static str,
+    pub explanation: &'static str,
+
+    /// Suggested actions.
+    pub actions: &'static [&'static str],
+}
+
+/// Find knowledge base entries for a verdict code or an error message.
+///
+/// Exact verdict code match wins, otherwise all entries with a message
+/// pattern contained in the query are returned.
+pub fn lookup(query: &str) -> Vec<&'static Entry> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    if let Some(entry) = KNOWLEDGE_BASE
+        .iter()
+        .find(|entry| entry.codes.iter().any(|code| code.to_lowercase() == query))
+    {
+        return vec![entry];
+    }
+
+    KNOWLEDGE_BASE
+        .iter()
+        .filter(|entry| {
+            entry
+                .patterns
+                .iter()
+                .any(|pattern| query.contains(&pattern.to_lowercase()))
+        })
+        .collect()
+}
+
+pub static KNOWLEDGE_BASE: &[Entry] = &[
+    Entry {
+        codes: &["WA", "wrong-answer", "wrong_answer"],
+        patterns: &["wrong answer"],
+        title: "Wrong Answer",
+        explanation: "The program finished in time, but its output differs from the expected one \
+                      (on a hidden test, if samples pass).",
+        actions: &[
+            "Re-check edge cases: minimal/maximal constraints, equal elements, empty ranges.",
+            "Look for integer overflow: run `algorist test <id>` (debug build has overflow \
+             checks).",
+            "Make sure the output format is right: check the comparator with `algorist test <id> \
+             --cmp exact`.",
+        ],
+    },
+    Entry {
+        codes: &["TLE", "TL", "time-limit-exceeded"],
+        patterns: &["time limit exceeded"],
+        title: "Time Limit Exceeded",
+        explanation: "The program did not finish within the time limit: either the algorithm is \
+                      too slow for the constraints, or it is stuck (infinite loop, waiting for \
+                      input).",
+        actions: &[
+            "Estimate the complexity against the maximal constraints.",
+            "Measure on a maximal test: `algorist test <id> --release --time-limit <ms>`.",
+            "Make sure the output is buffered and flushed once (e.g. `BufWriter`).",
+            "Check for reading more input than provided (waiting on stdin).",
+        ],
+    },
+    Entry {
+        codes: &["MLE", "ML", "memory-limit-exceeded"],
+        patterns: &["memory limit exceeded"],
+        title: "Memory Limit Exceeded",
+        explanation: "The program used more memory than allowed.",
+        actions: &[
+            "Estimate memory of the data structures against the maximal constraints.",
+            "Prefer smaller integer types (`u32` instead of `u64`/`usize`) for large arrays.",
+            "Avoid cloning large collections, and deep recursion keeping big frames.",
+        ],
+    },
+    Entry {
+        codes: &["RE", "RTE", "runtime-error"],
+        patterns: &["runtime error", "panicked at", "stack overflow"],
+        title: "Runtime Error",
+        explanation: "The program terminated abnormally: a panic (index out of bounds, `unwrap` \
+                      on `None`, overflow in debug builds, division by zero), or a stack overflow \
+                      due to deep recursion.",
+        actions: &[
+            "Reproduce locally: `algorist test <id>` shows standard error of failing cases.",
+            "Replace deep recursion with an explicit stack, or run the solution in a thread with \
+             bigger stack.",
+        ],
+    },
+    Entry {
+        codes: &["CE", "compilation-error"],
+        patterns: &["compilation error"],
+        title: "Compilation Error",
+        explanation: "The submitted file does not compile on the judge. Normally, this is caused \
+                      by an older compiler (or edition) on the judge, or by the bundled file \
+                      referring to something that was not included.",
+        actions: &[
+            "Compile the bundled file locally: `algorist bundle <id> --check` (or \
+             `--check-release`) builds it, and maps errors back to the original sources.",
+            "Search for the judge's error message: `algorist explain <message>`.",
+        ],
+    },
+    Entry {
+        codes: &["ILE", "idleness-limit-exceeded"],
+        patterns: &["idleness limit exceeded"],
+        title: "Idleness Limit Exceeded",
+        explanation: "In an interactive problem, the program waited for input that never came: \
+                      usually the output was not flushed after a query.",
+        actions: &["Flush standard output after every query."],
+    },
+    Entry {
+        codes: &["PE", "presentation-error"],
+        patterns: &["presentation error"],
+        title: "Presentation Error",
+        explanation: "The answer is right, but formatting (whitespace, line breaks) is not.",
+        actions: &["Compare outputs exactly: `algorist test <id> --cmp exact`."],
+    },
+    Entry {
+        codes: &[],
+        patterns: &[
+            "edition 2024",
+            "edition2024",
+            "feature `edition2024` is required",
+        ],
+        title: "Edition 2024 is not supported",
+        explanation: "The judge compiles with an older toolchain, which does not support Rust \
+                      2024 edition.",
+        actions: &[
+            "Avoid edition-specific syntax (let chains, `gen` blocks) in the solution and the \
+             library.",
+        ],
+    },
+    Entry {
+        codes: &[],
+        patterns: &[
+            "is unstable",
+            "may not be used on the stable release channel",
+            "use of unstable library feature",
+            "let chains",
+        ],
+        title: "Unstable or too new language feature",
+        explanation: "The code uses a feature (or standard library API), which is not available \
+                      in the judge's compiler version.",
+        actions: &[
+            "Check the judge's Rust version, and replace newer APIs (e.g. `is_some_and`, \
+             `div_ceil`) with older equivalents.",
+        ],
+    },
+    Entry {
+        codes: &[],
+        patterns: &[
+            "can't find crate for",
+            "unresolved import",
+            "failed to resolve",
+        ],
+        title: "Unresolved crate or module",
+        explanation: "The submitted file refers to a crate or module which is not present in it: \
+                      judges do not provide external crates, so everything must be bundled.",
+        actions: &[
+            "Submit the bundled file (`bundled/src/bin/<id>.rs`), not the original one.",
+            "Re-run `algorist bundle <id>`, and check which modules were ignored.",
+        ],
+    },
+    Entry {
+        codes: &[],
+        patterns: &[
+            "source too long",
+            "source code is too long",
+            "file too large",
+        ],
+        title: "Source is too long",
+        explanation: "The bundled file exceeds the judge's source size limit.",
+        actions: &[
+            "Keep documentation out of the bundle (do not use `--keep-attr doc`).",
+            "Drop library items the solution never reaches: `algorist bundle <id> --prune-items`.",
+            "Strip comments and collapse whitespace with `--minify` (add `--minify-mods` to also \
+             shorten module names).",
+            "Split rarely used parts of the library into separate modules, so that they are not \
+             bundled when unused.",
+        ],
+    },
+];
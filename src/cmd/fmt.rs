@@ -0,0 +1,123 @@
+use {
+    crate::cmd::{
+        RUSTFMT_TOML,
+        SubCmd,
+        create::update_checksum_json,
+        problem::{BIN_DIR, problem_ids},
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::{self, Command, ExitStatus},
+    },
+};
+
+/// Format problem files and library crates with `rustfmt`.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "fmt")]
+pub struct FormatProjectSubCmd {
+    #[argh(switch)]
+    /// check formatting without modifying files, fail if any file needs
+    /// formatting
+    check: bool,
+}
+
+impl SubCmd for FormatProjectSubCmd {
+    fn run(&self) -> Result<()> {
+        let mut files = problem_ids(Path::new("."))?
+            .into_iter()
+            .map(|id| Path::new(BIN_DIR).join(format!("{id}.rs")))
+            .collect::<Vec<_>>();
+
+        // Out-of-line modules are followed by `rustfmt`, so it is enough to pass the
+        // crate roots of the libraries.
+        let crates = local_crates(Path::new("crates"))?;
+        files.extend(
+            crates
+                .iter()
+                .map(|path| path.join("src/lib.rs"))
+                .filter(|path| path.exists()),
+        );
+        if files.is_empty() {
+            println!("Nothing to format.");
+            return Ok(());
+        }
+
+        println!(
+            "{} {} file(s)...",
+            if self.check { "Checking" } else { "Formatting" },
+            files.len()
+        );
+        let status = rustfmt(&files, self.check)?;
+
+        if !self.check {
+            // Library crates are vendored, so their checksums must be kept in sync.
+            for path in &crates {
+                update_checksum_json(path)
+                    .context(format!("failed to update checksums for {}", path.display()))?;
+            }
+        }
+
+        if !status.success() {
+            return Err(anyhow!(if self.check {
+                "some files are not formatted, run `cargo algorist fmt`"
+            } else {
+                "rustfmt failed"
+            }));
+        }
+        Ok(())
+    }
+}
+
+/// Run `rustfmt` on the files, with the project's `rustfmt.toml` if it exists,
+/// otherwise with the shipped one.
+pub fn rustfmt(files: &[PathBuf], check: bool) -> Result<ExitStatus> {
+    let mut cmd = Command::new("rustfmt");
+    let fallback_config = if Path::new("rustfmt.toml").exists()
+        || Path::new(".rustfmt.toml").exists()
+    {
+        None
+    } else {
+        let path = std::env::temp_dir().join(format!("algorist-rustfmt-{}.toml", process::id()));
+        fs::write(&path, RUSTFMT_TOML).context("failed to write rustfmt config")?;
+        cmd.arg("--config-path").arg(&path);
+        Some(path)
+    };
+    if check {
+        cmd.arg("--check");
+    }
+    let status = cmd.args(files).status().context("failed to run rustfmt");
+    if let Some(path) = fallback_config {
+        let _ = fs::remove_file(path);
+    }
+    status
+}
+
+/// Returns paths of the library crates, copied into the project (as opposed to
+/// crates vendored from the registry, which must not be modified).
+///
+/// Copied crates have their `.cargo-checksum.json` written without package
+/// checksum.
+pub fn local_crates(crates_dir: &Path) -> Result<Vec<PathBuf>> {
+    if !crates_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut crates = Vec::new();
+    for entry in fs::read_dir(crates_dir)? {
+        let path = entry?.path();
+        let checksum = match fs::read_to_string(path.join(".cargo-checksum.json")) {
+            Ok(content) => content,
+            Err(_) => continue,
+        };
+        let value: serde_json::Value = serde_json::from_str(&checksum)
+            .context(format!("failed to parse checksums of {}", path.display()))?;
+        if value.get("package").is_some_and(|p| p.is_null()) {
+            crates.push(path);
+        }
+    }
+    crates.sort();
+    Ok(crates)
+}
@@ -0,0 +1,430 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        api::{PublicApi, library_crates},
+        problem::{problem_ids, solution_path},
+        upsolve::contest_projects,
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    proc_macro2::{TokenStream, TokenTree},
+    quote::ToTokens,
+    std::{
+        collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+        fs,
+        path::Path,
+    },
+    syn::visit::Visit,
+};
+
+/// First line of the generated prelude, telling it apart from a hand-written
+/// one.
+const MARKER: &str = "//! Generated by `algorist gen-prelude`";
+
+/// Generate (or update) the `prelude` module of the library, re-exporting the
+/// items most commonly used by the solutions.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "gen-prelude")]
+pub struct GenPreludeSubCmd {
+    #[argh(positional, default = "String::from(\".\")")]
+    /// contest project, or directory with (archived) contest projects, to
+    /// collect the usage from (defaults to the current one)
+    dir: String,
+
+    #[argh(option, long = "crate")]
+    /// library crate to generate the prelude for (defaults to the only one)
+    crate_name: Option<String>,
+
+    #[argh(option, default = "20")]
+    /// maximum number of items to re-export (defaults to 20)
+    top: usize,
+
+    #[argh(option, default = "2")]
+    /// minimum number of solutions, an item must be used by (defaults to 2)
+    min_uses: usize,
+
+    #[argh(switch)]
+    /// only check that the prelude is up to date, without updating it
+    check: bool,
+}
+
+impl SubCmd for GenPreludeSubCmd {
+    fn run(&self) -> Result<()> {
+        let crates = library_crates(Path::new("."))?;
+        let (name, crate_dir) = match (&self.crate_name, crates.as_slice()) {
+            (Some(name), _) => crates
+                .iter()
+                .find(|(krate, _)| krate == name)
+                .ok_or_else(|| anyhow!("crate {name:?} is not a library crate of the project"))?,
+            (None, [krate]) => krate,
+            (None, []) => return Err(anyhow!("no library crates found in `crates`")),
+            (None, _) => {
+                return Err(anyhow!(
+                    "several library crates found ({}), choose one with `--crate`",
+                    crates
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        };
+        let items = Items::new(&PublicApi::collect(crate_dir)?);
+
+        let mut uses = BTreeMap::<&str, usize>::new();
+        let mut solutions = 0;
+        for project in contest_projects(Path::new(&self.dir))? {
+            for id in problem_ids(&project)? {
+                let path = project.join(solution_path(&project, &id));
+                let content = fs::read_to_string(&path)
+                    .context(format!("failed to read {}", path.display()))?;
+                let Ok(file) = syn::parse_file(&content) else {
+                    println!("Skipping {}, as it doesn't parse", path.display());
+                    continue;
+                };
+                solutions += 1;
+                for item in items.used_by(&file, name) {
+                    *uses.entry(item).or_default() += 1;
+                }
+            }
+        }
+
+        let mut ranked = uses
+            .into_iter()
+            .filter(|(_, uses)| *uses >= self.min_uses)
+            .collect::<Vec<_>>();
+        ranked.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        ranked.truncate(self.top);
+        if ranked.is_empty() {
+            println!(
+                "No library items are used by {} or more of {solutions} solution(s), no prelude \
+                 generated",
+                self.min_uses
+            );
+            return Ok(());
+        }
+        let paths = ranked
+            .iter()
+            .map(|(item, _)| item.trim_end_matches('!').to_string())
+            .collect::<BTreeSet<_>>();
+
+        let path = crate_dir.join("src").join("prelude.rs");
+        let existing = fs::read_to_string(&path).ok();
+        if let Some(existing) = &existing
+            && !existing.starts_with(MARKER)
+        {
+            return Err(anyhow!(
+                "{} is not generated by `algorist gen-prelude`, remove it to generate one",
+                path.display()
+            ));
+        }
+        // Items are compared rather than the text, as the file may be formatted
+        // since.
+        let up_to_date = existing.as_deref().map(reexported) == Some(paths.clone());
+        if self.check {
+            if !up_to_date {
+                return Err(anyhow!(
+                    "prelude of crate {name:?} is out of date, run `algorist gen-prelude` to \
+                     update it"
+                ));
+            }
+            println!("Prelude of crate {name:?} is up to date");
+            return Ok(());
+        }
+
+        println!("Most used items across {solutions} solution(s):");
+        for (item, uses) in &ranked {
+            println!("  {uses:>4}  {item}");
+        }
+        if up_to_date {
+            println!("\nPrelude of crate {name:?} is up to date");
+            return Ok(());
+        }
+        fs::write(&path, render(name, &paths))
+            .context(format!("failed to write {}", path.display()))?;
+        declare_prelude(&crate_dir.join("src").join("lib.rs"))?;
+        println!(
+            "\nPrelude of crate {name:?} updated with {} item(s), import them with `use \
+             {name}::prelude::*`",
+            paths.len()
+        );
+        Ok(())
+    }
+}
+
+/// Library items, the prelude may re-export, along with the re-exports of the
+/// library, solutions may refer to the items by.
+struct Items {
+    /// Paths of the items, e.g. `math::gcd`, `ds::Fenwick` or `wln!`.
+    candidates: HashSet<String>,
+
+    /// Re-exports, e.g. `gcd` for `pub use self::math::gcd`, mapped to the
+    /// paths they refer to.
+    reexports: HashMap<String, String>,
+}
+
+impl Items {
+    fn new(api: &PublicApi) -> Self {
+        let paths = api.paths().collect::<HashSet<_>>();
+        let mut candidates = HashSet::new();
+        let mut reexports = HashMap::new();
+        for (path, signature, _) in api.items() {
+            if signature.starts_with("pub use ") {
+                if let Some(target) = reexport_target(path, signature) {
+                    reexports.insert(path.to_string(), target);
+                }
+                continue;
+            }
+            // Methods (their types are re-exported instead) and trait impls are not
+            // importable.
+            let parent = path.rsplit_once("::").map(|(parent, _)| parent);
+            if !signature.starts_with("impl")
+                && !path.contains(' ')
+                && !path.starts_with("prelude::")
+                && !parent.is_some_and(|parent| paths.contains(parent))
+            {
+                candidates.insert(path.to_string());
+            }
+        }
+        Self {
+            candidates,
+            reexports,
+        }
+    }
+
+    /// Item, the path (relative to the crate root) refers to, directly or
+    /// through re-exports.
+    fn resolve(&self, path: &str) -> Option<&str> {
+        let mut path = path;
+        for _ in 0..=self.reexports.len() {
+            if let Some(item) = self.candidates.get(path) {
+                return Some(item);
+            }
+            if let Some(item) = self.candidates.get(&format!("{path}!")) {
+                return Some(item);
+            }
+            path = self.reexports.get(path)?;
+        }
+        None
+    }
+
+    /// Items, the solution uses: imports them, refers to them by paths, or
+    /// mentions them with a glob import of their module in place.
+    fn used_by(&self, file: &syn::File, crate_name: &str) -> HashSet<&str> {
+        let mut refs = References::new(crate_name);
+        refs.visit_file(file);
+        let mut names = HashSet::new();
+        idents(file.to_token_stream(), &mut names);
+
+        let mut used = HashSet::new();
+        for path in &refs.paths {
+            let path = match path.split_first() {
+                Some((first, rest)) if first == crate_name => rest.to_vec(),
+                Some((first, rest)) => match refs.imports.get(first) {
+                    Some(import) => [import.as_slice(), rest].concat(),
+                    None => continue,
+                },
+                None => continue,
+            };
+            // Paths may go past the item, e.g. `ds::Fenwick::new`.
+            let item = (1..=path.len())
+                .rev()
+                .find_map(|len| self.resolve(&path[..len].join("::")));
+            used.extend(item);
+        }
+        for module in &refs.globs {
+            let Some((_, module)) = module
+                .split_first()
+                .filter(|(first, _)| *first == crate_name)
+            else {
+                continue;
+            };
+            for name in &names {
+                let path = module
+                    .iter()
+                    .chain(std::iter::once(name))
+                    .map(String::as_str)
+                    .collect::<Vec<_>>();
+                used.extend(self.resolve(&path.join("::")));
+            }
+        }
+        used
+    }
+}
+
+/// Paths, a solution refers to.
+struct References<'a> {
+    /// Name, the solution refers to the library crate by.
+    crate_name: &'a str,
+
+    /// Imported and referred paths, e.g. `mylib::math::gcd` or `math::gcd`.
+    paths: Vec<Vec<String>>,
+
+    /// Names, imported from the crate, mapped to the paths they refer to
+    /// (relative to the crate root), e.g. `math` for `use mylib::math`.
+    imports: HashMap<String, Vec<String>>,
+
+    /// Modules, imported with a glob, e.g. `mylib::prelude`.
+    globs: Vec<Vec<String>>,
+}
+
+impl<'a> References<'a> {
+    fn new(crate_name: &'a str) -> Self {
+        Self {
+            crate_name,
+            paths: Vec::new(),
+            imports: HashMap::new(),
+            globs: Vec::new(),
+        }
+    }
+
+    fn use_tree(&mut self, tree: &syn::UseTree, mut prefix: Vec<String>) {
+        match tree {
+            syn::UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                self.use_tree(&path.tree, prefix);
+            }
+            syn::UseTree::Name(leaf) => {
+                let name = leaf.ident.to_string();
+                if name != "self" {
+                    prefix.push(name.clone());
+                }
+                self.import(name, prefix);
+            }
+            syn::UseTree::Rename(leaf) => {
+                prefix.push(leaf.ident.to_string());
+                self.import(leaf.rename.to_string(), prefix);
+            }
+            syn::UseTree::Glob(_) => self.globs.push(prefix),
+            syn::UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.use_tree(tree, prefix.clone());
+                }
+            }
+        }
+    }
+
+    fn import(&mut self, name: String, path: Vec<String>) {
+        if let Some((first, rest)) = path.split_first()
+            && first == self.crate_name
+        {
+            self.imports.insert(name, rest.to_vec());
+        }
+        self.paths.push(path);
+    }
+}
+
+impl<'ast> Visit<'ast> for References<'_> {
+    fn visit_item_use(&mut self, node: &'ast syn::ItemUse) {
+        self.use_tree(&node.tree, Vec::new());
+    }
+
+    fn visit_path(&mut self, node: &'ast syn::Path) {
+        self.paths.push(
+            node.segments
+                .iter()
+                .map(|segment| segment.ident.to_string())
+                .collect(),
+        );
+        syn::visit::visit_path(self, node);
+    }
+}
+
+/// Path, the `pub use` declaration (given its path and signature, e.g.
+/// `math::gcd` and `pub use super::gcd::gcd;`) re-exports, relative to the
+/// crate root.
+fn reexport_target(path: &str, signature: &str) -> Option<String> {
+    let item = syn::parse_str::<syn::ItemUse>(signature).ok()?;
+    let mut segments = Vec::new();
+    let mut tree = &item.tree;
+    while let syn::UseTree::Path(path) = tree {
+        segments.push(path.ident.to_string());
+        tree = &path.tree;
+    }
+    match tree {
+        syn::UseTree::Name(leaf) => segments.push(leaf.ident.to_string()),
+        syn::UseTree::Rename(leaf) => segments.push(leaf.ident.to_string()),
+        _ => return None,
+    }
+
+    let mut resolved = path.split("::").map(String::from).collect::<Vec<_>>();
+    resolved.pop();
+    for (i, segment) in segments.into_iter().enumerate() {
+        match segment.as_str() {
+            "crate" if i == 0 => resolved.clear(),
+            "self" => {}
+            "super" => {
+                resolved.pop();
+            }
+            _ => resolved.push(segment),
+        }
+    }
+    Some(resolved.join("::"))
+}
+
+fn idents(stream: TokenStream, names: &mut HashSet<String>) {
+    for tree in stream {
+        match tree {
+            TokenTree::Ident(ident) => {
+                names.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => idents(group.stream(), names),
+            _ => {}
+        }
+    }
+}
+
+/// Paths, the prelude re-exports (relative to the crate root).
+fn reexported(content: &str) -> BTreeSet<String> {
+    let Ok(file) = syn::parse_file(content) else {
+        return BTreeSet::new();
+    };
+    let mut refs = References::new("crate");
+    refs.visit_file(&file);
+    refs.paths
+        .into_iter()
+        .filter_map(|path| {
+            Some(
+                path.split_first()
+                    .filter(|(first, _)| *first == "crate")?
+                    .1
+                    .join("::"),
+            )
+        })
+        .collect()
+}
+
+fn render(crate_name: &str, paths: &BTreeSet<String>) -> String {
+    let mut out = format!(
+        "{MARKER}, from the usage of the library\n//! across the solutions: re-run it to update \
+         the prelude, rather than editing it.\n//!\n//! Import the most commonly used items at \
+         once with `use {crate_name}::prelude::*`.\n\npub use crate::{{\n"
+    );
+    for path in paths {
+        out.push_str(&format!("    {path},\n"));
+    }
+    out.push_str("};\n");
+    out
+}
+
+/// Declare the `prelude` module in the crate root, unless it is there already.
+fn declare_prelude(lib: &Path) -> Result<()> {
+    let content = fs::read_to_string(lib).context(format!("failed to read {}", lib.display()))?;
+    let file = syn::parse_file(&content).context(format!("failed to parse {}", lib.display()))?;
+    let declared = file
+        .items
+        .iter()
+        .any(|item| matches!(item, syn::Item::Mod(item) if item.ident == "prelude"));
+    if declared {
+        return Ok(());
+    }
+
+    // Declared after the other modules, if any.
+    let mut lines = content.lines().collect::<Vec<_>>();
+    let at = lines
+        .iter()
+        .rposition(|line| line.starts_with("pub mod ") && line.ends_with(';'))
+        .map_or(lines.len(), |i| i + 1);
+    lines.insert(at, "pub mod prelude;");
+    fs::write(lib, lines.join("\n") + "\n").context(format!("failed to write {}", lib.display()))
+}
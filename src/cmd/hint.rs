@@ -0,0 +1,66 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        meta::{Event, record_events},
+        problem::{resolve_id, statement_editorial, statement_path, statement_url},
+    },
+    anyhow::{Result, anyhow},
+    argh::FromArgs,
+    std::path::Path,
+};
+
+/// Show the editorial of a problem, recording that a hint was used.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "hint")]
+pub struct HintSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+
+    #[argh(switch)]
+    /// confirm that the editorial may be shown (it spoils the problem)
+    spoil: bool,
+}
+
+impl SubCmd for HintSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+        let Some(editorial) = editorial(&id) else {
+            return Err(anyhow!(
+                "no editorial is known for problem {id:?}, add an `Editorial: <url>` line to {}",
+                statement_path(&id).display()
+            ));
+        };
+        if !self.spoil {
+            println!(
+                "The editorial spoils problem {id:?}, and using it is recorded in the stats. To \
+                 see it, run `algorist hint {id} --spoil`"
+            );
+            return Ok(());
+        }
+
+        record_events(Path::new("."), &[&id], Event::Hinted);
+        println!("Editorial of problem {id:?}: {editorial}");
+        Ok(())
+    }
+}
+
+/// Editorial link of the problem: the one of the statement notes, or, for
+/// Codeforces problems, the contest page (editorials are linked from there).
+fn editorial(id: &str) -> Option<String> {
+    statement_editorial(Path::new("."), id).or_else(|| {
+        let url = statement_url(Path::new("."), id)?;
+        let rest = url
+            .strip_prefix("https://codeforces.com/contest/")
+            .or_else(|| url.strip_prefix("https://codeforces.com/gym/"))?;
+        let contest = rest.split('/').next()?;
+        let kind = if url.contains("/gym/") {
+            "gym"
+        } else {
+            "contest"
+        };
+        Some(format!(
+            "https://codeforces.com/{kind}/{contest} (see the Tutorial in the contest materials)"
+        ))
+    })
+}
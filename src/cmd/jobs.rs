@@ -0,0 +1,174 @@
+use {
+    crate::cmd::config::setting,
+    anyhow::{Result, anyhow},
+    std::{
+        ffi::OsStr,
+        io::ErrorKind,
+        process::{Command, Stdio},
+        sync::{
+            OnceLock,
+            atomic::{AtomicUsize, Ordering},
+            mpsc,
+        },
+        thread,
+    },
+};
+
+/// Limits of heavy operations (builds, runs of solutions), so that long runs
+/// leave the machine responsive.
+///
+/// Limits are applied to the spawned processes: niceness with `nice` (on Unix),
+/// and CPU affinity with `taskset` (on Linux, processes are pinned to as many
+/// CPUs as there are jobs). Where the tools are not available, processes run
+/// unrestricted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Jobs {
+    /// Maximum number of parallel jobs: compiler processes of cargo builds, and
+    /// solutions run at once.
+    pub jobs: Option<usize>,
+
+    /// Niceness of the spawned processes, from -20 (highest priority) to 19
+    /// (lowest).
+    pub nice: Option<i32>,
+}
+
+impl Jobs {
+    /// Limits, given on the command line, or configured (`jobs` and `nice`
+    /// settings).
+    pub fn resolve(jobs: Option<usize>, nice: Option<i32>) -> Result<Self> {
+        let jobs = match jobs {
+            Some(jobs) => Some(jobs),
+            None => setting("jobs")?
+                .map(|value| value.parse::<usize>().map_err(|_| value))
+                .transpose()
+                .map_err(|value| anyhow!("invalid `jobs` setting {value:?}, expected a number"))?,
+        };
+        if jobs == Some(0) {
+            return Err(anyhow!("number of jobs should be positive"));
+        }
+        let nice = match nice {
+            Some(nice) => Some(nice),
+            None => setting("nice")?
+                .map(|value| value.parse::<i32>().map_err(|_| value))
+                .transpose()
+                .map_err(|value| anyhow!("invalid `nice` setting {value:?}, expected a number"))?,
+        };
+        if let Some(nice) = nice.filter(|nice| !(-20..=19).contains(nice)) {
+            return Err(anyhow!(
+                "invalid niceness {nice}, expected a number from -20 to 19"
+            ));
+        }
+        Ok(Self { jobs, nice })
+    }
+
+    /// Command to run the program with the limits applied.
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut prefix = Vec::new();
+        if let Some(jobs) = self.jobs
+            && cfg!(target_os = "linux")
+            && available("taskset")
+        {
+            let cpus = thread::available_parallelism().map_or(jobs, usize::from);
+            prefix.push(vec![
+                "taskset".to_string(),
+                "-c".to_string(),
+                format!("0-{}", jobs.min(cpus) - 1),
+            ]);
+        }
+        if let Some(nice) = self.nice
+            && cfg!(unix)
+            && available("nice")
+        {
+            prefix.push(vec!["nice".to_string(), "-n".to_string(), nice.to_string()]);
+        }
+        let mut args = prefix.into_iter().flatten();
+        let Some(wrapper) = args.next() else {
+            return Command::new(program);
+        };
+        let mut cmd = Command::new(wrapper);
+        cmd.args(args).arg(program);
+        cmd
+    }
+
+    /// Arguments, limiting the number of compiler processes of cargo.
+    pub fn cargo_args(&self) -> Vec<String> {
+        self.jobs
+            .map(|jobs| vec![format!("--jobs={jobs}")])
+            .unwrap_or_default()
+    }
+
+    /// Run the operation on each of the items, with up to `jobs` of them at
+    /// once (one at a time, if not limited explicitly).
+    ///
+    /// The callback is invoked on the calling thread, as soon as each item is
+    /// done. Results are returned in the order of the items.
+    pub fn run<T: Sync, R: Send>(
+        &self,
+        items: &[T],
+        op: impl Fn(&T) -> R + Sync,
+        mut on_done: impl FnMut(&R),
+    ) -> Vec<R> {
+        let workers = self.jobs.unwrap_or(1).min(items.len());
+        if workers <= 1 {
+            return items
+                .iter()
+                .map(|item| {
+                    let result = op(item);
+                    on_done(&result);
+                    result
+                })
+                .collect();
+        }
+
+        let next = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+        let mut results = (0..items.len()).map(|_| None).collect::<Vec<_>>();
+        thread::scope(|scope| {
+            for _ in 0..workers {
+                let (tx, next, op) = (tx.clone(), &next, &op);
+                scope.spawn(move || {
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            break;
+                        };
+                        if tx.send((i, op(item))).is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+            drop(tx);
+            for (i, result) in rx {
+                on_done(&result);
+                results[i] = Some(result);
+            }
+        });
+        results
+            .into_iter()
+            .map(|result| result.expect("every item should be done"))
+            .collect()
+    }
+}
+
+/// Whether the tool is installed, checked once per run (a warning is shown, if
+/// it is not).
+fn available(tool: &'static str) -> bool {
+    static TASKSET: OnceLock<bool> = OnceLock::new();
+    static NICE: OnceLock<bool> = OnceLock::new();
+    let cell = if tool == "taskset" { &TASKSET } else { &NICE };
+    *cell.get_or_init(|| {
+        let found = !matches!(
+            Command::new(tool)
+                .arg("--version")
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status(),
+            Err(e) if e.kind() == ErrorKind::NotFound
+        );
+        if !found {
+            println!("Warning: `{tool}` is not found, processes are run without its limits");
+        }
+        found
+    })
+}
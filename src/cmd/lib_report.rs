@@ -0,0 +1,230 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        api::library_crates,
+        cargo,
+        jobs::Jobs,
+        manifest::package_name,
+        problem::resolve_id,
+        stats::format_ms,
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        collections::BTreeMap,
+        path::Path,
+        process::Stdio,
+        time::{Duration, Instant},
+    },
+};
+
+/// Directory, the library is built into for the report, not to invalidate the
+/// regular builds.
+const TARGET_DIR: &str = "target/lib-report";
+
+/// Report compile times of the library crates, and their generic bloat (the
+/// code generated for each module).
+#[derive(FromArgs)]
+#[argh(subcommand, name = "lib-report")]
+pub struct LibReportSubCmd {
+    #[argh(positional)]
+    /// problem ID: measure the bloat within its binary, where generics of the
+    /// library are instantiated (defaults to the library crates on their own)
+    id: Option<String>,
+
+    #[argh(option, default = "10")]
+    /// number of the largest functions to list (defaults to 10)
+    top: usize,
+}
+
+/// LLVM IR, generated for a function (all its instantiations).
+struct Function {
+    name: String,
+    lines: u64,
+    copies: u64,
+}
+
+impl SubCmd for LibReportSubCmd {
+    fn run(&self) -> Result<()> {
+        let crates = library_crates(Path::new("."))?
+            .into_iter()
+            .filter_map(|(name, dir)| Some((name, package_name(&dir)?)))
+            .collect::<Vec<_>>();
+        if crates.is_empty() {
+            return Err(anyhow!("no library crates found in `crates`"));
+        }
+
+        // Crates are built from scratch (with the judge's profile), as contest
+        // compilations are.
+        println!("Compile times (release, from scratch):");
+        for (name, package) in &crates {
+            let elapsed = build_time(package)?;
+            println!("  {name}: {}", format_ms(elapsed.as_millis() as u64));
+        }
+
+        if !llvm_lines_installed() {
+            println!(
+                "\nGeneric bloat is measured with `cargo llvm-lines`, install it with `cargo \
+                 install cargo-llvm-lines`"
+            );
+            return Ok(());
+        }
+        let mut functions = Vec::new();
+        match &self.id {
+            Some(id) => {
+                let id = resolve_id(Path::new("."), id)?;
+                functions.extend(llvm_lines(&["--bin", &id])?);
+            }
+            None => {
+                for (_, package) in &crates {
+                    functions.extend(llvm_lines(&["--package", package, "--lib"])?);
+                }
+            }
+        }
+        let names = crates
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        print_bloat(&functions, &names, self.top);
+        Ok(())
+    }
+}
+
+/// Time of building the library crate (and its dependencies) from scratch.
+fn build_time(package: &str) -> Result<Duration> {
+    let status = cargo::command("clean", Jobs::default())
+        .args([
+            "--package",
+            package,
+            "--release",
+            "--target-dir",
+            TARGET_DIR,
+        ])
+        .stderr(Stdio::null())
+        .status()
+        .context("failed to run cargo clean")?;
+    if !status.success() {
+        return Err(anyhow!("failed to clean the build of {package:?}"));
+    }
+    let started = Instant::now();
+    let status = cargo::command("build", Jobs::default())
+        .args(["--package", package, "--lib", "--release", "--quiet"])
+        .args(["--target-dir", TARGET_DIR])
+        .status()
+        .context("failed to run cargo build")?;
+    if !status.success() {
+        return Err(anyhow!("failed to build {package:?}"));
+    }
+    Ok(started.elapsed())
+}
+
+fn llvm_lines_installed() -> bool {
+    cargo::command("llvm-lines", Jobs::default())
+        .arg("--version")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// Functions of the target (given by the arguments, e.g. `--bin a`), as
+/// reported by `cargo llvm-lines`.
+fn llvm_lines(args: &[&str]) -> Result<Vec<Function>> {
+    let output = cargo::command("llvm-lines", Jobs::default())
+        .args(args)
+        .args(["--release", "--target-dir", TARGET_DIR])
+        .stderr(Stdio::inherit())
+        .output()
+        .context("failed to run cargo llvm-lines")?;
+    if !output.status.success() {
+        return Err(anyhow!("cargo llvm-lines failed for {}", args.join(" ")));
+    }
+
+    // Rows are `<lines> (<%>, <total %>) <copies> (<%>, <total %>) <name>`.
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let functions = stdout
+        .lines()
+        .filter_map(|row| {
+            let (lines, rest) = row.trim().split_once(' ')?;
+            let (_, rest) = rest.split_once("%)")?;
+            let (copies, rest) = rest.trim().split_once(' ')?;
+            let (_, name) = rest.split_once("%)")?;
+            Some(Function {
+                name: name.trim().to_string(),
+                lines: lines.parse().ok()?,
+                copies: copies.parse().ok()?,
+            })
+        })
+        .collect();
+    Ok(functions)
+}
+
+/// Print the generated code per library module, and the largest functions of
+/// the library.
+fn print_bloat(functions: &[Function], crates: &[&str], top: usize) {
+    let mut modules = BTreeMap::<String, (u64, u64)>::new();
+    let mut largest = Vec::new();
+    for function in functions {
+        let Some(module) = library_module(&function.name, crates) else {
+            continue;
+        };
+        let entry = modules.entry(module).or_default();
+        entry.0 += function.lines;
+        entry.1 += function.copies;
+        largest.push(function);
+    }
+    if modules.is_empty() {
+        println!("\nNo library code is generated for the target");
+        return;
+    }
+
+    let mut modules = modules.into_iter().collect::<Vec<_>>();
+    modules.sort_by_key(|(_, (lines, _))| std::cmp::Reverse(*lines));
+    let width = modules.iter().map(|(module, _)| module.len()).max();
+    let width = width.unwrap_or_default().max("module".len());
+    println!("\nGeneric bloat (LLVM IR lines) by library module:");
+    println!("  {:<width$}{:>10}{:>10}", "module", "lines", "copies");
+    for (module, (lines, copies)) in &modules {
+        println!("  {module:<width$}{lines:>10}{copies:>10}");
+    }
+
+    largest.sort_by_key(|function| std::cmp::Reverse(function.lines));
+    println!("\nLargest functions of the library:");
+    for function in largest.iter().take(top) {
+        println!(
+            "  {:>8} lines, {:>4} copies  {}",
+            function.lines, function.copies, function.name
+        );
+    }
+}
+
+/// Library module, the function is defined in, if any: e.g.
+/// `<mylib::ds::fenwick::Fenwick<T>>::add` is in `mylib::ds::fenwick`.
+fn library_module(name: &str, crates: &[&str]) -> Option<String> {
+    let start = crates
+        .iter()
+        .filter_map(|krate| {
+            name.match_indices(&format!("{krate}::"))
+                .map(|(start, _)| start)
+                .find(|&start| {
+                    start == 0
+                        || !name[..start].ends_with(|c: char| c.is_alphanumeric() || c == '_')
+                })
+        })
+        .min()?;
+
+    // Path ends at generic arguments, closures and the like.
+    let path = &name[start..];
+    let end = path.find(['<', '>', '{', ' ', '(']).unwrap_or(path.len());
+    let mut segments = path[..end]
+        .split("::")
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+
+    // Function (or method) name goes, and so do the types.
+    segments.pop();
+    while segments.len() > 1 && segments.last()?.starts_with(char::is_uppercase) {
+        segments.pop();
+    }
+    Some(segments.join("::"))
+}
@@ -0,0 +1,88 @@
+use {
+    crate::cmd::metrics::{Failure, record_failure},
+    anyhow::{Context, Result, anyhow},
+    std::{
+        fs::{self, File, OpenOptions, TryLockError},
+        io::{Read, Seek, Write},
+        path::Path,
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+/// Location of the lock file, relative to the project root.
+const LOCK_FILE: &str = ".algorist/lock";
+
+/// How often a held lock is re-checked.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Project-level advisory lock, held by commands modifying the project (e.g.
+/// `bundled/` or `.algorist/`), so that concurrent invocations do not clobber
+/// each other's outputs.
+///
+/// The lock is released once dropped (or when the process exits).
+#[derive(Debug)]
+pub struct ProjectLock {
+    _file: File,
+}
+
+impl ProjectLock {
+    /// Acquire the lock of a project at the given root, waiting for other
+    /// holders up to the given timeout.
+    pub fn acquire(root: &Path, timeout: Duration) -> Result<Self> {
+        let path = root.join(LOCK_FILE);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).context("failed to create .algorist directory")?;
+        }
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .context(format!("failed to open lock file {}", path.display()))?;
+
+        let started = Instant::now();
+        let mut announced = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {}
+                Err(TryLockError::Error(e)) => {
+                    return Err(e).context(format!("failed to lock {}", path.display()));
+                }
+            }
+
+            let holder = holder(&mut file);
+            if started.elapsed() >= timeout {
+                record_failure(Failure::Lock);
+                return Err(anyhow!(
+                    "another algorist command ({holder}) is still running in this project, gave \
+                     up after {}s (use `--lock-timeout` to wait longer)",
+                    timeout.as_secs()
+                ));
+            }
+            if !announced {
+                println!("Waiting for another algorist command ({holder}) to finish...");
+                announced = true;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+
+        // Record the holder, for the waiting commands to report.
+        file.set_len(0)
+            .and_then(|_| file.rewind())
+            .and_then(|_| write!(file, "{}", std::process::id()))
+            .context("failed to write lock file")?;
+        Ok(Self { _file: file })
+    }
+}
+
+/// Description of the process, holding the lock.
+fn holder(file: &mut File) -> String {
+    let mut pid = String::new();
+    match file.rewind().and_then(|_| file.read_to_string(&mut pid)) {
+        Ok(_) if !pid.trim().is_empty() => format!("pid {}", pid.trim()),
+        _ => "unknown pid".to_string(),
+    }
+}
@@ -0,0 +1,227 @@
+use {
+    crate::cmd::{TPL_DIR, write_atomic},
+    anyhow::{Context, Result},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+    toml_edit::{DocumentMut, InlineTable, Item, Table, Value},
+};
+
+/// Dependency entry of a manifest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Dependency {
+    /// Published crate, with a version requirement: `name = "req"`.
+    Version(String),
+    /// Local crate: `name = { path = "...", package = "..." }`, the package
+    /// is only set when it differs from the dependency name.
+    Path {
+        path: String,
+        package: Option<String>,
+    },
+}
+
+impl Dependency {
+    /// Local crate at the given path.
+    pub fn path(path: impl Into<String>) -> Self {
+        Dependency::Path {
+            path: path.into(),
+            package: None,
+        }
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Dependency::Version(req) => Value::from(req.as_str()),
+            Dependency::Path { path, package } => {
+                let mut table = InlineTable::new();
+                table.insert("path", Value::from(path.as_str()));
+                if let Some(package) = package {
+                    table.insert("package", Value::from(package.as_str()));
+                }
+                Value::InlineTable(table)
+            }
+        }
+    }
+}
+
+/// Project's `Cargo.toml`, edited in place: formatting and comments of the
+/// parts not touched are preserved.
+#[derive(Debug, Clone)]
+pub struct Manifest {
+    doc: DocumentMut,
+}
+
+impl Manifest {
+    /// Read and parse the manifest at the given path.
+    pub fn read(path: &Path) -> Result<Self> {
+        let content =
+            fs::read_to_string(path).context(format!("failed to read {}", path.display()))?;
+        content
+            .parse()
+            .context(format!("failed to parse {}", path.display()))
+    }
+
+    /// Manifest of a contest project, as shipped in the templates.
+    pub fn template() -> Result<Self> {
+        TPL_DIR
+            .get_file("Cargo.toml.tpl")
+            .and_then(|file| file.contents_utf8())
+            .context("Cargo.toml template should exist")?
+            .parse()
+            .context("failed to parse Cargo.toml template")
+    }
+
+    /// Version requirement of a published dependency, if any.
+    pub fn dependency_version(&self, name: &str) -> Option<&str> {
+        self.doc
+            .get("dependencies")?
+            .get(name)?
+            .as_value()?
+            .as_str()
+    }
+
+    /// Add (or replace) a dependency. An existing entry keeps its position
+    /// and surrounding comments.
+    pub fn set_dependency(&mut self, name: &str, dependency: &Dependency) -> Result<()> {
+        let deps = self
+            .doc
+            .entry("dependencies")
+            .or_insert_with(|| Item::Table(Table::new()))
+            .as_table_like_mut()
+            .context("`dependencies` should be a table")?;
+        let mut value = dependency.to_value();
+        match deps.get_mut(name).and_then(|item| item.as_value_mut()) {
+            Some(existing) => {
+                *value.decor_mut() = existing.decor().clone();
+                *existing = value;
+            }
+            None => {
+                deps.insert(name, Item::Value(value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove a feature from the default features.
+    pub fn remove_default_feature(&mut self, feature: &str) {
+        let Some(default) = self
+            .doc
+            .get_mut("features")
+            .and_then(|features| features.get_mut("default"))
+        else {
+            return;
+        };
+        if let Some(array) = default.as_array_mut() {
+            array.retain(|value| value.as_str() != Some(feature));
+            if array.is_empty() {
+                *default = Item::None;
+            }
+        }
+    }
+
+    /// Replace the settings of a `[profile.<name>]` table, documenting them
+    /// with the given comment. Values are TOML literals, e.g. `3` or
+    /// `"abort"`.
+    pub fn set_profile(
+        &mut self,
+        name: &str,
+        comment: &str,
+        settings: &[(String, String)],
+    ) -> Result<()> {
+        let mut table = Table::new();
+        for (key, value) in settings {
+            let value = value
+                .parse::<Value>()
+                .context(format!("invalid value of `profile.{name}.{key}`: {value}"))?;
+            table.insert(key, Item::Value(value));
+        }
+        let comment = comment
+            .lines()
+            .map(|line| format!("# {line}\n"))
+            .collect::<String>();
+        table.decor_mut().set_prefix(format!("\n{comment}"));
+
+        let profiles = self
+            .doc
+            .entry("profile")
+            .or_insert_with(|| {
+                let mut profiles = Table::new();
+                profiles.set_implicit(true);
+                Item::Table(profiles)
+            })
+            .as_table_mut()
+            .context("`profile` should be a table")?;
+        match profiles.get_mut(name).and_then(|item| item.as_table_mut()) {
+            // Keep the position of the table in the document.
+            Some(existing) => {
+                table.set_position(existing.position().unwrap_or_default());
+                *existing = table;
+            }
+            None => {
+                profiles.insert(name, Item::Table(table));
+            }
+        }
+        Ok(())
+    }
+
+    /// Make the package a workspace of its own (an empty `[workspace]` table),
+    /// so that cargo does not consider it a member of an enclosing workspace.
+    pub fn isolate_workspace(&mut self, comment: &str) {
+        if self.doc.contains_key("workspace") {
+            return;
+        }
+        let comment = comment
+            .lines()
+            .map(|line| format!("# {line}\n"))
+            .collect::<String>();
+        let mut table = Table::new();
+        table.decor_mut().set_prefix(format!("\n{comment}"));
+        self.doc.insert("workspace", Item::Table(table));
+    }
+
+    /// Write the manifest to the given path, unless the file already has the
+    /// same content (so that the project is not rebuilt needlessly).
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let content = self.doc.to_string();
+        if fs::read_to_string(path).ok().as_deref() != Some(content.as_str()) {
+            write_atomic(path, content).context(format!("failed to write {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Root of the cargo workspace, enclosing the directory (i.e. the closest
+/// ancestor with a `[workspace]` manifest), if any. The directory itself is
+/// not checked.
+pub fn enclosing_workspace(dir: &Path) -> Option<PathBuf> {
+    let dir = dir.canonicalize().ok()?;
+    dir.ancestors()
+        .skip(1)
+        .find(|dir| {
+            fs::read_to_string(dir.join("Cargo.toml"))
+                .ok()
+                .and_then(|content| content.parse::<toml::Table>().ok())
+                .is_some_and(|manifest| manifest.contains_key("workspace"))
+        })
+        .map(Path::to_path_buf)
+}
+
+/// Package name of the crate, as specified in its `Cargo.toml`.
+pub fn package_name(crate_dir: &Path) -> Option<String> {
+    let content = fs::read_to_string(crate_dir.join("Cargo.toml")).ok()?;
+    let value = content.parse::<toml::Value>().ok()?;
+    value
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(|name| name.to_string())
+}
+
+impl std::str::FromStr for Manifest {
+    type Err = toml_edit::TomlError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self { doc: s.parse()? })
+    }
+}
@@ -0,0 +1,244 @@
+use {
+    crate::cmd::{now_secs, write_atomic},
+    anyhow::{Context, Result, anyhow},
+    serde_json::{Map, Value},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Location of the metadata store, relative to the project root.
+const META_FILE: &str = ".algorist/meta.json";
+
+/// Milestones in solving a problem, recorded in the metadata store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// Problem file was created (with `create` or `add`).
+    Created,
+
+    /// Solution was run for the first time (with `run` or `test`).
+    FirstRun,
+
+    /// All test cases passed for the first time.
+    FirstPass,
+
+    /// All test cases passed (updated on every such run).
+    Tested,
+
+    /// Problem was bundled (updated on every bundling).
+    Bundled,
+
+    /// Hint (editorial) was looked up.
+    Hinted,
+}
+
+impl Event {
+    fn key(&self) -> &'static str {
+        match self {
+            Event::Created => "created",
+            Event::FirstRun => "first_run",
+            Event::FirstPass => "first_pass",
+            Event::Tested => "tested",
+            Event::Bundled => "bundled",
+            Event::Hinted => "hinted",
+        }
+    }
+}
+
+/// Per-problem metadata, stored in `.algorist/meta.json` of the project.
+///
+/// For each problem, timestamps (seconds since Unix epoch) of solving
+/// milestones are recorded, along with its tags and difficulty, if known.
+#[derive(Debug)]
+pub struct MetaStore {
+    path: PathBuf,
+    problems: Map<String, Value>,
+}
+
+impl MetaStore {
+    /// Open the store of a project at the given root, creating an empty one if
+    /// it doesn't exist.
+    pub fn open(root: &Path) -> Result<Self> {
+        let path = root.join(META_FILE);
+        let problems = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str::<Value>(&content)
+                .context(format!("failed to parse {}", path.display()))?
+                .get("problems")
+                .and_then(Value::as_object)
+                .cloned()
+                .unwrap_or_default(),
+            Err(_) => Map::new(),
+        };
+        Ok(Self { path, problems })
+    }
+
+    /// Record the event, unless it has already been recorded for the problem.
+    ///
+    /// Returns whether the event was recorded.
+    pub fn record(&mut self, problem_id: &str, event: Event) -> bool {
+        let entry = self
+            .problems
+            .entry(problem_id)
+            .or_insert_with(|| Value::Object(Map::new()));
+        match entry.as_object_mut() {
+            Some(events) if !events.contains_key(event.key()) => {
+                events.insert(event.key().to_string(), Value::from(now_secs()));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Record the event, overwriting the previous timestamp, if any.
+    pub fn update(&mut self, problem_id: &str, event: Event) {
+        let entry = self
+            .problems
+            .entry(problem_id)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(events) = entry.as_object_mut() {
+            events.insert(event.key().to_string(), Value::from(now_secs()));
+        }
+    }
+
+    /// Timestamp of the event, if recorded.
+    pub fn get(&self, problem_id: &str, event: Event) -> Option<u64> {
+        self.problems.get(problem_id)?.get(event.key())?.as_u64()
+    }
+
+    /// Set tags (e.g. `dp`, `graphs`) and difficulty (e.g. Codeforces rating)
+    /// of the problem, keeping the known ones, if not given.
+    pub fn set_labels(&mut self, problem_id: &str, tags: &[String], difficulty: Option<u64>) {
+        let entry = self
+            .problems
+            .entry(problem_id)
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Some(labels) = entry.as_object_mut() {
+            if !tags.is_empty() {
+                labels.insert("tags".to_string(), Value::from(tags.to_vec()));
+            }
+            if let Some(difficulty) = difficulty {
+                labels.insert("difficulty".to_string(), Value::from(difficulty));
+            }
+        }
+    }
+
+    /// Tags of the problem, if any.
+    pub fn tags(&self, problem_id: &str) -> Vec<String> {
+        self.problems
+            .get(problem_id)
+            .and_then(|problem| problem.get("tags")?.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|tag| tag.as_str().map(String::from))
+            .collect()
+    }
+
+    /// Difficulty of the problem, if known.
+    pub fn difficulty(&self, problem_id: &str) -> Option<u64> {
+        self.problems.get(problem_id)?.get("difficulty")?.as_u64()
+    }
+
+    /// IDs of all the problems in the store, sorted.
+    pub fn problems(&self) -> Vec<String> {
+        let mut ids = self.problems.keys().cloned().collect::<Vec<_>>();
+        ids.sort();
+        ids
+    }
+
+    pub fn save(&self) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(&serde_json::json!({
+            "problems": self.problems,
+        }))?;
+        write_atomic(&self.path, content)
+            .context(format!("failed to write {}", self.path.display()))
+    }
+}
+
+/// Record events in the store of the project at the given root.
+///
+/// Tracking is best-effort: failures are reported, but never fail the command.
+pub fn record_events(root: &Path, problem_ids: &[&str], event: Event) {
+    let result = MetaStore::open(root).and_then(|mut store| {
+        let mut changed = false;
+        for id in problem_ids {
+            changed |= store.record(id, event);
+        }
+        if changed { store.save() } else { Ok(()) }
+    });
+    if let Err(e) = result {
+        println!("Warning: failed to record problem metadata: {e:#}");
+    }
+}
+
+/// Update events in the store of the project at the given root, overwriting
+/// the previous timestamps.
+///
+/// Same as [`record_events`], tracking is best-effort.
+pub fn update_events(root: &Path, problem_ids: &[&str], event: Event) {
+    let result = MetaStore::open(root).and_then(|mut store| {
+        for id in problem_ids {
+            store.update(id, event);
+        }
+        store.save()
+    });
+    if let Err(e) = result {
+        println!("Warning: failed to record problem metadata: {e:#}");
+    }
+}
+
+/// Filter of the problems by their labels: `tag=<tag>`, or
+/// `difficulty=<min>..<max>` (either bound may be omitted), or
+/// `difficulty=<value>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Tag(String),
+    Difficulty(Option<u64>, Option<u64>),
+}
+
+impl Filter {
+    pub fn parse(spec: &str) -> Result<Self> {
+        let (key, value) = spec
+            .split_once('=')
+            .context(format!("invalid filter {spec:?}, expected `key=value`"))?;
+        let bound = |bound: &str| -> Result<Option<u64>> {
+            match bound.trim() {
+                "" => Ok(None),
+                bound => bound
+                    .parse()
+                    .map(Some)
+                    .context(format!("invalid difficulty {bound:?}")),
+            }
+        };
+        match key.trim() {
+            "tag" => Ok(Filter::Tag(value.trim().to_lowercase())),
+            "difficulty" => match value.split_once("..") {
+                Some((min, max)) => Ok(Filter::Difficulty(bound(min)?, bound(max)?)),
+                None => {
+                    let value = bound(value)?;
+                    Ok(Filter::Difficulty(value, value))
+                }
+            },
+            key => Err(anyhow!(
+                "unknown filter key {key:?}, expected `tag` or `difficulty`"
+            )),
+        }
+    }
+
+    /// Whether the problem of the store passes the filter. Problems of unknown
+    /// difficulty never pass difficulty filters.
+    pub fn matches(&self, store: &MetaStore, problem_id: &str) -> bool {
+        match self {
+            Filter::Tag(tag) => store
+                .tags(problem_id)
+                .iter()
+                .any(|other| other.eq_ignore_ascii_case(tag)),
+            Filter::Difficulty(min, max) => store.difficulty(problem_id).is_some_and(|value| {
+                min.is_none_or(|min| value >= min) && max.is_none_or(|max| value <= max)
+            }),
+        }
+    }
+}
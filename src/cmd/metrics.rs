@@ -0,0 +1,185 @@
+use {
+    crate::cmd::{config::setting, now_secs},
+    anyhow::{Context, Result},
+    serde_json::{Map, Value},
+    std::{
+        env,
+        fs::{self, OpenOptions},
+        io::Write,
+        path::{Path, PathBuf},
+        sync::Mutex,
+        time::{Duration, Instant},
+    },
+};
+
+/// Location of the metrics file, relative to the state directory
+/// (`$XDG_STATE_HOME`, or `~/.local/state`).
+const METRICS_FILE: &str = "algorist/metrics.jsonl";
+
+/// Why a command failed, as recorded in the metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Failure {
+    /// Solution failed to compile.
+    Compile,
+
+    /// Some test cases failed.
+    Tests,
+
+    /// Bundled file failed verification.
+    Bundle,
+
+    /// Another command held the project lock for too long.
+    Lock,
+
+    /// Reading or writing files failed.
+    Io,
+
+    Other,
+}
+
+impl Failure {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Failure::Compile => "compile",
+            Failure::Tests => "tests",
+            Failure::Bundle => "bundle",
+            Failure::Lock => "lock",
+            Failure::Io => "io",
+            Failure::Other => "other",
+        }
+    }
+}
+
+/// Measurements of the running command, recorded once it completes.
+#[derive(Debug, Default)]
+struct Recording {
+    /// Time spent in phases (e.g. `build`), in milliseconds.
+    phases: Map<String, Value>,
+
+    /// Failure, noted where the command failed.
+    failure: Option<Failure>,
+}
+
+static RECORDING: Mutex<Option<Recording>> = Mutex::new(None);
+
+/// Add the time, spent in the phase (e.g. compiling), to the running command.
+pub fn record_phase(phase: &str, started: Instant) {
+    let ms = started.elapsed().as_millis() as u64;
+    let mut recording = RECORDING.lock().unwrap_or_else(|e| e.into_inner());
+    let phases = &mut recording.get_or_insert_with(Recording::default).phases;
+    let total = phases
+        .get(phase)
+        .and_then(Value::as_u64)
+        .unwrap_or_default();
+    phases.insert(phase.to_string(), Value::from(total + ms));
+}
+
+/// Note why the running command fails (the first noted failure is kept, e.g.
+/// when several problems are tested).
+pub fn record_failure(failure: Failure) {
+    let mut recording = RECORDING.lock().unwrap_or_else(|e| e.into_inner());
+    recording
+        .get_or_insert_with(Recording::default)
+        .failure
+        .get_or_insert(failure);
+}
+
+/// Append the metrics of the completed command to the metrics file, if the
+/// `telemetry` setting is `on`.
+///
+/// Recording is best-effort: failures are reported, but never fail the
+/// command.
+pub fn record_command(command: &str, elapsed: Duration, result: &Result<()>) {
+    let recording = RECORDING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .take()
+        .unwrap_or_default();
+    if setting("telemetry").ok().flatten().as_deref() != Some("on") {
+        return;
+    }
+    let failure = result.as_ref().err().map(|e| {
+        recording.failure.unwrap_or_else(|| {
+            if e.chain().any(|cause| cause.is::<std::io::Error>()) {
+                Failure::Io
+            } else {
+                Failure::Other
+            }
+        })
+    });
+    let entry = serde_json::json!({
+        "command": command,
+        "at": now_secs(),
+        "ms": elapsed.as_millis() as u64,
+        "phases": recording.phases,
+        "failure": failure.map(|failure| failure.key()),
+    });
+    let result = metrics_path()
+        .context("neither XDG_STATE_HOME nor HOME is set")
+        .and_then(|path| append(&path, &entry.to_string()));
+    if let Err(e) = result {
+        println!("Warning: failed to record command metrics: {e:#}");
+    }
+}
+
+/// Metrics of a single command run, as recorded in the metrics file.
+#[derive(Debug)]
+pub struct Entry {
+    pub command: String,
+
+    /// When the command completed, seconds since Unix epoch.
+    pub at: u64,
+    pub ms: u64,
+    pub phases: Vec<(String, u64)>,
+    pub failure: Option<String>,
+}
+
+/// Read the recorded metrics (none, if nothing is recorded yet). Malformed
+/// lines are skipped.
+pub fn read_entries() -> Result<Vec<Entry>> {
+    let path = metrics_path().context("neither XDG_STATE_HOME nor HOME is set")?;
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).context(format!("failed to read {}", path.display())),
+    };
+    Ok(content
+        .lines()
+        .filter_map(|line| {
+            let value = serde_json::from_str::<Value>(line).ok()?;
+            Some(Entry {
+                command: value["command"].as_str()?.to_string(),
+                at: value["at"].as_u64()?,
+                ms: value["ms"].as_u64()?,
+                phases: value["phases"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(phase, ms)| Some((phase.clone(), ms.as_u64()?)))
+                    .collect(),
+                failure: value["failure"].as_str().map(String::from),
+            })
+        })
+        .collect())
+}
+
+pub fn metrics_path() -> Option<PathBuf> {
+    let dir = env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")))?;
+    Some(dir.join(METRICS_FILE))
+}
+
+/// Append the line to the file, creating it (and its directory) if needed.
+fn append(path: &Path, line: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context(format!("failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .context(format!("failed to open {}", path.display()))?;
+    writeln!(file, "{line}").context(format!("failed to write {}", path.display()))
+}
@@ -1,17 +1,82 @@
 pub mod add;
+pub mod api;
+pub mod api_diff;
 pub mod bundle;
+pub mod cargo;
+mod cfg;
+pub mod ci_init;
+pub mod compat;
+pub mod config;
+pub mod coverage;
 pub mod create;
+pub mod dedup;
+pub mod doc;
+pub mod explain;
+pub mod fmt;
+pub mod gen_prelude;
+pub mod hint;
+pub mod jobs;
+pub mod lib_report;
+pub mod lock;
+pub mod manifest;
+pub mod meta;
+pub mod metrics;
+pub mod open;
+pub mod problem;
+pub mod promote;
+pub mod repro;
+pub mod retemplate;
 pub mod run;
+pub mod scaffold;
+pub mod setup;
+pub mod share;
+pub mod stats;
+pub mod status;
+pub mod target;
+pub mod test;
+pub mod upgrade;
+pub mod upsolve;
+pub mod use_add;
+pub mod vcs;
 
 use {
     add::AddProblemSubCmd,
     anyhow::Result,
-    argh::FromArgs,
+    api_diff::ApiDiffSubCmd,
+    argh::{FromArgs, SubCommand},
     bundle::BundleProblemSubCmd,
+    ci_init::CiInitSubCmd,
+    config::ConfigSubCmd,
+    coverage::CoverageSubCmd,
     create::CreateContestSubCmd,
+    dedup::DedupSubCmd,
+    doc::DocSubCmd,
+    explain::ExplainSubCmd,
+    fmt::FormatProjectSubCmd,
+    gen_prelude::GenPreludeSubCmd,
+    hint::HintSubCmd,
     include_dir::{Dir, include_dir},
+    lib_report::LibReportSubCmd,
+    lock::ProjectLock,
+    open::OpenSubCmd,
+    promote::PromoteSubCmd,
+    repro::ReproSubCmd,
+    retemplate::RetemplateSubCmd,
     run::RunProblemSubCmd,
-    std::{fs, path::Path},
+    setup::SetupSubCmd,
+    share::ShareFailureSubCmd,
+    stats::StatsSubCmd,
+    status::StatusSubCmd,
+    std::{
+        fs,
+        path::Path,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+    },
+    test::TestProblemSubCmd,
+    upgrade::UpgradeLibSubCmd,
+    upsolve::UpsolveSubCmd,
+    use_add::UseAddSubCmd,
 };
 
 pub trait SubCmd {
@@ -24,6 +89,19 @@ pub trait SubCmd {
 pub struct MainCmd {
     #[argh(subcommand)]
     nested: Cmd,
+
+    #[argh(option, default = "60")]
+    /// seconds to wait for other commands, modifying the project, to finish
+    /// (default: 60)
+    lock_timeout: u64,
+
+    #[argh(switch)]
+    /// run cargo with `--offline`, never accessing the network
+    offline: bool,
+
+    #[argh(switch)]
+    /// run cargo with `--locked`, never updating `Cargo.lock`
+    locked: bool,
 }
 
 #[derive(FromArgs)]
@@ -33,16 +111,139 @@ enum Cmd {
     BundleProblem(BundleProblemSubCmd),
     AddProblem(AddProblemSubCmd),
     RunProblem(RunProblemSubCmd),
+    TestProblem(TestProblemSubCmd),
+    FormatProject(FormatProjectSubCmd),
+    Explain(ExplainSubCmd),
+    Status(StatusSubCmd),
+    UpgradeLib(UpgradeLibSubCmd),
+    ApiDiff(ApiDiffSubCmd),
+    Setup(SetupSubCmd),
+    Config(ConfigSubCmd),
+    Repro(ReproSubCmd),
+    Promote(PromoteSubCmd),
+    CiInit(CiInitSubCmd),
+    Open(OpenSubCmd),
+    UseAdd(UseAddSubCmd),
+    Doc(DocSubCmd),
+    Retemplate(RetemplateSubCmd),
+    ShareFailure(ShareFailureSubCmd),
+    Stats(StatsSubCmd),
+    Coverage(CoverageSubCmd),
+    Dedup(DedupSubCmd),
+    Upsolve(UpsolveSubCmd),
+    Hint(HintSubCmd),
+    LibReport(LibReportSubCmd),
+    GenPrelude(GenPreludeSubCmd),
 }
 
 impl MainCmd {
-    /// Run the nested command.
+    /// Run the nested command, recording its metrics (if enabled).
     pub fn run(&self) -> Result<()> {
+        let started = Instant::now();
+        cargo::set_flags(self.offline, self.locked);
+        let result = self.run_nested();
+        metrics::record_command(self.nested.name(), started.elapsed(), &result);
+        result
+    }
+
+    fn run_nested(&self) -> Result<()> {
+        // Projects with an outdated layout are reported before they confuse commands.
+        if !matches!(
+            &self.nested,
+            Cmd::NewContest(_) | Cmd::Explain(_) | Cmd::ApiDiff(_) | Cmd::Setup(_) | Cmd::Config(_)
+        ) {
+            compat::check(Path::new("."));
+        }
+
+        // Commands, modifying the project, are not run concurrently.
+        let _lock = match &self.nested {
+            Cmd::BundleProblem(_)
+            | Cmd::AddProblem(_)
+            | Cmd::RunProblem(_)
+            | Cmd::TestProblem(_)
+            | Cmd::FormatProject(_)
+            | Cmd::UpgradeLib(_)
+            | Cmd::Repro(_)
+            | Cmd::Promote(_)
+            | Cmd::CiInit(_)
+            | Cmd::Open(_)
+            | Cmd::UseAdd(_)
+            | Cmd::Retemplate(_)
+            | Cmd::ShareFailure(_)
+            | Cmd::Hint(_)
+            | Cmd::GenPrelude(_) => Some(ProjectLock::acquire(
+                Path::new("."),
+                Duration::from_secs(self.lock_timeout),
+            )?),
+            _ => None,
+        };
+
         match &self.nested {
             Cmd::NewContest(cmd) => cmd.run(),
             Cmd::BundleProblem(cmd) => cmd.run(),
             Cmd::AddProblem(cmd) => cmd.run(),
             Cmd::RunProblem(cmd) => cmd.run(),
+            Cmd::TestProblem(cmd) => cmd.run(),
+            Cmd::FormatProject(cmd) => cmd.run(),
+            Cmd::Explain(cmd) => cmd.run(),
+            Cmd::Status(cmd) => cmd.run(),
+            Cmd::UpgradeLib(cmd) => cmd.run(),
+            Cmd::ApiDiff(cmd) => cmd.run(),
+            Cmd::Setup(cmd) => cmd.run(),
+            Cmd::Config(cmd) => cmd.run(),
+            Cmd::Repro(cmd) => cmd.run(),
+            Cmd::Promote(cmd) => cmd.run(),
+            Cmd::CiInit(cmd) => cmd.run(),
+            Cmd::Open(cmd) => cmd.run(),
+            Cmd::UseAdd(cmd) => cmd.run(),
+            Cmd::Doc(cmd) => cmd.run(),
+            Cmd::Retemplate(cmd) => cmd.run(),
+            Cmd::ShareFailure(cmd) => cmd.run(),
+            Cmd::Stats(cmd) => cmd.run(),
+            Cmd::Coverage(cmd) => cmd.run(),
+            Cmd::Dedup(cmd) => cmd.run(),
+            Cmd::Upsolve(cmd) => cmd.run(),
+            Cmd::Hint(cmd) => cmd.run(),
+            Cmd::LibReport(cmd) => cmd.run(),
+            Cmd::GenPrelude(cmd) => cmd.run(),
+        }
+    }
+}
+
+impl Cmd {
+    /// Name of the command, as given on the command line.
+    fn name(&self) -> &'static str {
+        fn name<T: SubCommand>(_: &T) -> &'static str {
+            T::COMMAND.name
+        }
+        match self {
+            Cmd::NewContest(cmd) => name(cmd),
+            Cmd::BundleProblem(cmd) => name(cmd),
+            Cmd::AddProblem(cmd) => name(cmd),
+            Cmd::RunProblem(cmd) => name(cmd),
+            Cmd::TestProblem(cmd) => name(cmd),
+            Cmd::FormatProject(cmd) => name(cmd),
+            Cmd::Explain(cmd) => name(cmd),
+            Cmd::Status(cmd) => name(cmd),
+            Cmd::UpgradeLib(cmd) => name(cmd),
+            Cmd::ApiDiff(cmd) => name(cmd),
+            Cmd::Setup(cmd) => name(cmd),
+            Cmd::Config(cmd) => name(cmd),
+            Cmd::Repro(cmd) => name(cmd),
+            Cmd::Promote(cmd) => name(cmd),
+            Cmd::CiInit(cmd) => name(cmd),
+            Cmd::Open(cmd) => name(cmd),
+            Cmd::UseAdd(cmd) => name(cmd),
+            Cmd::Doc(cmd) => name(cmd),
+            Cmd::Retemplate(cmd) => name(cmd),
+            Cmd::ShareFailure(cmd) => name(cmd),
+            Cmd::Stats(cmd) => name(cmd),
+            Cmd::Coverage(cmd) => name(cmd),
+            Cmd::Dedup(cmd) => name(cmd),
+            Cmd::Upsolve(cmd) => name(cmd),
+            Cmd::Hint(cmd) => name(cmd),
+            Cmd::LibReport(cmd) => name(cmd),
+            Cmd::GenPrelude(cmd) => name(cmd),
         }
     }
 }
@@ -80,3 +281,57 @@ pub fn copy_to(dir: &Dir, src: &str, target: &Path) -> std::io::Result<()> {
     }
     fs::write(target, file.contents())
 }
+
+/// Write file atomically: content is written into a temporary file next to the
+/// target, which is then renamed over it.
+///
+/// Concurrent writers (and readers) never observe partially written files.
+pub fn write_atomic(target: &Path, content: impl AsRef<[u8]>) -> std::io::Result<()> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let tmp = target.with_file_name(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::write(&tmp, content)?;
+    fs::rename(&tmp, target).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Current time, as seconds since Unix epoch.
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Format seconds since Unix epoch as `YYYY-MM-DD HH:MM:SS UTC`.
+pub fn format_utc(secs: u64) -> String {
+    let (days, rem) = (secs / 86400, secs % 86400);
+
+    // Convert days since epoch to a civil date (see Howard Hinnant's
+    // `civil_from_days` algorithm).
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02} {:02}:{:02}:{:02} UTC",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
@@ -0,0 +1,48 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        add::open_in_editor,
+        config::setting,
+        problem::{resolve_id, solution_path, statement_path, write_statement_stub},
+    },
+    anyhow::{Result, anyhow},
+    argh::FromArgs,
+    std::path::Path,
+};
+
+/// Open a problem file (its companion module, if it has one), or its statement
+/// notes, with the configured editor.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "open")]
+pub struct OpenSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+
+    #[argh(switch)]
+    /// open the statement notes, `statements/{id}.md` (created, if missing)
+    statement: bool,
+}
+
+impl SubCmd for OpenSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+        let Some(editor) = setting("editor")? else {
+            return Err(anyhow!(
+                "no editor is configured, set it with `algorist config set editor <command>`"
+            ));
+        };
+
+        let file = if self.statement {
+            // Problems, added before statement notes were introduced, have none yet.
+            if write_statement_stub(Path::new("."), &id, None)? {
+                println!("Statement notes stub created at {:?}", statement_path(&id));
+            }
+            statement_path(&id)
+        } else {
+            solution_path(Path::new("."), &id)
+        };
+        open_in_editor(&editor, &file);
+        Ok(())
+    }
+}
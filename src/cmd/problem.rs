@@ -0,0 +1,259 @@
+use {
+    crate::cmd::{
+        TPL_DIR,
+        config::setting,
+        meta::{Event, MetaStore},
+        scaffold::{Template, solve_main},
+    },
+    anyhow::{Context, Result, anyhow},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        time::UNIX_EPOCH,
+    },
+};
+
+/// Directory with problem binaries, relative to the project root.
+pub const BIN_DIR: &str = "src/bin";
+
+/// Directory with problem statement notes, relative to the project root.
+pub const STATEMENTS_DIR: &str = "statements";
+
+/// IDs of the problems in the project, sorted.
+pub fn problem_ids(root: &Path) -> Result<Vec<String>> {
+    let bin_dir = root.join(BIN_DIR);
+    let mut ids = fs::read_dir(&bin_dir)
+        .context(format!("failed to read {}", bin_dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "rs"))
+        .filter_map(|path| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .collect::<Vec<_>>();
+    ids.sort();
+    Ok(ids)
+}
+
+/// Companion module of a problem, relative to the project root: with the
+/// `solve_module` setting on, the solution goes there, and the problem file
+/// only calls its `solve()`.
+pub fn solve_module_path(id: &str) -> PathBuf {
+    Path::new(BIN_DIR).join(id).join("solve.rs")
+}
+
+/// File with the solution of a problem, relative to the project root: its
+/// companion module, if it has one, or the problem file itself.
+pub fn solution_path(root: &Path, id: &str) -> PathBuf {
+    let module = solve_module_path(id);
+    if root.join(&module).exists() {
+        module
+    } else {
+        Path::new(BIN_DIR).join(format!("{id}.rs"))
+    }
+}
+
+/// Write the files of a new problem, from the configured template (or the
+/// built-in one): the problem file, and, with the `solve_module` setting on,
+/// its companion module (which gets the template then).
+///
+/// If the solution of another problem is given, its code is put into the
+/// template, see [`apply_template`](crate::cmd::scaffold::apply_template).
+///
+/// Returns the file with the solution, relative to the project root.
+pub fn write_template(root: &Path, id: &str, from: Option<&str>) -> Result<PathBuf> {
+    let solve_module = setting("solve_module")?.as_deref() == Some("on");
+    let template = Template::load(solve_module)?;
+    let content = match from {
+        Some(source) => template.render_with(source)?,
+        None => template.render(),
+    };
+    let main = Path::new(BIN_DIR).join(format!("{id}.rs"));
+    let files = if solve_module {
+        vec![(solve_module_path(id), content), (main, solve_main(id)?)]
+    } else {
+        vec![(main, content)]
+    };
+    for (file, content) in &files {
+        let target = root.join(file);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&target, content).context(format!("failed to write {}", target.display()))?;
+    }
+    Ok(files[0].0.clone())
+}
+
+/// Path of the statement notes of a problem, relative to the project root.
+pub fn statement_path(id: &str) -> PathBuf {
+    Path::new(STATEMENTS_DIR).join(format!("{id}.md"))
+}
+
+/// Problem URL, as recorded in the statement notes (the `URL:` line), if any.
+pub fn statement_url(root: &Path, id: &str) -> Option<String> {
+    statement_field(root, id, "URL:")
+}
+
+/// Editorial URL, as recorded in the statement notes (the `Editorial:` line),
+/// if any.
+pub fn statement_editorial(root: &Path, id: &str) -> Option<String> {
+    statement_field(root, id, "Editorial:")
+}
+
+fn statement_field(root: &Path, id: &str, prefix: &str) -> Option<String> {
+    fs::read_to_string(root.join(statement_path(id)))
+        .ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix(prefix))
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(String::from)
+}
+
+/// Write a statement notes stub (with the URL, constraints and notes
+/// sections), unless the problem has one already.
+///
+/// Returns whether the stub was written.
+pub fn write_statement_stub(root: &Path, id: &str, url: Option<&str>) -> Result<bool> {
+    let target = root.join(statement_path(id));
+    if target.exists() {
+        return Ok(false);
+    }
+    let template = TPL_DIR
+        .get_file("statement.md")
+        .and_then(|file| file.contents_utf8())
+        .context("statement template should exist")?;
+    let content = template
+        .replace("{{ID}}", id)
+        .replace("{{URL}}", url.unwrap_or(""));
+    fs::create_dir_all(root.join(STATEMENTS_DIR))
+        .context("failed to create statements directory")?;
+    fs::write(&target, content).context(format!("failed to write {}", target.display()))?;
+    Ok(true)
+}
+
+/// Normalize a problem ID, as given on the command line: `A`, `a`, `a.rs` and
+/// `src/bin/a.rs` all refer to problem `a`.
+pub fn normalize_id(id: &str) -> String {
+    file_stem(id).to_lowercase()
+}
+
+/// Resolve a problem ID, as given on the command line, to the ID of an
+/// existing problem.
+///
+/// IDs are matched case-insensitively, an exact match is preferred if several
+/// problems differ only in case.
+pub fn resolve_id(root: &Path, id: &str) -> Result<String> {
+    let ids = problem_ids(root)?;
+    let name = file_stem(id);
+    if ids.contains(&name) {
+        return Ok(name);
+    }
+
+    let normalized = normalize_id(id);
+    let matches = ids
+        .iter()
+        .filter(|candidate| candidate.to_lowercase() == normalized)
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [id] => Ok(id.to_string()),
+        [] => Err(anyhow!("problem {id:?} is not found in {BIN_DIR}")),
+        _ => Err(anyhow!(
+            "problem {id:?} is ambiguous, matches: {}",
+            matches
+                .iter()
+                .map(|id| id.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+    }
+}
+
+/// Select problems to operate on: either a single one, given by ID, all of
+/// them (`--all`), or only those whose source file was modified since the
+/// given event was last recorded for them (`--changed`).
+pub fn select_ids(
+    root: &Path,
+    id: Option<&str>,
+    all: bool,
+    changed: bool,
+    since: Event,
+) -> Result<Vec<String>> {
+    match (id, all, changed) {
+        (Some(id), false, false) => Ok(vec![resolve_id(root, id)?]),
+        (None, true, false) => problem_ids(root),
+        (None, false, true) => {
+            let store = MetaStore::open(root)?;
+            let mut ids = Vec::new();
+            for id in problem_ids(root)? {
+                let mut modified = modified_secs(&root.join(BIN_DIR).join(format!("{id}.rs")))?;
+                if root.join(solve_module_path(&id)).exists() {
+                    modified = modified.max(modified_secs(&root.join(solve_module_path(&id)))?);
+                }
+                // Timestamps are in seconds, so a change within the same second is
+                // considered to be made after the event.
+                if store.get(&id, since).is_none_or(|at| modified >= at) {
+                    ids.push(id);
+                }
+            }
+            Ok(ids)
+        }
+        (None, false, false) => Err(anyhow!(
+            "either a problem ID, `--all` or `--changed` is required"
+        )),
+        _ => Err(anyhow!(
+            "problem ID, `--all` and `--changed` are mutually exclusive"
+        )),
+    }
+}
+
+/// Run an operation on each of the selected problems.
+///
+/// A single problem is handled as is, while for several ones failures do not
+/// stop the batch: they are reported in a summary once all problems are done.
+pub fn for_each_id(ids: &[String], mut op: impl FnMut(&str) -> Result<()>) -> Result<()> {
+    if let [id] = ids {
+        return op(id);
+    }
+
+    let mut failed = Vec::new();
+    for id in ids {
+        println!("==> Problem {id:?}");
+        if let Err(e) = op(id) {
+            println!("Problem {id:?} failed: {e:#}");
+            failed.push(id.as_str());
+        }
+        println!();
+    }
+    println!(
+        "{}/{} problem(s) succeeded",
+        ids.len() - failed.len(),
+        ids.len()
+    );
+    if !failed.is_empty() {
+        return Err(anyhow!("failed problem(s): {}", failed.join(", ")));
+    }
+    Ok(())
+}
+
+fn modified_secs(path: &Path) -> Result<u64> {
+    let modified = fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .context(format!(
+            "failed to read modification time of {}",
+            path.display()
+        ))?;
+    Ok(modified
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs())
+}
+
+/// Problem ID, with the directory and `.rs` extension stripped.
+fn file_stem(id: &str) -> String {
+    let name = Path::new(id.trim())
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    name.strip_suffix(".rs").unwrap_or(&name).to_string()
+}
@@ -0,0 +1,233 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        problem::resolve_id,
+        run::saved_run,
+        test::{
+            artifacts::Artifacts,
+            runner::{TestCase, Verdict},
+        },
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        fs,
+        io::{self, BufRead, Write},
+        path::{Path, PathBuf},
+    },
+};
+
+/// Maximum number of diff lines shown per case.
+const MAX_DIFF_LINES: usize = 40;
+
+/// Outputs, larger than this (in lines), are not diffed, as the diff is
+/// quadratic.
+const MAX_DIFF_INPUT: usize = 2000;
+
+/// Accept actual outputs of failing (or new) test cases as the expected ones,
+/// or the output, saved by `run --save-output`.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "promote")]
+pub struct PromoteSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+
+    #[argh(positional)]
+    /// case to promote (defaults to all cases, recorded by the last `test`
+    /// run)
+    case: Option<String>,
+
+    #[argh(switch, short = 'y')]
+    /// promote without asking for confirmation
+    yes: bool,
+
+    #[argh(switch)]
+    /// promote the output, saved by `run --save-output`, as the expected
+    /// output of the case (defaults to the case with the same input, or a new
+    /// one)
+    run: bool,
+}
+
+impl SubCmd for PromoteSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+        if self.run {
+            return self.promote_saved_run(&id);
+        }
+        let artifacts = Artifacts::new(&id);
+        let mut cases = artifacts.cases();
+        if let Some(name) = &self.case {
+            cases.retain(|(case, _)| case == name);
+            if cases.is_empty() {
+                return Err(anyhow!(
+                    "case {name:?} of problem {id:?} has no recorded output, run `algorist test \
+                     {id}` first"
+                ));
+            }
+        }
+        if cases.is_empty() {
+            println!("No failing or new cases recorded for problem {id:?}, nothing to promote.");
+            return Ok(());
+        }
+
+        let tests_dir = PathBuf::from("tests").join(&id);
+        let mut lines = io::stdin().lock().lines();
+        let mut promoted = 0;
+        for (name, dir) in &cases {
+            // Outputs of crashed or timed out runs are never correct.
+            let verdict = fs::read_to_string(dir.join("verdict.txt")).unwrap_or_default();
+            let promotable = [Verdict::WrongAnswer, Verdict::NoExpected]
+                .iter()
+                .any(|v| v.to_string() == verdict.trim());
+            if !promotable {
+                println!("Skipping case {name:?}: {}", verdict.trim());
+                continue;
+            }
+            if !tests_dir.join(format!("{name}.in")).exists() {
+                println!("Skipping case {name:?}: its input is no longer in {tests_dir:?}");
+                continue;
+            }
+
+            let actual = fs::read_to_string(dir.join("actual.txt"))
+                .context("failed to read actual output")?;
+            let expected = fs::read_to_string(dir.join("expected.txt")).unwrap_or_default();
+            println!("\n==> Case {name:?} ({})", verdict.trim());
+            for line in diff(&expected, &actual) {
+                println!("{line}");
+            }
+
+            let target = tests_dir.join(format!("{name}.out"));
+            if !self.yes && !confirm(&mut lines, &target)? {
+                continue;
+            }
+            fs::write(&target, &actual).context(format!("failed to write {}", target.display()))?;
+            fs::remove_dir_all(dir).context("failed to remove promoted case artifacts")?;
+            promoted += 1;
+        }
+        println!("\n{promoted}/{} case(s) promoted", cases.len());
+        Ok(())
+    }
+}
+
+impl PromoteSubCmd {
+    /// Turn the last saved run of the problem into a test case: the given one,
+    /// the one with the same input, or a new one (numbered after the existing
+    /// ones).
+    fn promote_saved_run(&self, id: &str) -> Result<()> {
+        let (input_file, output_file) = saved_run(id);
+        if !output_file.exists() || !input_file.exists() {
+            return Err(anyhow!(
+                "no saved output for problem {id:?}, run it with `algorist run {id} \
+                 --save-output` first"
+            ));
+        }
+        let input = fs::read(&input_file).context("failed to read saved input")?;
+        let actual = fs::read_to_string(&output_file).context("failed to read saved output")?;
+
+        let tests_dir = PathBuf::from("tests").join(id);
+        let cases = if tests_dir.exists() {
+            TestCase::discover(&tests_dir)?
+        } else {
+            Vec::new()
+        };
+        let same_input = |case: &TestCase| fs::read(&case.input).is_ok_and(|c| c == input);
+        let name = match &self.case {
+            Some(name) => match cases.iter().find(|case| &case.name == name) {
+                Some(case) if !same_input(case) => {
+                    return Err(anyhow!(
+                        "case {name:?} of problem {id:?} has another input than the saved run"
+                    ));
+                }
+                _ => name.clone(),
+            },
+            None => match cases.iter().find(|case| same_input(case)) {
+                Some(case) => case.name.clone(),
+                None => cases
+                    .iter()
+                    .filter_map(|case| case.name.parse::<u64>().ok())
+                    .max()
+                    .map_or(1, |n| n + 1)
+                    .to_string(),
+            },
+        };
+
+        let target = tests_dir.join(format!("{name}.out"));
+        let expected = fs::read_to_string(&target).unwrap_or_default();
+        println!("==> Case {name:?}");
+        for line in diff(&expected, &actual) {
+            println!("{line}");
+        }
+        if !self.yes && !confirm(&mut io::stdin().lock().lines(), &target)? {
+            return Ok(());
+        }
+
+        let input_target = tests_dir.join(format!("{name}.in"));
+        if !input_target.exists() {
+            fs::create_dir_all(&tests_dir).context("failed to create tests directory")?;
+            fs::write(&input_target, &input).context("failed to write case input")?;
+        }
+        fs::write(&target, &actual).context(format!("failed to write {}", target.display()))?;
+        println!(
+            "Output of {} promoted to the expected output of case {name:?} ({})",
+            output_file.display(),
+            target.display()
+        );
+        Ok(())
+    }
+}
+
+/// Ask whether to accept the actual output as the given expected output file.
+fn confirm(lines: &mut impl Iterator<Item = io::Result<String>>, target: &Path) -> Result<bool> {
+    print!("Accept the actual output as {}? [y/N] ", target.display());
+    io::stdout().flush()?;
+    let answer = lines.next().transpose().context("failed to read answer")?;
+    Ok(answer.is_some_and(|answer| answer.trim().eq_ignore_ascii_case("y")))
+}
+
+/// Line diff between the expected and the actual outputs: removed lines are
+/// prefixed with `-`, added ones with `+`, and only changes are shown.
+fn diff(expected: &str, actual: &str) -> Vec<String> {
+    let old = expected.lines().collect::<Vec<_>>();
+    let new = actual.lines().collect::<Vec<_>>();
+    if old.len() > MAX_DIFF_INPUT || new.len() > MAX_DIFF_INPUT {
+        return vec![format!(
+            "(outputs are too large to diff: {} vs {} lines)",
+            old.len(),
+            new.len()
+        )];
+    }
+
+    // Longest common subsequence of lines, computed from the end.
+    let mut lcs = vec![vec![0; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            changes.push(format!("-{}", old[i]));
+            i += 1;
+        } else {
+            changes.push(format!("+{}", new[j]));
+            j += 1;
+        }
+    }
+    if changes.len() > MAX_DIFF_LINES {
+        let more = changes.len() - MAX_DIFF_LINES;
+        changes.truncate(MAX_DIFF_LINES);
+        changes.push(format!("... {more} more changed line(s)"));
+    }
+    changes
+}
@@ -0,0 +1,219 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        problem::{BIN_DIR, resolve_id},
+        test::{
+            artifacts::Artifacts,
+            runner::{TestCase, Verdict},
+        },
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// Manifest of the reproduction crate: the bundled file needs no dependencies.
+const MANIFEST: &str = r#"[package]
+name = "{{NAME}}"
+version = "0.1.0"
+edition = "2021"
+
+# A workspace of its own: the crate is normally exported into the contest directory, which may
+# itself be (isolated) within a cargo workspace.
+[workspace]
+
+[dependencies]
+"#;
+
+/// Export the bundled solution and a failing test case as a standalone crate.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "repro")]
+pub struct ReproSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+
+    #[argh(option)]
+    /// case to include: a failing one (saved by `test`), or one from
+    /// `tests/{id}/` (defaults to the first failing case)
+    case: Option<String>,
+
+    #[argh(option)]
+    /// directory to create the crate in (defaults to `repro-{id}`)
+    out: Option<String>,
+}
+
+/// Files of a reproduction case.
+pub struct ReproCase {
+    pub name: String,
+    pub input: PathBuf,
+    pub expected: Option<PathBuf>,
+
+    /// Actual output, standard error and verdict, if the case is a recorded
+    /// failure.
+    pub actual: Option<PathBuf>,
+    pub stderr: Option<PathBuf>,
+    pub verdict: Option<String>,
+}
+
+impl SubCmd for ReproSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+
+        // Only the bundled file is shared: it is self-contained, and doesn't expose the
+        // library layout.
+        let bundled = Path::new("bundled").join(BIN_DIR).join(format!("{id}.rs"));
+        if !bundled.exists() {
+            return Err(anyhow!(
+                "problem {id:?} is not bundled yet, run `algorist bundle {id}` first"
+            ));
+        }
+        let source = Path::new(BIN_DIR).join(format!("{id}.rs"));
+        if modified(&source)? > modified(&bundled)? {
+            println!(
+                "Warning: {} is older than the solution, run `algorist bundle {id}` to refresh it",
+                bundled.display()
+            );
+        }
+        let case = find_case(&id, self.case.as_deref())?;
+
+        let out = PathBuf::from(self.out.clone().unwrap_or_else(|| format!("repro-{id}")));
+        if out.exists() {
+            return Err(anyhow!("directory {} already exists", out.display()));
+        }
+        fs::create_dir_all(out.join("src")).context("failed to create repro directory")?;
+        let manifest = MANIFEST.replace("{{NAME}}", &format!("repro-{}", id.replace('_', "-")));
+        fs::write(out.join("Cargo.toml"), manifest)?;
+        fs::copy(&bundled, out.join("src/main.rs")).context("failed to copy bundled file")?;
+
+        let files = [
+            ("input.txt", Some(&case.input)),
+            ("expected.txt", case.expected.as_ref()),
+            ("actual.txt", case.actual.as_ref()),
+            ("stderr.txt", case.stderr.as_ref()),
+        ];
+        let mut included = Vec::new();
+        for (name, path) in files {
+            if let Some(path) = path {
+                fs::copy(path, out.join(name)).context(format!("failed to copy {name}"))?;
+                included.push(name);
+            }
+        }
+        fs::write(out.join("README.md"), readme(&id, &case, &included))?;
+
+        println!(
+            "Reproduction crate for problem {id:?} (case {:?}) created at {}",
+            case.name,
+            out.display()
+        );
+        Ok(())
+    }
+}
+
+/// Case of a problem to reproduce: the requested one (a recorded failure, or
+/// one from `tests/{id}/`), or the first recorded failure.
+pub fn find_case(id: &str, case: Option<&str>) -> Result<ReproCase> {
+    let failures = Artifacts::new(id).cases();
+    let failure = match case {
+        Some(name) => failures.into_iter().find(|(case, _)| case == name),
+        // New cases (without expected output) are recorded as well, but don't fail.
+        None => failures.into_iter().find(|(_, dir)| {
+            fs::read_to_string(dir.join("verdict.txt"))
+                .is_ok_and(|verdict| verdict != Verdict::NoExpected.to_string())
+        }),
+    };
+    if let Some((name, dir)) = failure {
+        let file = |name: &str| Some(dir.join(name)).filter(|path| path.exists());
+        return Ok(ReproCase {
+            name,
+            input: dir.join("input.txt"),
+            expected: file("expected.txt"),
+            actual: file("actual.txt"),
+            stderr: file("stderr.txt"),
+            verdict: fs::read_to_string(dir.join("verdict.txt")).ok(),
+        });
+    }
+
+    let Some(name) = case else {
+        return Err(anyhow!(
+            "no failing cases recorded for problem {id:?}, run `algorist test {id}` first, or \
+             pick a case with `--case`"
+        ));
+    };
+    let tests_dir = Path::new("tests").join(id);
+    let case = TestCase::discover(&tests_dir)?
+        .into_iter()
+        .find(|case| case.name == name)
+        .context(format!(
+            "case {name:?} is not found in {}",
+            tests_dir.display()
+        ))?;
+    Ok(ReproCase {
+        name: case.name,
+        input: case.input,
+        expected: case.expected,
+        actual: None,
+        stderr: None,
+        verdict: None,
+    })
+}
+
+fn readme(id: &str, case: &ReproCase, files: &[&str]) -> String {
+    let mut lines = vec![
+        format!("# Reproduction: problem `{id}`, case `{}`", case.name),
+        String::new(),
+        "Self-contained solution (`src/main.rs`, depends on `std` only), along with the test case \
+         it fails on."
+            .to_string(),
+        String::new(),
+        "To run:".to_string(),
+        String::new(),
+        "``` bash".to_string(),
+        "cargo run --release < input.txt".to_string(),
+        "```".to_string(),
+        String::new(),
+        "Files:".to_string(),
+        String::new(),
+    ];
+    for file in files {
+        let description = match *file {
+            "input.txt" => "input",
+            "expected.txt" => "expected output",
+            "actual.txt" => "actual output of the solution",
+            "stderr.txt" => "standard error of the solution",
+            _ => continue,
+        };
+        lines.push(format!("- `{file}`: {description}"));
+    }
+    lines.push(String::new());
+    lines.push("Environment:".to_string());
+    lines.push(String::new());
+    if let Some(rustc) = rustc_version() {
+        lines.push(format!("- {rustc}"));
+    }
+    lines.push(format!(
+        "- {} {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    ));
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+pub fn rustc_version() -> Option<String> {
+    let output = Command::new("rustc").arg("--version").output().ok()?;
+    let version = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !version.is_empty()).then_some(version)
+}
+
+pub fn modified(path: &Path) -> Result<std::time::SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .context(format!(
+            "failed to read modification time of {}",
+            path.display()
+        ))
+}
@@ -0,0 +1,76 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        problem::{BIN_DIR, resolve_id, solution_path, solve_module_path},
+        scaffold::{Template, solve_main, template_stamp},
+        write_atomic,
+    },
+    anyhow::{Context, Result},
+    argh::FromArgs,
+    std::{fs, path::Path},
+};
+
+/// Re-apply the (updated) problem template to a problem file, keeping the code
+/// within its marked regions (e.g. the solution).
+///
+/// For problems with a companion module, the template is applied to the
+/// module, and the problem file, calling its `solve()`, is re-generated.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "retemplate")]
+pub struct RetemplateSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+
+    #[argh(switch)]
+    /// re-apply the template, even if the problem was generated from its
+    /// current version
+    force: bool,
+}
+
+impl SubCmd for RetemplateSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+        let file = solution_path(Path::new("."), &id);
+        let content =
+            fs::read_to_string(&file).context(format!("failed to read {}", file.display()))?;
+
+        let solve_module = file == solve_module_path(&id);
+        if solve_module {
+            sync_main(&id)?;
+        }
+        let template = Template::load(solve_module)?;
+        let stamp = template_stamp(&content);
+        if stamp == Some(template.id.as_str()) && !self.force {
+            println!(
+                "Problem {id:?} is generated from the current template ({}) already",
+                template.id
+            );
+            return Ok(());
+        }
+
+        let updated = template
+            .render_with(&content)
+            .context(format!("failed to re-template {}", file.display()))?;
+        write_atomic(&file, updated).context(format!("failed to write {}", file.display()))?;
+        println!(
+            "Problem {id:?} re-templated: {} -> {}",
+            stamp.unwrap_or("unknown template"),
+            template.id
+        );
+        Ok(())
+    }
+}
+
+/// Re-generate the problem file, calling `solve()` of the companion module,
+/// unless it is up to date.
+fn sync_main(id: &str) -> Result<()> {
+    let file = Path::new(BIN_DIR).join(format!("{id}.rs"));
+    let content = solve_main(id)?;
+    if fs::read_to_string(&file).ok().as_deref() == Some(content.as_str()) {
+        return Ok(());
+    }
+    write_atomic(&file, content).context(format!("failed to write {}", file.display()))?;
+    println!("Problem file {} re-generated", file.display());
+    Ok(())
+}
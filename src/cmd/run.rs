@@ -1,10 +1,29 @@
 use {
-    crate::cmd::SubCmd,
-    anyhow::{Context, Result},
+    crate::cmd::{
+        SubCmd,
+        cargo,
+        config::setting,
+        jobs::Jobs,
+        meta::{Event, record_events},
+        problem::resolve_id,
+        target::Target,
+        test::runner::{BuildOptions, build},
+    },
+    anyhow::{Context, Result, anyhow},
     argh::FromArgs,
-    std::{fs, path::PathBuf, process},
+    std::{
+        fs,
+        io::{self, Read, Write},
+        path::{Path, PathBuf},
+        process::Stdio,
+        sync::{Arc, Mutex},
+        thread,
+    },
 };
 
+/// Directory with outputs, saved by `run --save-output`.
+const OUTPUTS_DIR: &str = "outputs";
+
 /// Run a given problem using the `cargo run` command.
 #[derive(FromArgs)]
 #[argh(subcommand, name = "run")]
@@ -13,6 +32,31 @@ pub struct RunProblemSubCmd {
     /// read input from `inputs/{id}.txt` file, if it exists
     from_file: bool,
 
+    #[argh(option)]
+    /// input to run with, instead of typing it in: escape sequences (`\n`,
+    /// `\t`, `\\`) are expanded, and `-` reads it from stdin
+    input_text: Option<String>,
+
+    #[argh(switch)]
+    /// save the output (and the input) of a successful run into
+    /// `outputs/{id}.txt`, so that it can be promoted to a test case with
+    /// `promote --run`
+    save_output: bool,
+
+    #[argh(switch)]
+    /// show all compiler diagnostics, if the solution fails to compile (only
+    /// errors are shown by default)
+    full: bool,
+
+    #[argh(switch)]
+    /// report where the build time is spent, see `cargo build --timings`
+    timings: bool,
+
+    #[argh(option)]
+    /// target to build the solution for, and to run it on (overrides the
+    /// configured `target`)
+    target: Option<String>,
+
     #[argh(positional)]
     /// problem ID
     id: String,
@@ -20,38 +64,190 @@ pub struct RunProblemSubCmd {
 
 impl SubCmd for RunProblemSubCmd {
     fn run(&self) -> Result<()> {
-        let id = self.id.trim_end_matches(".rs");
-        if self.from_file {
-            let inputs_dir = PathBuf::from("inputs");
-            let input_file = inputs_dir.join(format!("{}.txt", self.id.trim_end_matches(".rs")));
-            if input_file.exists() {
-                println!("Running problem {id:?} with input from {input_file:?}",);
-                println!(
-                    "Executing: cargo run --bin {id} -- < {}",
-                    input_file.display()
-                );
-                let input = fs::File::open(input_file)?;
-                process::Command::new("cargo")
-                    .arg("run")
-                    .arg("--bin")
-                    .arg(id)
-                    .stdin(process::Stdio::from(input))
-                    .status()
-                    .context("failed to run cargo command")?;
-                return Ok(());
+        let id = resolve_id(Path::new("."), &self.id)?;
+        let id = id.as_str();
+        if self.from_file && self.input_text.is_some() {
+            return Err(anyhow!("`-i` and `--input-text` can't be used together"));
+        }
+        record_events(Path::new("."), &[id], Event::FirstRun);
+
+        let input = match &self.input_text {
+            Some(text) if text == "-" => {
+                let mut input = String::new();
+                io::stdin()
+                    .read_to_string(&mut input)
+                    .context("failed to read input from stdin")?;
+                Input::Text(input)
             }
+            Some(text) => Input::Text(unescape(text)),
+            None => {
+                let input_file = PathBuf::from("inputs").join(format!("{id}.txt"));
+                if self.from_file && input_file.exists() {
+                    Input::File(input_file)
+                } else {
+                    Input::Inherit
+                }
+            }
+        };
+        let save_output = self.save_output || setting("save_output")?.as_deref() == Some("on");
+        let target = Target::resolve(self.target.as_deref())?;
+
+        // Built upfront, so that compile errors are not buried under warnings.
+        build(id, &BuildOptions {
+            full_diagnostics: self.full,
+            timings: self.timings,
+            target: target.clone(),
+            ..Default::default()
+        })?;
+        run_problem(id, input, save_output, target.as_ref())
+    }
+}
+
+/// Input, a problem is run with.
+enum Input {
+    /// Standard input of the tool itself (no redirection).
+    Inherit,
+
+    /// Contents of a file.
+    File(PathBuf),
+
+    /// Inline text.
+    Text(String),
+}
+
+fn run_problem(id: &str, input: Input, save_output: bool, target: Option<&Target>) -> Result<()> {
+    let mut command = cargo::command("run", Jobs::default());
+    command.arg("--bin").arg(id);
+    let mut shown = format!("cargo run --bin {id}");
+    if let Some(target) = target {
+        command.args(target.cargo_args());
+        target.set_runner_env(&mut command);
+        shown.push_str(&format!(" --target {}", target.triple));
+    }
+    match &input {
+        Input::Inherit => {
+            // By default, run the problem without input redirection.
+            println!("Running problem {id:?} without input redirection");
+            println!("Executing: {shown}");
+        }
+        Input::File(input_file) => {
+            println!("Running problem {id:?} with input from {input_file:?}",);
+            println!("Executing: {shown} -- < {}", input_file.display());
+            command.stdin(Stdio::from(fs::File::open(input_file)?));
+        }
+        Input::Text(_) => {
+            println!("Running problem {id:?} with inline input");
+            println!("Executing: {shown}");
+            command.stdin(Stdio::piped());
         }
+    }
+    if save_output {
+        // Typed input is passed through, so that it can be saved along with the output.
+        if let Input::Inherit = input {
+            command.stdin(Stdio::piped());
+        }
+        command.stdout(Stdio::piped());
+    }
+
+    let mut child = command.spawn().context("failed to run cargo command")?;
+
+    // Input is fed from a separate thread, as the output may have to be read
+    // concurrently (otherwise, both pipes may fill up).
+    let typed = Arc::new(Mutex::new(Vec::new()));
+    if let Some(mut stdin) = child.stdin.take() {
+        let text = match &input {
+            Input::Text(text) => Some(text.clone()),
+            _ => None,
+        };
+        let typed = Arc::clone(&typed);
+        thread::spawn(move || {
+            // The solution may exit without reading all of its input, which is fine.
+            let Some(text) = text else {
+                return tee(&mut io::stdin(), &mut stdin, &typed);
+            };
+            let _ = stdin.write_all(text.as_bytes());
+        });
+    }
+    let output = Arc::new(Mutex::new(Vec::new()));
+    if let Some(mut stdout) = child.stdout.take() {
+        tee(&mut stdout, &mut io::stdout(), &output);
+    }
+    let status = child.wait().context("failed to run cargo command")?;
 
-        // By default, run the problem without input redirection.
-        println!("Running problem {id:?} without input redirection");
-        println!("Executing: cargo run --bin {id}");
-        process::Command::new("cargo")
-            .arg("run")
-            .arg("--bin")
-            .arg(id)
-            .status()
-            .context("failed to run cargo command")?;
+    // Output of a crashed (or not even compiled) solution is not worth promoting.
+    if save_output && !status.success() {
+        println!("Warning: problem {id:?} failed ({status}), its output is not saved");
+    } else if save_output {
+        let input = match input {
+            Input::Inherit => typed.lock().unwrap().clone(),
+            Input::File(input_file) => fs::read(input_file).context("failed to read input file")?,
+            Input::Text(text) => text.into_bytes(),
+        };
+        let (input_file, output_file) = saved_run(id);
+        fs::create_dir_all(OUTPUTS_DIR).context("failed to create outputs directory")?;
+        fs::write(&input_file, input).context("failed to save input")?;
+        fs::write(&output_file, &*output.lock().unwrap()).context("failed to save output")?;
+        println!(
+            "Output saved into {}, promote it to a test case with `algorist promote {id} --run`",
+            output_file.display()
+        );
+    }
+    Ok(())
+}
 
-        Ok(())
+/// Files with the input and the output of the last run of a problem (saved
+/// with `--save-output`).
+pub fn saved_run(id: &str) -> (PathBuf, PathBuf) {
+    let dir = Path::new(OUTPUTS_DIR);
+    (
+        dir.join(format!("{id}.in.txt")),
+        dir.join(format!("{id}.txt")),
+    )
+}
+
+/// Copy everything from the reader into the writer, keeping a copy.
+fn tee(reader: &mut impl Read, writer: &mut impl Write, copy: &Mutex<Vec<u8>>) {
+    let mut buf = [0; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => n,
+        };
+        copy.lock().unwrap().extend_from_slice(&buf[..n]);
+        if writer
+            .write_all(&buf[..n])
+            .and_then(|_| writer.flush())
+            .is_err()
+        {
+            break;
+        }
+    }
+}
+
+/// Expand escape sequences (`\n`, `\t`, `\\`) of inline input, so that
+/// multi-line input fits on the command line (final line break is added, if
+/// missing).
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => unescaped.push('\n'),
+            Some('t') => unescaped.push('\t'),
+            Some('\\') => unescaped.push('\\'),
+            Some(other) => {
+                unescaped.push('\\');
+                unescaped.push(other);
+            }
+            None => unescaped.push('\\'),
+        }
+    }
+    if !unescaped.ends_with('\n') {
+        unescaped.push('\n');
     }
+    unescaped
 }
@@ -0,0 +1,299 @@
+use {
+    crate::cmd::{
+        TPL_DIR,
+        config::setting,
+        use_add::{add_import, offset},
+    },
+    anyhow::{Context, Result, anyhow},
+    quote::ToTokens,
+    sha2::{Digest, Sha256},
+    std::{fs, ops::Range, path::Path},
+    syn::spanned::Spanned,
+};
+
+/// Prefix of the first line of problem files, identifying the template they
+/// were generated from.
+const TEMPLATE_STAMP: &str = "// algorist:template ";
+
+/// Prefixes of the markers of regions: `// algorist:begin <name>` and
+/// `// algorist:end <name>`, each on a line of its own.
+const BEGIN_MARKER: &str = "// algorist:begin ";
+const END_MARKER: &str = "// algorist:end ";
+
+/// Region with the solution, which every template should have.
+pub const SOLVE_REGION: &str = "solve";
+
+/// Problem template: the configured one, or the built-in one.
+#[derive(Debug)]
+pub struct Template {
+    /// Identifier of the template version, e.g. `builtin-3fa2c1d09e7b`: the
+    /// template name, with a hash of its content.
+    pub id: String,
+
+    content: String,
+}
+
+impl Template {
+    /// Load the template. With `solve_module`, it is the template of the
+    /// companion module with the solution (see [`solve_main`]), rather than of
+    /// the problem file.
+    pub fn load(solve_module: bool) -> Result<Self> {
+        let (name, content) = match setting("template")? {
+            Some(template) => {
+                let content = fs::read_to_string(&template)
+                    .context(format!("failed to read problem template {template}"))?;
+                let name = Path::new(&template)
+                    .file_stem()
+                    .map_or("custom".into(), |stem| stem.to_string_lossy());
+                (name.into_owned(), content)
+            }
+            None => {
+                let file = if solve_module {
+                    "solve.rs"
+                } else {
+                    "problem.rs"
+                };
+                let content = TPL_DIR
+                    .get_file(file)
+                    .and_then(|file| file.contents_utf8())
+                    .context("problem template should exist")?;
+                ("builtin".to_string(), content.to_string())
+            }
+        };
+        // Templates, made of problem files, may be stamped already.
+        let content = match template_stamp(&content) {
+            Some(_) => content.split_once('\n').map_or("", |(_, rest)| rest),
+            None => &content,
+        };
+        let hash = format!("{:x}", Sha256::digest(content.as_bytes()));
+        Ok(Self {
+            id: format!("{name}-{}", &hash[..12]),
+            content: content.to_string(),
+        })
+    }
+
+    /// Content of a problem file, generated from the template: stamped with
+    /// the template ID.
+    pub fn render(&self) -> String {
+        format!("{TEMPLATE_STAMP}{}\n{}", self.id, self.content)
+    }
+
+    /// Content of a problem file, generated from the template, with the code
+    /// of the given problem file put into it: see [`apply_template`].
+    pub fn render_with(&self, source: &str) -> Result<String> {
+        apply_template(&self.render(), source)
+    }
+}
+
+/// Problem file, only calling `solve()` of its companion module (which is
+/// generated from the [`Template`]).
+///
+/// The file is the template's as a whole: there is nothing to keep, when it is
+/// re-generated.
+pub fn solve_main(id: &str) -> Result<String> {
+    let content = TPL_DIR
+        .get_file("solve_main.rs")
+        .and_then(|file| file.contents_utf8())
+        .context("problem file template should exist")?;
+    Ok(content.replace("{{ID}}", id))
+}
+
+/// ID of the template, the problem file was generated from, if it is stamped.
+pub fn template_stamp(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .next()?
+        .strip_prefix(TEMPLATE_STAMP)
+        .map(str::trim)
+}
+
+/// Region of a problem file, between a pair of markers: the code within it is
+/// the user's (e.g. the solution), everything outside is the template's.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Region {
+    pub name: String,
+
+    /// Byte range of the lines between the markers.
+    pub range: Range<usize>,
+}
+
+/// Marked regions of a problem file, in the order of appearance.
+///
+/// Rewrites of problem files (re-templating, or generating a problem from
+/// another one) only carry the code within regions, codemods should only
+/// touch the code outside of them.
+#[derive(Debug, Default)]
+pub struct Regions(Vec<Region>);
+
+impl Regions {
+    /// Find the regions of the content. Regions can not be nested, and their
+    /// names must be unique.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut regions = Vec::<Region>::new();
+        let mut open: Option<(String, usize, usize)> = None;
+        let mut offset = 0;
+        for (number, line) in content.split_inclusive('\n').enumerate() {
+            let number = number + 1;
+            let marker = line.trim();
+            if let Some(name) = marker.strip_prefix(BEGIN_MARKER).map(str::trim) {
+                if let Some((open, line, _)) = &open {
+                    return Err(anyhow!(
+                        "line {number}: region {name:?} starts within region {open:?}, opened at \
+                         line {line}"
+                    ));
+                }
+                if regions.iter().any(|region| region.name == name) {
+                    return Err(anyhow!("line {number}: region {name:?} is marked twice"));
+                }
+                open = Some((name.to_string(), number, offset + line.len()));
+            } else if let Some(name) = marker.strip_prefix(END_MARKER).map(str::trim) {
+                match open.take() {
+                    Some((open, _, start)) if open == name => regions.push(Region {
+                        name: open,
+                        range: start..offset,
+                    }),
+                    Some((open, line, _)) => {
+                        return Err(anyhow!(
+                            "line {number}: region {name:?} ends, but region {open:?} (opened at \
+                             line {line}) is not"
+                        ));
+                    }
+                    None => {
+                        return Err(anyhow!(
+                            "line {number}: region {name:?} ends, but is not opened"
+                        ));
+                    }
+                }
+            }
+            offset += line.len();
+        }
+        if let Some((name, line, _)) = open {
+            return Err(anyhow!(
+                "region {name:?}, opened at line {line}, is not closed"
+            ));
+        }
+        Ok(Self(regions))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Region> {
+        self.0.iter().find(|region| region.name == name)
+    }
+
+    /// Whether the byte offset is within one of the regions.
+    pub fn contains(&self, offset: usize) -> bool {
+        self.0.iter().any(|region| region.range.contains(&offset))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Region> {
+        self.0.iter()
+    }
+}
+
+/// Put the code of the problem file (`source`) into the rendered template:
+/// regions of the template get the content of the same regions of the file.
+///
+/// Imports and top-level items (e.g. helper functions) outside of the regions
+/// of the file, which the template doesn't have, are carried over as well, so
+/// that the code keeps compiling.
+pub fn apply_template(rendered: &str, source: &str) -> Result<String> {
+    let regions = Regions::parse(source).context("invalid region markers")?;
+    let template_regions =
+        Regions::parse(rendered).context("invalid region markers of the problem template")?;
+    if regions.get(SOLVE_REGION).is_none() {
+        return Err(anyhow!(
+            "no `{SOLVE_REGION}` region, marked with `{BEGIN_MARKER}{SOLVE_REGION}` and \
+             `{END_MARKER}{SOLVE_REGION}`"
+        ));
+    }
+    if let Some(region) = regions
+        .iter()
+        .find(|region| template_regions.get(&region.name).is_none())
+    {
+        return Err(anyhow!(
+            "region {:?} is missing in the problem template, its code would be lost",
+            region.name
+        ));
+    }
+
+    // Regions are filled from the end, so that the ranges of the others stay
+    // valid.
+    let mut updated = rendered.to_string();
+    for template_region in template_regions
+        .iter()
+        .collect::<Vec<_>>()
+        .into_iter()
+        .rev()
+    {
+        if let Some(region) = regions.get(&template_region.name) {
+            updated.replace_range(template_region.range.clone(), &source[region.range.clone()]);
+        }
+    }
+
+    let file = syn::parse_file(source).context("failed to parse the problem file")?;
+    let template = syn::parse_file(rendered).context("failed to parse the problem template")?;
+    let template_keys = template.items.iter().map(item_key).collect::<Vec<_>>();
+    let mut carried = Vec::new();
+    for item in &file.items {
+        let (start, end) = (
+            offset(source, item.span().start()),
+            offset(source, item.span().end()),
+        );
+        // Items, enclosing regions (e.g. `main()` of a problem file, put into the
+        // template of a companion module), are the template's as well.
+        let encloses_region = regions
+            .iter()
+            .any(|region| (start..end).contains(&region.range.start));
+        if regions.contains(start) || encloses_region || template_keys.contains(&item_key(item)) {
+            continue;
+        }
+        match item {
+            syn::Item::Use(import)
+                if import.attrs.is_empty()
+                    && import.leading_colon.is_none()
+                    && matches!(import.tree, syn::UseTree::Path(_)) =>
+            {
+                if let Some(merged) = add_import(&updated, import.clone())
+                    .context("failed to parse the generated file")?
+                {
+                    updated = merged;
+                }
+            }
+            _ => carried.push(&source[start..end]),
+        }
+    }
+    for item in carried {
+        updated = format!("{}\n\n{item}\n", updated.trim_end());
+    }
+    Ok(updated)
+}
+
+/// Key, identifying a top-level item: items with the same name (e.g. `main`)
+/// are taken from the template.
+fn item_key(item: &syn::Item) -> String {
+    match item {
+        syn::Item::Fn(item) => format!("fn {}", item.sig.ident),
+        syn::Item::Struct(syn::ItemStruct { ident, .. })
+        | syn::Item::Enum(syn::ItemEnum { ident, .. })
+        | syn::Item::Union(syn::ItemUnion { ident, .. })
+        | syn::Item::Trait(syn::ItemTrait { ident, .. })
+        | syn::Item::Type(syn::ItemType { ident, .. }) => format!("type {ident}"),
+        syn::Item::Const(syn::ItemConst { ident, .. })
+        | syn::Item::Static(syn::ItemStatic { ident, .. }) => format!("value {ident}"),
+        syn::Item::Mod(item) => format!("mod {}", item.ident),
+        syn::Item::Macro(syn::ItemMacro {
+            ident: Some(ident), ..
+        }) => format!("macro {ident}"),
+        syn::Item::Impl(item) => {
+            let trait_ = item
+                .trait_
+                .as_ref()
+                .map(|(_, path, _)| path.to_token_stream().to_string());
+            format!(
+                "impl {} for {}",
+                trait_.unwrap_or_default(),
+                item.self_ty.to_token_stream()
+            )
+        }
+        item => item.to_token_stream().to_string(),
+    }
+}
@@ -0,0 +1,63 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        config::{ConfigFile, SETTINGS},
+    },
+    anyhow::{Context, Result},
+    argh::FromArgs,
+    std::io::{self, BufRead, Write},
+};
+
+/// Interactively configure user-wide defaults.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "setup")]
+pub struct SetupSubCmd {}
+
+impl SubCmd for SetupSubCmd {
+    fn run(&self) -> Result<()> {
+        let mut config = ConfigFile::user()?;
+        println!(
+            "Configuring defaults in {} (press Enter to keep the current value, `-` to unset it).",
+            config.path().display()
+        );
+
+        let mut lines = io::stdin().lock().lines();
+        for setting in SETTINGS {
+            println!("\n{}: {}", setting.key, setting.help);
+            let current = config.get(setting.key).map(String::from);
+            loop {
+                print!(
+                    "{} [{}]: ",
+                    setting.key,
+                    current.as_deref().unwrap_or("unset")
+                );
+                io::stdout().flush()?;
+
+                // End of input keeps the remaining values as they are.
+                let Some(line) = lines.next() else {
+                    println!();
+                    break;
+                };
+                let value = line.context("failed to read input")?;
+                match value.trim() {
+                    "" => break,
+                    "-" => {
+                        config.set(setting.key, None);
+                        break;
+                    }
+                    value => match setting.validate(value) {
+                        Ok(()) => {
+                            config.set(setting.key, Some(value));
+                            break;
+                        }
+                        Err(e) => println!("{e}"),
+                    },
+                }
+            }
+        }
+
+        config.save()?;
+        println!("\nDefaults saved into {}", config.path().display());
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        problem::{BIN_DIR, resolve_id, solution_path, statement_path},
+        repro::{ReproCase, find_case, modified, rustc_version},
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        fs,
+        io::ErrorKind,
+        path::{Path, PathBuf},
+        process::Command,
+    },
+};
+
+/// Package a failing test case, along with the solution, into an archive to
+/// share with teammates.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "share-failure")]
+pub struct ShareFailureSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+
+    #[argh(positional)]
+    /// case to share: a failing one (saved by `test`), or one from
+    /// `tests/{id}/`
+    case: String,
+
+    #[argh(option)]
+    /// archive to create: `.zip`, `.tar.gz` or `.tgz` (defaults to
+    /// `share-{id}-{case}.zip`, or `.tar.gz` if `zip` is not installed)
+    out: Option<String>,
+}
+
+impl SubCmd for ShareFailureSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+        let case = find_case(&id, Some(&self.case))?;
+        let name = format!("share-{id}-{}", case.name);
+
+        // Files are gathered in a temporary directory, archived as a whole.
+        let staging = std::env::temp_dir().join(format!("algorist-{}", std::process::id()));
+        let dir = staging.join(&name);
+        fs::create_dir_all(&dir).context("failed to create staging directory")?;
+        let result = gather(&id, &case, &dir).and_then(|files| {
+            let archive = archive(&staging, &name, self.out.as_deref())?;
+            Ok((files, archive))
+        });
+        let _ = fs::remove_dir_all(&staging);
+        let (files, archive) = result?;
+
+        println!(
+            "Failure of problem {id:?} (case {:?}) packaged into {}:",
+            case.name,
+            archive.display()
+        );
+        for file in files {
+            println!("  {file}");
+        }
+        Ok(())
+    }
+}
+
+/// Copy the files of the failure into the directory, along with the summary.
+///
+/// Returns the names of the files.
+fn gather(id: &str, case: &ReproCase, dir: &Path) -> Result<Vec<&'static str>> {
+    let source = solution_path(Path::new("."), id);
+    let bundled = Path::new("bundled").join(BIN_DIR).join(format!("{id}.rs"));
+    let mut notes = Vec::new();
+    let bundled = if !bundled.exists() {
+        notes.push(format!(
+            "The solution is not bundled (run `algorist bundle {id}`)."
+        ));
+        None
+    } else {
+        if modified(&source)? > modified(&bundled)? {
+            notes.push("The bundled file is older than the solution.".to_string());
+        }
+        Some(bundled)
+    };
+    let statement = Some(statement_path(id)).filter(|path| path.exists());
+
+    let files = [
+        ("solution.rs", Some(source)),
+        ("bundled.rs", bundled),
+        ("statement.md", statement),
+        ("input.txt", Some(case.input.clone())),
+        ("expected.txt", case.expected.clone()),
+        ("actual.txt", case.actual.clone()),
+        ("stderr.txt", case.stderr.clone()),
+    ];
+    let mut included = Vec::new();
+    for (name, path) in files {
+        if let Some(path) = path {
+            fs::copy(&path, dir.join(name))
+                .context(format!("failed to copy {}", path.display()))?;
+            included.push(name);
+        }
+    }
+    fs::write(dir.join("SUMMARY.md"), summary(id, case, &included, &notes))
+        .context("failed to write summary")?;
+    included.insert(0, "SUMMARY.md");
+    Ok(included)
+}
+
+fn summary(id: &str, case: &ReproCase, files: &[&str], notes: &[String]) -> String {
+    let mut lines = vec![
+        format!("# Problem `{id}`, case `{}`", case.name),
+        String::new(),
+        format!(
+            "Verdict: {}",
+            case.verdict.as_deref().unwrap_or("not recorded")
+        ),
+    ];
+    let read =
+        |path: &Option<PathBuf>| path.as_ref().and_then(|path| fs::read_to_string(path).ok());
+    if let (Some(expected), Some(actual)) = (read(&case.expected), read(&case.actual)) {
+        lines.push(String::new());
+        lines.push(first_difference(&expected, &actual));
+    }
+    for note in notes {
+        lines.push(String::new());
+        lines.push(note.clone());
+    }
+    lines.extend([String::new(), "Files:".to_string(), String::new()]);
+    for file in files {
+        let description = match *file {
+            "solution.rs" => "solution, as written (depends on the library)",
+            "bundled.rs" => "self-contained solution, as submitted",
+            "statement.md" => "statement notes",
+            "input.txt" => "input",
+            "expected.txt" => "expected output",
+            "actual.txt" => "actual output of the solution",
+            "stderr.txt" => "standard error of the solution",
+            _ => continue,
+        };
+        lines.push(format!("- `{file}`: {description}"));
+    }
+    lines.extend([String::new(), "Environment:".to_string(), String::new()]);
+    if let Some(rustc) = rustc_version() {
+        lines.push(format!("- {rustc}"));
+    }
+    lines.push(format!(
+        "- {} {}",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION")
+    ));
+    lines.into_iter().map(|line| line + "\n").collect()
+}
+
+/// Describe the first line, the outputs differ at.
+fn first_difference(expected: &str, actual: &str) -> String {
+    let (mut expected_lines, mut actual_lines) = (expected.lines(), actual.lines());
+    for number in 1.. {
+        match (expected_lines.next(), actual_lines.next()) {
+            (None, None) => break,
+            (Some(expected), Some(actual)) if expected.trim_end() == actual.trim_end() => {}
+            (expected, actual) => {
+                let show = |line: Option<&str>| {
+                    line.map_or("end of output".to_string(), |line| format!("`{line}`"))
+                };
+                return format!(
+                    "First difference at line {number}: expected {}, got {}.",
+                    show(expected),
+                    show(actual)
+                );
+            }
+        }
+    }
+    "Outputs only differ in whitespace.".to_string()
+}
+
+/// Archive the directory `name` (within `staging`), with `zip` or `tar`.
+///
+/// Returns the path of the archive.
+fn archive(staging: &Path, name: &str, out: Option<&str>) -> Result<PathBuf> {
+    let cwd = std::env::current_dir().context("failed to get current directory")?;
+    let tar = |out: PathBuf| (out, "tar", vec!["-czf".to_string()]);
+    let zip = |out: PathBuf| (out, "zip", vec!["-qr".to_string()]);
+    let candidates = match out {
+        Some(out) if out.ends_with(".tar.gz") || out.ends_with(".tgz") => vec![tar(cwd.join(out))],
+        Some(out) if out.ends_with(".zip") => vec![zip(cwd.join(out))],
+        Some(out) => {
+            return Err(anyhow!(
+                "unsupported archive {out:?}, expected `.zip`, `.tar.gz` or `.tgz`"
+            ));
+        }
+        None => vec![
+            zip(cwd.join(format!("{name}.zip"))),
+            tar(cwd.join(format!("{name}.tar.gz"))),
+        ],
+    };
+    for (out, tool, mut args) in candidates {
+        if out.exists() {
+            return Err(anyhow!("{} already exists", out.display()));
+        }
+        args.push(out.display().to_string());
+        args.push(name.to_string());
+        let status = match Command::new(tool).args(&args).current_dir(staging).status() {
+            Ok(status) => status,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).context(format!("failed to run {tool}")),
+        };
+        if !status.success() {
+            return Err(anyhow!("{tool} exited with {status}"));
+        }
+        return Ok(out.strip_prefix(&cwd).map(Path::to_path_buf).unwrap_or(out));
+    }
+    Err(anyhow!("no archiver found (tried: zip, tar)"))
+}
@@ -0,0 +1,239 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        format_utc,
+        meta::{Event, MetaStore},
+        metrics::{Entry, metrics_path, read_entries},
+        now_secs,
+        status::format_duration,
+    },
+    anyhow::Result,
+    argh::FromArgs,
+    std::{collections::BTreeMap, path::Path},
+};
+
+/// Show statistics of solved problems, or, with `--tool`, of the tool usage.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "stats")]
+pub struct StatsSubCmd {
+    #[argh(switch)]
+    /// show where the time goes: durations and failures of the commands, as
+    /// recorded with the `telemetry` setting on
+    tool: bool,
+
+    #[argh(option)]
+    /// only count the commands, run within the given number of days
+    days: Option<u64>,
+
+    #[argh(switch)]
+    /// break the statistics of solved problems down by their tags
+    by_tag: bool,
+}
+
+impl SubCmd for StatsSubCmd {
+    fn run(&self) -> Result<()> {
+        if self.tool {
+            self.tool_stats()
+        } else {
+            problem_stats(self.by_tag)
+        }
+    }
+}
+
+impl StatsSubCmd {
+    fn tool_stats(&self) -> Result<()> {
+        let since = self
+            .days
+            .map_or(0, |days| now_secs().saturating_sub(days * 86400));
+        let entries = read_entries()?
+            .into_iter()
+            .filter(|entry| entry.at >= since)
+            .collect::<Vec<_>>();
+        let Some(first) = entries.iter().map(|entry| entry.at).min() else {
+            println!(
+                "No commands recorded yet, enable recording with `algorist config set telemetry \
+                 on`"
+            );
+            return Ok(());
+        };
+
+        // Commands, the most time consuming first.
+        let mut commands = BTreeMap::<&str, Vec<&Entry>>::new();
+        for entry in &entries {
+            commands.entry(&entry.command).or_default().push(entry);
+        }
+        let mut commands = commands.into_iter().collect::<Vec<_>>();
+        let total = |entries: &[&Entry]| entries.iter().map(|entry| entry.ms).sum::<u64>();
+        commands.sort_by_key(|(_, entries)| std::cmp::Reverse(total(entries)));
+
+        println!(
+            "{} command(s) recorded since {}:\n",
+            entries.len(),
+            format_utc(first)
+        );
+        println!(
+            "{:<16}{:>6}{:>8}{:>12}{:>10}",
+            "command", "runs", "failed", "total", "average"
+        );
+        for (command, entries) in &commands {
+            let failed = entries
+                .iter()
+                .filter(|entry| entry.failure.is_some())
+                .count();
+            let total = total(entries);
+            println!(
+                "{command:<16}{:>6}{failed:>8}{:>12}{:>10}",
+                entries.len(),
+                format_ms(total),
+                format_ms(total / entries.len() as u64)
+            );
+        }
+
+        let all = entries.iter().map(|entry| entry.ms).sum::<u64>().max(1);
+        let mut phases = BTreeMap::<&str, u64>::new();
+        let mut failures = BTreeMap::<&str, usize>::new();
+        for entry in &entries {
+            for (phase, ms) in &entry.phases {
+                *phases.entry(phase).or_default() += ms;
+            }
+            if let Some(failure) = &entry.failure {
+                *failures.entry(failure).or_default() += 1;
+            }
+        }
+        if !phases.is_empty() {
+            println!("\nTime spent:");
+            for (phase, ms) in phases {
+                println!(
+                    "  {}: {} ({}% of the total)",
+                    phase_name(phase),
+                    format_ms(ms),
+                    ms * 100 / all
+                );
+            }
+        }
+        if !failures.is_empty() {
+            println!("\nFailures:");
+            for (failure, count) in failures {
+                println!("  {failure}: {count}");
+            }
+        }
+        if let Some(path) = metrics_path() {
+            println!("\nMetrics are recorded in {}", path.display());
+        }
+        Ok(())
+    }
+}
+
+/// Summary of the problems of the project: how many are solved, and how long
+/// solving took.
+fn problem_stats(by_tag: bool) -> Result<()> {
+    let store = MetaStore::open(Path::new("."))?;
+    let problems = store.problems();
+    if problems.is_empty() {
+        println!("No problems tracked yet.");
+        return Ok(());
+    }
+    let mut solve_times = problems
+        .iter()
+        .filter_map(|id| {
+            let created = store.get(id, Event::Created)?;
+            let passed = store.get(id, Event::FirstPass)?;
+            Some((passed.saturating_sub(created), id))
+        })
+        .collect::<Vec<_>>();
+    solve_times.sort();
+    let solved = problems
+        .iter()
+        .filter(|id| store.get(id, Event::FirstPass).is_some())
+        .count();
+    // Hints, looked up after solving (e.g. to compare approaches), don't count.
+    let hinted = problems
+        .iter()
+        .filter(|id| {
+            let passed = store.get(id, Event::FirstPass);
+            store
+                .get(id, Event::Hinted)
+                .is_some_and(|hinted| passed.is_some_and(|passed| hinted <= passed))
+        })
+        .count();
+    match hinted {
+        0 => println!("{solved}/{} problem(s) solved", problems.len()),
+        _ => println!(
+            "{solved}/{} problem(s) solved, {hinted} of them with a hint",
+            problems.len()
+        ),
+    }
+    if let (Some((fastest, fastest_id)), Some((slowest, slowest_id))) =
+        (solve_times.first(), solve_times.last())
+    {
+        println!(
+            "Solve time: median {}, fastest {} ({fastest_id}), slowest {} ({slowest_id})",
+            format_duration(solve_times[solve_times.len() / 2].0),
+            format_duration(*fastest),
+            format_duration(*slowest)
+        );
+    }
+    if by_tag {
+        tag_stats(&store, &problems);
+    }
+    Ok(())
+}
+
+/// Solved problems and the median solve time per tag, the most solved first.
+fn tag_stats(store: &MetaStore, problems: &[String]) {
+    let mut tags = BTreeMap::<String, Vec<&String>>::new();
+    for id in problems {
+        let problem_tags = store.tags(id);
+        if problem_tags.is_empty() {
+            tags.entry("(untagged)".to_string()).or_default().push(id);
+        }
+        for tag in problem_tags {
+            tags.entry(tag).or_default().push(id);
+        }
+    }
+    let mut tags = tags.into_iter().collect::<Vec<_>>();
+    let solved = |ids: &[&String]| {
+        ids.iter()
+            .filter(|id| store.get(id, Event::FirstPass).is_some())
+            .count()
+    };
+    tags.sort_by_key(|(_, ids)| std::cmp::Reverse(solved(ids)));
+
+    println!("\nBy tag:");
+    for (tag, ids) in &tags {
+        let mut solve_times = ids
+            .iter()
+            .filter_map(|id| {
+                let created = store.get(id, Event::Created)?;
+                let passed = store.get(id, Event::FirstPass)?;
+                Some(passed.saturating_sub(created))
+            })
+            .collect::<Vec<_>>();
+        solve_times.sort();
+        let median = solve_times
+            .get(solve_times.len() / 2)
+            .map(|&median| format!(", median solve time {}", format_duration(median)))
+            .unwrap_or_default();
+        println!("  {tag}: {}/{} solved{median}", solved(ids), ids.len());
+    }
+}
+
+/// Description of the phase, recorded by the commands.
+fn phase_name(phase: &str) -> &str {
+    match phase {
+        "build" => "compiling solutions",
+        "check" => "verifying bundled files",
+        "bundle" => "bundling",
+        "run" => "running test cases",
+        phase => phase,
+    }
+}
+
+/// Format duration in milliseconds as e.g. `850ms`, `12.3s` or `1m 02s`.
+pub fn format_ms(ms: u64) -> String {
+    match ms {
+        0..1000 => format!("{ms}ms"),
+        1000..60_000 => format!("{:.1}s", ms as f64 / 1000.0),
+        _ => format_duration(ms / 1000),
+    }
+}
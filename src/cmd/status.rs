@@ -0,0 +1,94 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        format_utc,
+        meta::{Event, Filter, MetaStore},
+    },
+    anyhow::Result,
+    argh::FromArgs,
+    std::path::Path,
+};
+
+/// Show per-problem progress: when problems were started and solved.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "status")]
+pub struct StatusSubCmd {
+    #[argh(option)]
+    /// only show problems with the given label: `tag=<tag>`, or
+    /// `difficulty=<min>..<max>` (can be repeated, all must match)
+    filter: Vec<String>,
+}
+
+impl SubCmd for StatusSubCmd {
+    fn run(&self) -> Result<()> {
+        let store = MetaStore::open(Path::new("."))?;
+        let problems = store.problems();
+        if problems.is_empty() {
+            println!("No problems tracked yet.");
+            return Ok(());
+        }
+        let filters = self
+            .filter
+            .iter()
+            .map(|spec| Filter::parse(spec))
+            .collect::<Result<Vec<_>>>()?;
+        let problems = problems
+            .into_iter()
+            .filter(|id| filters.iter().all(|filter| filter.matches(&store, id)))
+            .collect::<Vec<_>>();
+        if problems.is_empty() {
+            println!("No problems match the filter.");
+            return Ok(());
+        }
+
+        for id in problems {
+            let created = store.get(&id, Event::Created);
+            let first_pass = store.get(&id, Event::FirstPass);
+            let progress = match (created, first_pass) {
+                (Some(created), Some(passed)) => {
+                    format!(
+                        "solved in {}",
+                        format_duration(passed.saturating_sub(created))
+                    )
+                }
+                (_, Some(_)) => "solved".to_string(),
+                (_, None) if store.get(&id, Event::FirstRun).is_some() => "in progress".to_string(),
+                _ => "not started".to_string(),
+            };
+            let hinted = match store.get(&id, Event::Hinted) {
+                Some(hinted) if first_pass.is_none_or(|passed| hinted <= passed) => ", hint used",
+                _ => "",
+            };
+            let created = created
+                .map(|ts| format!(" (created {})", format_utc(ts)))
+                .unwrap_or_default();
+            println!("{id}: {progress}{hinted}{created}{}", labels(&store, &id));
+        }
+        Ok(())
+    }
+}
+
+/// Tags and difficulty of the problem, if known, e.g. ` [dp, greedy] 1800`.
+fn labels(store: &MetaStore, id: &str) -> String {
+    let mut labels = String::new();
+    let tags = store.tags(id);
+    if !tags.is_empty() {
+        labels.push_str(&format!(" [{}]", tags.join(", ")));
+    }
+    if let Some(difficulty) = store.difficulty(id) {
+        labels.push_str(&format!(" {difficulty}"));
+    }
+    labels
+}
+
+/// Format duration in seconds as e.g. `1h 02m 03s`.
+pub fn format_duration(secs: u64) -> String {
+    let (h, m, s) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if h > 0 {
+        format!("{h}h {m:02}m {s:02}s")
+    } else if m > 0 {
+        format!("{m}m {s:02}s")
+    } else {
+        format!("{s}s")
+    }
+}
@@ -0,0 +1,89 @@
+use {
+    crate::cmd::{config::setting, jobs::Jobs},
+    anyhow::Result,
+    std::{env, path::Path, process::Command, sync::OnceLock},
+};
+
+/// Compilation target, solutions are built for (and run on), e.g.
+/// `x86_64-unknown-linux-musl` (as in a container), or `i686-unknown-linux-gnu`
+/// (to test 32-bit overflow behavior).
+///
+/// Binaries of non-native targets are run with the configured runner (e.g.
+/// `qemu-i386`), if any: the one of the `target_runner` setting, or cargo's own
+/// (`CARGO_TARGET_<TRIPLE>_RUNNER`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Target {
+    pub triple: String,
+
+    /// Runner command, including its arguments.
+    runner: Option<String>,
+}
+
+impl Target {
+    /// Target, given on the command line, or configured (`target` setting), if
+    /// any.
+    pub fn resolve(target: Option<&str>) -> Result<Option<Self>> {
+        let triple = match target {
+            Some(triple) => triple.to_string(),
+            None => match setting("target")? {
+                Some(triple) => triple,
+                None => return Ok(None),
+            },
+        };
+        let runner = if host().is_some_and(|host| host == triple) {
+            None
+        } else {
+            setting("target_runner")?.or_else(|| env::var(runner_var(&triple)).ok())
+        }
+        .filter(|runner| !runner.trim().is_empty());
+        Ok(Some(Self { triple, runner }))
+    }
+
+    pub fn cargo_args(&self) -> Vec<String> {
+        vec!["--target".to_string(), self.triple.clone()]
+    }
+
+    /// Command to run the binary, built for the target, with the limits
+    /// applied.
+    pub fn command(target: Option<&Self>, jobs: Jobs, exe: &Path) -> Command {
+        let mut runner = target
+            .and_then(|target| target.runner.as_deref())
+            .map(str::split_whitespace)
+            .into_iter()
+            .flatten();
+        let Some(program) = runner.next() else {
+            return jobs.command(exe);
+        };
+        let mut cmd = jobs.command(program);
+        cmd.args(runner).arg(exe);
+        cmd
+    }
+
+    /// Pass the runner to cargo (e.g. for `cargo run`), if any.
+    pub fn set_runner_env(&self, cmd: &mut Command) {
+        if let Some(runner) = &self.runner {
+            cmd.env(runner_var(&self.triple), runner);
+        }
+    }
+}
+
+/// Cargo's environment variable, configuring the runner of the target.
+fn runner_var(triple: &str) -> String {
+    format!(
+        "CARGO_TARGET_{}_RUNNER",
+        triple.to_uppercase().replace(['-', '.'], "_")
+    )
+}
+
+/// Triple of the host, as reported by `rustc -vV` (checked once per run).
+pub fn host() -> Option<&'static str> {
+    static HOST: OnceLock<Option<String>> = OnceLock::new();
+    HOST.get_or_init(|| {
+        let output = Command::new("rustc").arg("-vV").output().ok()?;
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .find_map(|line| line.strip_prefix("host: "))
+            .map(String::from)
+    })
+    .as_deref()
+}
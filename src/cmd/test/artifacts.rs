@@ -0,0 +1,119 @@
+use {
+    crate::cmd::test::runner::CaseResult,
+    anyhow::{Context, Result},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Root directory for the failure artifacts.
+const FAILURES_DIR: &str = ".algorist/failures";
+
+/// Full artifacts of failing test cases, stored in
+/// `.algorist/failures/<id>/<case>/`.
+///
+/// Inputs and outputs can be large, so instead of dumping them into the
+/// terminal, they are written to disk, and only previews are shown.
+#[derive(Debug, Clone)]
+pub struct Artifacts {
+    dir: PathBuf,
+}
+
+impl Artifacts {
+    /// Artifacts of a given problem.
+    pub fn new(problem_id: &str) -> Self {
+        Self {
+            dir: Path::new(FAILURES_DIR).join(problem_id),
+        }
+    }
+
+    /// Remove artifacts of the previous runs.
+    pub fn clear(&self) -> Result<()> {
+        if self.dir.exists() {
+            fs::remove_dir_all(&self.dir).context(format!(
+                "failed to remove failure artifacts at {}",
+                self.dir.display()
+            ))?;
+        }
+        Ok(())
+    }
+
+    /// Directories of the saved cases, by case name, sorted.
+    pub fn cases(&self) -> Vec<(String, PathBuf)> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut cases = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.is_dir())
+            .filter_map(|path| {
+                let name = path.file_name()?.to_string_lossy().into_owned();
+                Some((name, path))
+            })
+            .collect::<Vec<_>>();
+        cases.sort();
+        cases
+    }
+
+    /// Save input, expected and actual outputs, standard error and verdict of
+    /// a case.
+    ///
+    /// Returns the directory, artifacts are saved into.
+    pub fn save(&self, result: &CaseResult) -> Result<PathBuf> {
+        let dir = self.dir.join(&result.case.name);
+        fs::create_dir_all(&dir).context(format!(
+            "failed to create artifacts directory {}",
+            dir.display()
+        ))?;
+
+        fs::copy(&result.case.input, dir.join("input.txt"))?;
+        if let Some(expected) = &result.case.expected {
+            fs::copy(expected, dir.join("expected.txt"))?;
+        }
+        fs::write(dir.join("actual.txt"), &result.output)?;
+        fs::write(dir.join("stderr.txt"), &result.stderr)?;
+        fs::write(dir.join("verdict.txt"), result.verdict.to_string())?;
+        Ok(dir)
+    }
+}
+
+/// Render preview of a (possibly large) content: a header with its size,
+/// followed by at most `max_lines` lines.
+pub fn preview(label: &str, content: &str, max_lines: usize) -> String {
+    let total = content.lines().count();
+    let header = if total > max_lines {
+        format!(
+            "--- {label} ({}, showing first {max_lines} of {total} lines) ---",
+            human_size(content.len())
+        )
+    } else {
+        format!("--- {label} ({}) ---", human_size(content.len()))
+    };
+
+    let mut lines = vec![header];
+    lines.extend(content.lines().take(max_lines).map(|line| {
+        // Very long lines are truncated as well.
+        match line.char_indices().nth(200) {
+            Some((idx, _)) => format!("{}...", &line[..idx]),
+            None => line.to_string(),
+        }
+    }));
+    lines.join("\n")
+}
+
+/// Format byte count as a human-readable size, e.g. `4.2 MB`.
+fn human_size(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
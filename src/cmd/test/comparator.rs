@@ -0,0 +1,245 @@
+use {
+    crate::cmd::test::runner::TestCase,
+    anyhow::{Context, Result, anyhow},
+    std::{
+        fmt::Debug,
+        fs,
+        path::PathBuf,
+        process::{Command, Stdio},
+        sync::{
+            Arc,
+            atomic::{AtomicUsize, Ordering},
+        },
+    },
+};
+
+/// Decides whether the actual output of a solution is accepted.
+///
+/// This is an extension point: any type implementing it can be plugged into
+/// [`TestRunner`](crate::cmd::test::runner::TestRunner).
+pub trait Comparator: Debug + Send + Sync {
+    /// Whether the actual output is accepted, given the expected one.
+    fn compare(&self, case: &TestCase, expected: &str, actual: &str) -> Result<bool>;
+}
+
+/// Parse comparator specification, as given on the command line.
+///
+/// Supported values:
+/// - `exact`: byte-to-byte comparison.
+/// - `lines`: line-by-line, ignoring trailing whitespace and empty lines
+///   (default).
+/// - `tokens`: whitespace-separated tokens.
+/// - `case-insensitive`: tokens, ignoring ASCII case.
+/// - `float[:eps]`: tokens, numbers are compared with absolute or relative
+///   tolerance (default `1e-6`).
+/// - `unordered`: lines, in any order.
+/// - `checker:<path>`: external checker, invoked as `<path> <input> <output>
+///   <answer>` (testlib order), exit code 0 means accepted.
+pub fn parse_comparator(spec: &str) -> Result<Arc<dyn Comparator>> {
+    let spec = spec.trim();
+    let (name, arg) = spec.split_once(':').unwrap_or((spec, ""));
+    Ok(match (name, arg) {
+        ("exact", "") => Arc::new(Exact),
+        ("lines", "") => Arc::new(Lines),
+        ("tokens", "") => Arc::new(Tokens),
+        ("case-insensitive", "") => Arc::new(CaseInsensitive),
+        ("float", "") => Arc::new(FloatEpsilon(1e-6)),
+        ("float", eps) => Arc::new(FloatEpsilon(
+            eps.parse().context(format!("invalid epsilon: {eps:?}"))?,
+        )),
+        ("unordered", "") => Arc::new(UnorderedLines),
+        ("checker", path) if !path.is_empty() => Arc::new(Checker(PathBuf::from(path))),
+        _ => return Err(anyhow!("unknown comparator: {spec:?}")),
+    })
+}
+
+/// Byte-to-byte comparison.
+#[derive(Debug)]
+pub struct Exact;
+
+impl Comparator for Exact {
+    fn compare(&self, _case: &TestCase, expected: &str, actual: &str) -> Result<bool> {
+        Ok(expected == actual)
+    }
+}
+
+/// Line-by-line comparison, ignoring trailing whitespace and trailing empty
+/// lines.
+#[derive(Debug)]
+pub struct Lines;
+
+impl Comparator for Lines {
+    fn compare(&self, _case: &TestCase, expected: &str, actual: &str) -> Result<bool> {
+        Ok(significant_lines(expected) == significant_lines(actual))
+    }
+}
+
+/// Comparison of whitespace-separated tokens.
+#[derive(Debug)]
+pub struct Tokens;
+
+impl Comparator for Tokens {
+    fn compare(&self, _case: &TestCase, expected: &str, actual: &str) -> Result<bool> {
+        Ok(expected.split_whitespace().eq(actual.split_whitespace()))
+    }
+}
+
+/// Comparison of whitespace-separated tokens, ignoring ASCII case.
+#[derive(Debug)]
+pub struct CaseInsensitive;
+
+impl Comparator for CaseInsensitive {
+    fn compare(&self, _case: &TestCase, expected: &str, actual: &str) -> Result<bool> {
+        let (mut expected, mut actual) = (expected.split_whitespace(), actual.split_whitespace());
+        loop {
+            match (expected.next(), actual.next()) {
+                (None, None) => return Ok(true),
+                (Some(a), Some(b)) if a.eq_ignore_ascii_case(b) => {}
+                _ => return Ok(false),
+            }
+        }
+    }
+}
+
+/// Comparison of whitespace-separated tokens, where numeric tokens may differ
+/// within absolute or relative tolerance. Non-finite values (`nan`, `inf`) are
+/// compared as tokens.
+#[derive(Debug)]
+pub struct FloatEpsilon(pub f64);
+
+impl Comparator for FloatEpsilon {
+    fn compare(&self, _case: &TestCase, expected: &str, actual: &str) -> Result<bool> {
+        let (mut expected, mut actual) = (expected.split_whitespace(), actual.split_whitespace());
+        loop {
+            match (expected.next(), actual.next()) {
+                (None, None) => return Ok(true),
+                (Some(a), Some(b)) => match (a.parse::<f64>(), b.parse::<f64>()) {
+                    (Ok(x), Ok(y)) if x.is_finite() && y.is_finite() => {
+                        let diff = (x - y).abs();
+                        if !(diff <= self.0 || diff <= self.0 * x.abs()) {
+                            return Ok(false);
+                        }
+                    }
+                    _ if a == b => {}
+                    _ => return Ok(false),
+                },
+                _ => return Ok(false),
+            }
+        }
+    }
+}
+
+/// Line-by-line comparison, where lines may come in any order.
+#[derive(Debug)]
+pub struct UnorderedLines;
+
+impl Comparator for UnorderedLines {
+    fn compare(&self, _case: &TestCase, expected: &str, actual: &str) -> Result<bool> {
+        let (mut expected, mut actual) = (significant_lines(expected), significant_lines(actual));
+        expected.sort_unstable();
+        actual.sort_unstable();
+        Ok(expected == actual)
+    }
+}
+
+/// External checker program.
+#[derive(Debug)]
+pub struct Checker(pub PathBuf);
+
+impl Comparator for Checker {
+    fn compare(&self, case: &TestCase, expected: &str, actual: &str) -> Result<bool> {
+        // Cases are checked concurrently, each one gets a directory of its own.
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let dir = std::env::temp_dir().join(format!(
+            "algorist-checker-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)?;
+        let (output, answer) = (dir.join("output.txt"), dir.join("answer.txt"));
+        fs::write(&output, actual)?;
+        fs::write(&answer, expected)?;
+
+        let status = Command::new(&self.0)
+            .arg(&case.input)
+            .arg(&output)
+            .arg(&answer)
+            .stdout(Stdio::null())
+            .status()
+            .context(format!("failed to run checker {}", self.0.display()));
+        let _ = fs::remove_dir_all(&dir);
+        Ok(status?.success())
+    }
+}
+
+/// Lines with trailing whitespace trimmed, and trailing empty lines removed.
+fn significant_lines(s: &str) -> Vec<&str> {
+    let mut lines = s.lines().map(str::trim_end).collect::<Vec<_>>();
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn accepts(comparator: &str, expected: &str, actual: &str) -> bool {
+        let case = TestCase {
+            name: "1".into(),
+            input: PathBuf::from("1.in"),
+            expected: Some(PathBuf::from("1.out")),
+        };
+        parse_comparator(comparator)
+            .unwrap()
+            .compare(&case, expected, actual)
+            .unwrap()
+    }
+
+    #[test]
+    fn specs() {
+        assert!(parse_comparator("float:1e-3").is_ok());
+        assert!(parse_comparator("float:eps").is_err());
+        assert!(parse_comparator("checker").is_err());
+        assert!(parse_comparator("exact:1").is_err());
+        assert!(parse_comparator("fuzzy").is_err());
+    }
+
+    #[test]
+    fn lines_and_tokens() {
+        assert!(accepts("exact", "1 2\n", "1 2\n"));
+        assert!(!accepts("exact", "1 2\n", "1 2"));
+        assert!(accepts("lines", "1 2\n\n", "1 2  \n"));
+        assert!(!accepts("lines", "1 2\n", "1  2\n"));
+        assert!(accepts("tokens", "1 2\n", "1\n2"));
+        assert!(accepts("case-insensitive", "YES\n", "yes\n"));
+        assert!(!accepts("case-insensitive", "YES\n", "yes no\n"));
+        assert!(accepts("unordered", "1\n2\n", "2\n1\n\n"));
+        assert!(!accepts("unordered", "1\n1\n", "1\n"));
+    }
+
+    #[test]
+    fn float_tolerance() {
+        assert!(accepts("float", "0.5 x", "0.5000001 x"));
+        assert!(!accepts("float", "0.5", "0.50001"));
+        assert!(!accepts("float", "0.5 x", "0.5 y"));
+        assert!(!accepts("float", "0.5", "0.5 0.5"));
+        assert!(accepts("float:0.1", "1", "1.05"));
+        assert!(!accepts("float:0.1", "1", "1.2"));
+        // Relative tolerance, for large values.
+        assert!(accepts("float", "1000000000", "1000000100"));
+        assert!(!accepts("float", "1000000000", "1000010000"));
+    }
+
+    #[test]
+    fn float_non_finite() {
+        assert!(accepts("float", "nan", "nan"));
+        assert!(accepts("float", "inf -inf", "inf -inf"));
+        assert!(!accepts("float", "nan", "0"));
+        assert!(!accepts("float", "0", "nan"));
+        assert!(!accepts("float", "inf", "-inf"));
+        assert!(!accepts("float", "inf", "1e308"));
+        assert!(!accepts("float", "1", "inf"));
+    }
+}
@@ -0,0 +1,301 @@
+pub mod artifacts;
+pub mod comparator;
+pub mod runner;
+
+use {
+    crate::cmd::{
+        SubCmd,
+        jobs::Jobs,
+        meta::{Event, record_events, update_events},
+        metrics::{Failure, record_failure},
+        problem::{for_each_id, select_ids},
+        target::Target,
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    artifacts::{Artifacts, preview},
+    comparator::parse_comparator,
+    runner::{CaseResult, Profile, TestCase, TestRunner, Verdict},
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        sync::Arc,
+        time::Duration,
+    },
+};
+
+/// Largest input of a case, run under Miri, unless the case is selected
+/// explicitly (larger ones would take too long to interpret).
+const MIRI_MAX_INPUT: u64 = 4096;
+
+/// Run a given problem against test cases in `tests/{id}/`.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "test")]
+pub struct TestProblemSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: Option<String>,
+
+    #[argh(switch)]
+    /// test all problems (those without test cases are skipped)
+    all: bool,
+
+    #[argh(switch)]
+    /// test only problems, modified since they last passed all test cases
+    changed: bool,
+
+    #[argh(option)]
+    /// only run the given case (can be repeated)
+    case: Vec<String>,
+
+    #[argh(option, default = "2000")]
+    /// time limit per case, in milliseconds (default: 2000)
+    time_limit: u64,
+
+    #[argh(switch)]
+    /// build and run the solution in release mode
+    release: bool,
+
+    #[argh(option)]
+    /// run the cases under each of the comma-separated build profiles:
+    /// `debug`, `release` and `checked` (release with overflow checks and
+    /// debug assertions), and report a grid of verdicts
+    matrix: Option<String>,
+
+    #[argh(switch)]
+    /// show all compiler diagnostics, if the solution fails to compile (only
+    /// errors are shown by default)
+    full: bool,
+
+    #[argh(switch)]
+    /// report where the build time is spent, see `cargo build --timings`
+    timings: bool,
+
+    #[argh(option)]
+    /// output comparator: `exact`, `lines` (default), `tokens`,
+    /// `case-insensitive`, `float[:eps]`, `unordered` or `checker:<path>`;
+    /// overrides the one in `tests/{id}/comparator` file
+    cmp: Option<String>,
+
+    #[argh(option, default = "40")]
+    /// maximum number of lines shown for inputs and outputs of failing cases
+    /// (default: 40), full artifacts are saved into
+    /// `.algorist/failures/{id}/{case}/`
+    preview_lines: usize,
+
+    #[argh(option)]
+    /// maximum number of parallel jobs: compiler processes, and test cases run
+    /// at once (overrides the configured `jobs`; by default, the build uses all
+    /// CPUs, and cases run one at a time)
+    jobs: Option<usize>,
+
+    #[argh(option)]
+    /// niceness of the build and of the solution runs, from -20 to 19
+    /// (overrides the configured `nice`)
+    nice: Option<i32>,
+
+    #[argh(option)]
+    /// target to build the solution for, e.g. `i686-unknown-linux-gnu` to
+    /// test 32-bit overflow behavior (overrides the configured `target`)
+    target: Option<String>,
+
+    #[argh(switch)]
+    /// run the solution under Miri (requires the nightly toolchain), reporting
+    /// undefined behavior, e.g. in unsafe library code; only cases with small
+    /// inputs (up to 4 KiB) are run, unless selected with `--case`
+    miri: bool,
+}
+
+impl SubCmd for TestProblemSubCmd {
+    fn run(&self) -> Result<()> {
+        if self.release && self.matrix.is_some() {
+            return Err(anyhow!("`--release` and `--matrix` can't be used together"));
+        }
+        let ids = select_ids(
+            Path::new("."),
+            self.id.as_deref(),
+            self.all,
+            self.changed,
+            Event::Tested,
+        )?;
+        if ids.is_empty() {
+            println!("No problems to test.");
+            return Ok(());
+        }
+        let jobs = Jobs::resolve(self.jobs, self.nice)?;
+        let target = Target::resolve(self.target.as_deref())?;
+        for_each_id(&ids, |id| self.test_problem(id, jobs, target.as_ref()))
+    }
+}
+
+impl TestProblemSubCmd {
+    fn test_problem(&self, id: &str, jobs: Jobs, target: Option<&Target>) -> Result<()> {
+        // In batch mode, problems without test cases are not an error.
+        let tests_dir = PathBuf::from("tests").join(id);
+        let batch = self.all || self.changed;
+        let mut cases = if tests_dir.exists() || !batch {
+            TestCase::discover(&tests_dir)?
+        } else {
+            Vec::new()
+        };
+        cases.retain(|case| self.case.is_empty() || self.case.contains(&case.name));
+        if self.miri && self.case.is_empty() {
+            let total = cases.len();
+            cases.retain(|case| {
+                fs::metadata(&case.input).is_ok_and(|meta| meta.len() <= MIRI_MAX_INPUT)
+            });
+            if cases.len() < total {
+                println!(
+                    "Skipping {} case(s) with inputs over {MIRI_MAX_INPUT} bytes under Miri",
+                    total - cases.len()
+                );
+            }
+        }
+        if cases.is_empty() {
+            if batch {
+                println!("No test cases found for problem {id:?}, skipping");
+                return Ok(());
+            }
+            return Err(anyhow!(
+                "no test cases found, add `<case>.in` and `<case>.out` files to {tests_dir:?}"
+            ));
+        }
+
+        // Comparator is either given explicitly, or configured per problem.
+        let cmp_file = tests_dir.join("comparator");
+        let spec = match &self.cmp {
+            Some(spec) => spec.clone(),
+            None if cmp_file.exists() => {
+                fs::read_to_string(&cmp_file).context("failed to read comparator file")?
+            }
+            None => "lines".to_string(),
+        };
+        let comparator = parse_comparator(&spec)?;
+
+        let profiles = match &self.matrix {
+            Some(spec) => Profile::parse_list(spec)?,
+            None if self.release => vec![Profile::Release],
+            None => vec![Profile::Debug],
+        };
+
+        let artifacts = Artifacts::new(id);
+        artifacts.clear()?;
+
+        println!("Running {} test case(s) for problem {id:?}", cases.len());
+        record_events(Path::new("."), &[id], Event::FirstRun);
+        let mut grid = Vec::new();
+        for &profile in &profiles {
+            if self.matrix.is_some() {
+                println!("\n==> Profile: {profile}");
+            }
+            let mut save_errors = Vec::new();
+            let results = TestRunner::new(id)
+                .cases(cases.clone())
+                .time_limit(Duration::from_millis(self.time_limit))
+                .profile(profile)
+                .full_diagnostics(self.full)
+                .timings(self.timings)
+                .jobs(jobs)
+                .target(target.cloned())
+                .miri(self.miri)
+                .comparator(Arc::clone(&comparator))
+                .run(|result| {
+                    if let Err(e) = self.report(&artifacts, result) {
+                        save_errors.push(e);
+                    }
+                })?;
+            if let Some(e) = save_errors.into_iter().next() {
+                return Err(e.context("failed to save failure artifacts"));
+            }
+            grid.push(results);
+        }
+        if self.matrix.is_some() {
+            print_grid(&cases, &profiles, &grid);
+        }
+        let results = grid.into_iter().flatten().collect::<Vec<_>>();
+
+        let passed = results
+            .iter()
+            .filter(|r| r.verdict == Verdict::Passed)
+            .count();
+        let failed = results.iter().filter(|r| r.verdict.is_failure()).count();
+        println!("\n{passed}/{} passed", results.len());
+        if results
+            .iter()
+            .any(|r| matches!(r.verdict, Verdict::WrongAnswer | Verdict::NoExpected))
+        {
+            println!(
+                "Once reviewed, accept actual outputs as the expected ones with `algorist promote \
+                 {id}`"
+            );
+        }
+        if failed > 0 {
+            record_failure(Failure::Tests);
+            return Err(anyhow!("{failed} test case(s) failed"));
+        }
+        if passed > 0 {
+            record_events(Path::new("."), &[id], Event::FirstPass);
+            update_events(Path::new("."), &[id], Event::Tested);
+        }
+        Ok(())
+    }
+
+    /// Print result of a single case, saving artifacts of the failing ones.
+    fn report(&self, artifacts: &Artifacts, result: &CaseResult) -> Result<()> {
+        println!(
+            "- case {}: {} ({} ms)",
+            result.case.name,
+            result.verdict,
+            result.elapsed.as_millis()
+        );
+        if result.verdict == Verdict::Passed {
+            return Ok(());
+        }
+
+        let max_lines = self.preview_lines;
+        let input = fs::read_to_string(&result.case.input).unwrap_or_default();
+        println!("{}", preview("input", &input, max_lines));
+        match result.verdict {
+            Verdict::WrongAnswer | Verdict::NoExpected => {
+                if let Some(expected) = &result.case.expected {
+                    let expected = fs::read_to_string(expected).unwrap_or_default();
+                    println!("{}", preview("expected", &expected, max_lines));
+                }
+                println!("{}", preview("actual", &result.output, max_lines));
+            }
+            Verdict::RuntimeError(_) | Verdict::UndefinedBehavior => {
+                println!("{}", preview("stderr", &result.stderr, max_lines));
+            }
+            _ => {}
+        }
+
+        // Outputs of new cases are saved as well, so that they can be promoted.
+        if result.verdict.is_failure() || result.verdict == Verdict::NoExpected {
+            let dir = artifacts.save(result)?;
+            println!("--- full artifacts saved to {dir:?} ---");
+        }
+        Ok(())
+    }
+}
+
+/// Print verdicts of the cases (rows) under each of the profiles (columns).
+fn print_grid(cases: &[TestCase], profiles: &[Profile], grid: &[Vec<CaseResult>]) {
+    let width = cases
+        .iter()
+        .map(|case| case.name.len())
+        .chain(std::iter::once("case".len()))
+        .max()
+        .unwrap_or_default();
+    let mut header = format!("\n{:width$}", "case");
+    for profile in profiles {
+        header.push_str(&format!("  {:<8}", profile.to_string()));
+    }
+    println!("{}", header.trim_end());
+    for (i, case) in cases.iter().enumerate() {
+        let mut row = format!("{:width$}", case.name);
+        for results in grid {
+            row.push_str(&format!("  {:<8}", results[i].verdict.short()));
+        }
+        println!("{}", row.trim_end());
+    }
+}
@@ -0,0 +1,545 @@
+use {
+    crate::cmd::{
+        cargo,
+        jobs::Jobs,
+        metrics::{Failure, record_failure, record_phase},
+        target::Target,
+        test::comparator::{Comparator, Lines},
+    },
+    anyhow::{Context, Result, anyhow},
+    std::{
+        env,
+        fs::{self, File},
+        io::{self, BufRead, BufReader, IsTerminal, Read},
+        path::{Path, PathBuf},
+        process::{Command, Stdio},
+        sync::Arc,
+        thread,
+        time::{Duration, Instant},
+    },
+};
+
+/// Default time limit per test case.
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_secs(2);
+
+/// How many times the time limit is extended under Miri, which interprets the
+/// solution (orders of magnitude slower than running it natively).
+const MIRI_SLOWDOWN: u32 = 100;
+
+/// Single test case: input file and (optionally) the expected output.
+#[derive(Debug, Clone)]
+pub struct TestCase {
+    /// Case name, i.e. file stem of the input file.
+    pub name: String,
+
+    /// Path to the input file.
+    pub input: PathBuf,
+
+    /// Path to the expected output file, if any.
+    pub expected: Option<PathBuf>,
+}
+
+impl TestCase {
+    /// Discover test cases in a given directory.
+    ///
+    /// Each `<case>.in` file is an input, and `<case>.out` (if exists) is the
+    /// expected output. Cases are sorted by name, with numeric names in
+    /// numeric order.
+    pub fn discover(dir: &Path) -> Result<Vec<TestCase>> {
+        let mut cases = fs::read_dir(dir)
+            .context(format!("failed to read test directory {}", dir.display()))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "in"))
+            .filter_map(|input| {
+                let name = input.file_stem()?.to_string_lossy().into_owned();
+                let expected = Some(input.with_extension("out")).filter(|p| p.exists());
+                Some(TestCase {
+                    name,
+                    input,
+                    expected,
+                })
+            })
+            .collect::<Vec<_>>();
+        cases.sort_by(
+            |a, b| match (a.name.parse::<u64>(), b.name.parse::<u64>()) {
+                (Ok(x), Ok(y)) => x.cmp(&y),
+                _ => a.name.cmp(&b.name),
+            },
+        );
+        Ok(cases)
+    }
+}
+
+/// Outcome of a single test case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    /// Output matches the expected one.
+    Passed,
+
+    /// Output differs from the expected one.
+    WrongAnswer,
+
+    /// There is no expected output to compare with.
+    NoExpected,
+
+    /// Execution took longer than the time limit.
+    TimeLimitExceeded,
+
+    /// Solution exited with non-zero status (or was killed by a signal).
+    RuntimeError(Option<i32>),
+
+    /// Miri detected undefined behavior.
+    UndefinedBehavior,
+}
+
+impl Verdict {
+    /// Whether the verdict should be considered as a failure.
+    pub fn is_failure(&self) -> bool {
+        !matches!(self, Verdict::Passed | Verdict::NoExpected)
+    }
+
+    /// Abbreviated verdict, e.g. `WA` for a wrong answer.
+    pub fn short(&self) -> &'static str {
+        match self {
+            Verdict::Passed => "OK",
+            Verdict::WrongAnswer => "WA",
+            Verdict::NoExpected => "NEW",
+            Verdict::TimeLimitExceeded => "TLE",
+            Verdict::RuntimeError(_) => "RE",
+            Verdict::UndefinedBehavior => "UB",
+        }
+    }
+}
+
+impl std::fmt::Display for Verdict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Verdict::Passed => write!(f, "OK"),
+            Verdict::WrongAnswer => write!(f, "WRONG ANSWER"),
+            Verdict::NoExpected => write!(f, "NO EXPECTED OUTPUT"),
+            Verdict::TimeLimitExceeded => write!(f, "TIME LIMIT EXCEEDED"),
+            Verdict::RuntimeError(Some(code)) => write!(f, "RUNTIME ERROR (exit code {code})"),
+            Verdict::RuntimeError(None) => write!(f, "RUNTIME ERROR (killed)"),
+            Verdict::UndefinedBehavior => write!(f, "UNDEFINED BEHAVIOR"),
+        }
+    }
+}
+
+/// Result of running a single test case.
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    /// The test case that was run.
+    pub case: TestCase,
+
+    /// Outcome of the run.
+    pub verdict: Verdict,
+
+    /// Wall-clock time of the run.
+    pub elapsed: Duration,
+
+    /// Captured standard output.
+    pub output: String,
+
+    /// Captured standard error.
+    pub stderr: String,
+}
+
+/// Builds a problem's binary and runs it against a set of test cases.
+///
+/// This is the single place, where test execution semantics are defined
+/// (build profile, time limits, output comparison), so that all the
+/// subcommands running solutions behave the same way.
+#[derive(Debug, Clone)]
+pub struct TestRunner {
+    problem_id: String,
+    cases: Vec<TestCase>,
+    time_limit: Duration,
+    build: BuildOptions,
+    comparator: Arc<dyn Comparator>,
+    miri: bool,
+}
+
+impl TestRunner {
+    pub fn new(problem_id: &str) -> Self {
+        Self {
+            problem_id: problem_id.to_string(),
+            cases: Vec::new(),
+            time_limit: DEFAULT_TIME_LIMIT,
+            build: BuildOptions::default(),
+            comparator: Arc::new(Lines),
+            miri: false,
+        }
+    }
+
+    /// Set test cases to run.
+    pub fn cases(mut self, cases: Vec<TestCase>) -> Self {
+        self.cases = cases;
+        self
+    }
+
+    /// Set time limit per test case.
+    pub fn time_limit(mut self, time_limit: Duration) -> Self {
+        self.time_limit = time_limit;
+        self
+    }
+
+    /// Set build profile of the solution.
+    pub fn profile(mut self, profile: Profile) -> Self {
+        self.build.profile = profile;
+        self
+    }
+
+    /// Show all compiler diagnostics (including warnings) of a failed build.
+    pub fn full_diagnostics(mut self, full: bool) -> Self {
+        self.build.full_diagnostics = full;
+        self
+    }
+
+    /// Produce a report of where the build time is spent.
+    pub fn timings(mut self, timings: bool) -> Self {
+        self.build.timings = timings;
+        self
+    }
+
+    /// Set limits of the build and of the runs: cases are run in parallel, if
+    /// the number of jobs is given.
+    pub fn jobs(mut self, jobs: Jobs) -> Self {
+        self.build.jobs = jobs;
+        self
+    }
+
+    /// Set comparator used to check outputs.
+    pub fn comparator(mut self, comparator: Arc<dyn Comparator>) -> Self {
+        self.comparator = comparator;
+        self
+    }
+
+    /// Build the solution, and run it against all the test cases.
+    ///
+    /// The callback is invoked as soon as each case completes, so that callers
+    /// can report progress. Results are in the order of the cases.
+    pub fn run(&self, mut on_result: impl FnMut(&CaseResult)) -> Result<Vec<CaseResult>> {
+        // Compile errors are reported by the regular build, even under Miri.
+        let exe = self.build()?;
+        if self.miri {
+            check_miri()?;
+        }
+
+        let started = Instant::now();
+        let results = self
+            .build
+            .jobs
+            .run(
+                &self.cases,
+                |case| self.run_case(&exe, case),
+                |result| {
+                    if let Ok(result) = result {
+                        on_result(result);
+                    }
+                },
+            )
+            .into_iter()
+            .collect();
+        record_phase("run", started);
+        results
+    }
+
+    /// Set target to build the solution for, and to run it on.
+    pub fn target(mut self, target: Option<Target>) -> Self {
+        self.build.target = target;
+        self
+    }
+
+    /// Run the solution under Miri (with the nightly toolchain), detecting
+    /// undefined behavior. The time limit is extended accordingly.
+    pub fn miri(mut self, miri: bool) -> Self {
+        self.miri = miri;
+        self
+    }
+
+    /// Instrument the solution, writing coverage profiles of the runs (one per
+    /// case) to the directory.
+    pub fn coverage(mut self, dir: PathBuf) -> Self {
+        self.build.coverage = Some(dir);
+        self
+    }
+
+    /// Build the binary, returning path to the executable.
+    fn build(&self) -> Result<PathBuf> {
+        build(&self.problem_id, &self.build)
+    }
+
+    /// Run the executable against a single test case.
+    fn run_case(&self, exe: &Path, case: &TestCase) -> Result<CaseResult> {
+        let input = File::open(&case.input).context(format!(
+            "failed to open input file {}",
+            case.input.display()
+        ))?;
+
+        let (mut cmd, time_limit) = if self.miri {
+            let mut cmd = cargo::nightly_command(&["miri", "run"], self.build.jobs);
+            cmd.args(["--quiet", "--bin", &self.problem_id]);
+            cmd.args(self.build.profile.cargo_args());
+            if let Some(target) = &self.build.target {
+                cmd.args(target.cargo_args());
+            }
+            (cmd, self.time_limit * MIRI_SLOWDOWN)
+        } else {
+            let cmd = Target::command(self.build.target.as_ref(), self.build.jobs, exe);
+            (cmd, self.time_limit)
+        };
+        if let Some(dir) = &self.build.coverage {
+            cmd.env("LLVM_PROFILE_FILE", dir.join("%p.profraw"));
+        }
+
+        let start = Instant::now();
+        let mut child = cmd
+            .stdin(Stdio::from(input))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .context("failed to run solution")?;
+
+        // Drain pipes in background, so that the child never blocks on full buffers.
+        let drain = |pipe: Option<Box<dyn Read + Send>>| {
+            thread::spawn(move || {
+                let mut buf = Vec::new();
+                if let Some(mut pipe) = pipe {
+                    let _ = pipe.read_to_end(&mut buf);
+                }
+                String::from_utf8_lossy(&buf).into_owned()
+            })
+        };
+        let stdout = drain(child.stdout.take().map(|p| Box::new(p) as _));
+        let stderr = drain(child.stderr.take().map(|p| Box::new(p) as _));
+
+        let status = loop {
+            if let Some(status) = child.try_wait()? {
+                break Some(status);
+            }
+            if start.elapsed() > time_limit {
+                child.kill()?;
+                child.wait()?;
+                break None;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        let elapsed = start.elapsed();
+        let output = stdout.join().unwrap_or_default();
+        let stderr = stderr.join().unwrap_or_default();
+
+        let verdict = match status {
+            None => Verdict::TimeLimitExceeded,
+            Some(status)
+                if self.miri && !status.success() && stderr.contains("Undefined Behavior") =>
+            {
+                Verdict::UndefinedBehavior
+            }
+            Some(status) if !status.success() => Verdict::RuntimeError(status.code()),
+            Some(_) => match &case.expected {
+                None => Verdict::NoExpected,
+                Some(expected) => {
+                    let expected = fs::read_to_string(expected).context(format!(
+                        "failed to read expected output {}",
+                        expected.display()
+                    ))?;
+                    if self.comparator.compare(case, &expected, &output)? {
+                        Verdict::Passed
+                    } else {
+                        Verdict::WrongAnswer
+                    }
+                }
+            },
+        };
+
+        Ok(CaseResult {
+            case: case.clone(),
+            verdict,
+            elapsed,
+            output,
+            stderr,
+        })
+    }
+}
+
+/// Build profile of a solution.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Profile {
+    /// Unoptimized, with overflow checks and debug assertions (`dev` profile).
+    #[default]
+    Debug,
+
+    /// Optimized, without any checks (as solutions are built by judges).
+    Release,
+
+    /// Optimized, with overflow checks and debug assertions.
+    Checked,
+}
+
+impl Profile {
+    /// Parse a comma-separated list of profiles, e.g. `debug,release`.
+    pub fn parse_list(spec: &str) -> Result<Vec<Profile>> {
+        let mut profiles = Vec::new();
+        for name in spec.split(',').map(str::trim) {
+            let profile = match name {
+                "debug" => Profile::Debug,
+                "release" => Profile::Release,
+                "checked" => Profile::Checked,
+                _ => {
+                    return Err(anyhow!(
+                        "unknown profile {name:?}, expected `debug`, `release` or `checked`"
+                    ));
+                }
+            };
+            if !profiles.contains(&profile) {
+                profiles.push(profile);
+            }
+        }
+        Ok(profiles)
+    }
+
+    /// Arguments, selecting the profile for `cargo build`.
+    fn cargo_args(self) -> &'static [&'static str] {
+        match self {
+            Profile::Debug => &[],
+            Profile::Release => &["--release"],
+            // Defined on the fly, so that projects need no changes, and built into a
+            // directory of its own, so that other profiles are not rebuilt.
+            Profile::Checked => &[
+                "--profile=checked",
+                "--config=profile.checked.inherits=\"release\"",
+                "--config=profile.checked.overflow-checks=true",
+                "--config=profile.checked.debug-assertions=true",
+            ],
+        }
+    }
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Profile::Debug => write!(f, "debug"),
+            Profile::Release => write!(f, "release"),
+            Profile::Checked => write!(f, "checked"),
+        }
+    }
+}
+
+/// Ensure that Miri is installed for the nightly toolchain.
+fn check_miri() -> Result<()> {
+    let installed = Command::new("cargo")
+        .args(["+nightly", "miri", "--version"])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success());
+    if !installed {
+        return Err(anyhow!(
+            "Miri is not installed, install it with `rustup +nightly component add miri`"
+        ));
+    }
+    Ok(())
+}
+
+/// How a problem's binary is built.
+#[derive(Debug, Clone, Default)]
+pub struct BuildOptions {
+    pub profile: Profile,
+
+    /// Show all compiler diagnostics (including warnings) of a failed build.
+    pub full_diagnostics: bool,
+
+    /// Produce `cargo build --timings` report (cargo prints its location).
+    pub timings: bool,
+
+    /// Limits of the build, and of the runs of the binary.
+    pub jobs: Jobs,
+
+    /// Target to build for, the host one if not set.
+    pub target: Option<Target>,
+
+    /// Directory to write coverage profiles of the runs to, if the binary is
+    /// to be instrumented.
+    pub coverage: Option<PathBuf>,
+}
+
+/// Build a problem's binary, returning path to the executable.
+///
+/// Compiler warnings are held back until the build is complete: if it fails,
+/// only errors are shown (warnings, e.g. from a large library, would bury
+/// them), unless full diagnostics are requested.
+pub fn build(problem_id: &str, opts: &BuildOptions) -> Result<PathBuf> {
+    let started = Instant::now();
+    // Rendered diagnostics are colored, unless the output is redirected.
+    let format = if io::stderr().is_terminal() {
+        "--message-format=json-diagnostic-rendered-ansi"
+    } else {
+        "--message-format=json"
+    };
+    let mut cmd = cargo::command("build", opts.jobs);
+    cmd.arg("--bin").arg(problem_id).arg(format);
+    cmd.args(opts.profile.cargo_args());
+    if let Some(target) = &opts.target {
+        cmd.args(target.cargo_args());
+    }
+    if opts.timings {
+        cmd.arg("--timings");
+    }
+    if opts.coverage.is_some() {
+        // Instrumented artifacts are kept apart, not to invalidate the regular ones.
+        let rustflags = env::var("RUSTFLAGS").unwrap_or_default();
+        cmd.env("RUSTFLAGS", format!("{rustflags} -C instrument-coverage"))
+            .arg("--target-dir")
+            .arg(Path::new("target").join("coverage"));
+    }
+    let mut child = cmd
+        .stdout(Stdio::piped())
+        .spawn()
+        .context("failed to run cargo build")?;
+
+    // Locate the executable among the produced artifacts.
+    let mut exe = None;
+    let mut warnings = Vec::new();
+    let stdout = child
+        .stdout
+        .take()
+        .context("failed to capture cargo output")?;
+    for line in BufReader::new(stdout).lines() {
+        let message: serde_json::Value = match serde_json::from_str(&line?) {
+            Ok(message) => message,
+            Err(_) => continue,
+        };
+        if message["reason"] == "compiler-message"
+            && let Some(rendered) = message["message"]["rendered"].as_str()
+        {
+            if message["message"]["level"] == "warning" && !opts.full_diagnostics {
+                warnings.push(rendered.to_string());
+            } else {
+                eprint!("{rendered}");
+            }
+        }
+        if message["reason"] == "compiler-artifact"
+            && message["target"]["name"] == problem_id
+            && let Some(path) = message["executable"].as_str()
+        {
+            exe = Some(PathBuf::from(path));
+        }
+    }
+
+    let status = child.wait().context("failed to wait for cargo build")?;
+    record_phase("build", started);
+    if !status.success() {
+        record_failure(Failure::Compile);
+        if !warnings.is_empty() {
+            eprintln!(
+                "({} warning(s) hidden, use `--full` to show them)",
+                warnings.len()
+            );
+        }
+        return Err(anyhow!("failed to build problem {problem_id:?}"));
+    }
+    for rendered in warnings {
+        eprint!("{rendered}");
+    }
+    exe.context("executable not found among build artifacts")
+}
@@ -0,0 +1,165 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        api::PublicApi,
+        cargo,
+        jobs::Jobs,
+        manifest::{Dependency, Manifest},
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+    },
+};
+
+/// Upgrade the `algorist` dependency, and summarize API changes.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "upgrade-lib")]
+pub struct UpgradeLibSubCmd {
+    #[argh(option)]
+    /// version requirement to upgrade to (defaults to the latest version,
+    /// compatible with the current one)
+    version: Option<String>,
+}
+
+impl SubCmd for UpgradeLibSubCmd {
+    fn run(&self) -> Result<()> {
+        let root_dir = PathBuf::from("./")
+            .canonicalize()
+            .context("failed to canonicalize root directory path")?;
+
+        // Only projects, depending on published `algorist` crate, can be upgraded.
+        let cargo_toml = root_dir.join("Cargo.toml");
+        let manifest = fs::read_to_string(&cargo_toml).context("failed to read Cargo.toml")?;
+        let requirement = manifest
+            .parse::<Manifest>()
+            .context("failed to parse Cargo.toml")?
+            .dependency_version("algorist")
+            .map(|req| req.to_string())
+            .context("project doesn't depend on a published `algorist` crate")?;
+        let (old_dir, old_version) = vendored_algorist(&root_dir.join("crates"))?
+            .context("vendored `algorist` crate is not found in `crates`")?;
+        let old_api = PublicApi::collect(&old_dir).context("failed to collect current API")?;
+
+        // Exact (`=x.y.z`) requirement is kept exact, but bumped after vendoring.
+        let pinned = requirement.starts_with('=');
+        let new_requirement = match &self.version {
+            Some(version) => version.clone(),
+            None => requirement.trim_start_matches('=').to_string(),
+        };
+
+        // The previous state is set aside, to be restored if upgrade fails.
+        let lock_file = root_dir.join("Cargo.lock");
+        let lock = fs::read_to_string(&lock_file).ok();
+        let backup_dir = root_dir.join(".algorist/upgrade-backup");
+        fs::create_dir_all(&backup_dir).context("failed to create backup directory")?;
+        let backup = backup_dir.join(format!("algorist-{old_version}"));
+        if backup.exists() {
+            fs::remove_dir_all(&backup).context("failed to remove stale backup")?;
+        }
+        fs::rename(&old_dir, &backup).context("failed to back up vendored crate")?;
+
+        let restore = || -> Result<()> {
+            fs::write(&cargo_toml, &manifest)?;
+            if let Some(lock) = &lock {
+                fs::write(&lock_file, lock)?;
+            }
+            if old_dir.exists() {
+                fs::remove_dir_all(&old_dir)?;
+            }
+            fs::rename(&backup, &old_dir)?;
+            Ok(())
+        };
+
+        let upgraded = set_requirement(&cargo_toml, &new_requirement)
+            .and_then(|_| {
+                // Lock file pins the current version, so it is regenerated.
+                if lock.is_some() {
+                    fs::remove_file(&lock_file).context("failed to remove Cargo.lock")?;
+                }
+                cargo_vendor(&root_dir)
+            })
+            .and_then(|_| {
+                vendored_algorist(&root_dir.join("crates"))?
+                    .context("`algorist` crate is not vendored")
+            });
+        let (new_dir, new_version) = match upgraded {
+            Ok(vendored) => vendored,
+            Err(err) => {
+                restore().context("failed to restore the project after failed upgrade")?;
+                return Err(err.context("failed to upgrade `algorist`"));
+            }
+        };
+        fs::remove_dir_all(&backup).context("failed to remove backup")?;
+
+        if pinned {
+            set_requirement(&cargo_toml, &format!("={new_version}"))?;
+        }
+
+        if new_version == old_version {
+            println!("`algorist` is up to date ({old_version}).");
+            return Ok(());
+        }
+        println!("Upgraded `algorist`: {old_version} -> {new_version}");
+
+        let new_api = PublicApi::collect(&new_dir).context("failed to collect new API")?;
+        let diff = old_api.diff(&new_api);
+        if diff.is_empty() {
+            println!("No public API changes.");
+        } else {
+            print!("{diff}");
+            if diff.is_breaking() {
+                println!("Some items you may rely on were removed or changed, see above.");
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Directory and version of the vendored `algorist` crate.
+fn vendored_algorist(crates_dir: &Path) -> Result<Option<(PathBuf, String)>> {
+    for entry in fs::read_dir(crates_dir).context("failed to read crates directory")? {
+        let path = entry?.path();
+        let Ok(content) = fs::read_to_string(path.join("Cargo.toml")) else {
+            continue;
+        };
+        let Ok(value) = content.parse::<toml::Value>() else {
+            continue;
+        };
+        let package = value.get("package");
+        let name = package.and_then(|p| p.get("name")).and_then(|n| n.as_str());
+        let version = package
+            .and_then(|p| p.get("version"))
+            .and_then(|v| v.as_str());
+        if let (Some("algorist"), Some(version)) = (name, version) {
+            return Ok(Some((path, version.to_string())));
+        }
+    }
+    Ok(None)
+}
+
+/// Replace `algorist` version requirement in `Cargo.toml`.
+fn set_requirement(cargo_toml: &Path, requirement: &str) -> Result<()> {
+    let mut manifest = Manifest::read(cargo_toml)?;
+    manifest
+        .set_dependency("algorist", &Dependency::Version(requirement.to_string()))
+        .context(format!("failed to update {}", cargo_toml.display()))?;
+    manifest
+        .write(cargo_toml)
+        .context("failed to update Cargo.toml")
+}
+
+fn cargo_vendor(root_dir: &Path) -> Result<()> {
+    println!("Running `cargo vendor` to vendor dependencies...");
+    let status = cargo::command("vendor", Jobs::default())
+        .args(["crates", "--no-delete", "--quiet"])
+        .current_dir(root_dir)
+        .status()
+        .context("failed to run cargo vendor")?;
+    if !status.success() {
+        return Err(anyhow!("cargo vendor failed with status: {}", status));
+    }
+    Ok(())
+}
@@ -0,0 +1,114 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        meta::{Event, Filter, MetaStore},
+        problem::{BIN_DIR, problem_ids},
+    },
+    anyhow::{Context, Result},
+    argh::FromArgs,
+    std::{
+        fs,
+        path::{Path, PathBuf},
+        time::{SystemTime, UNIX_EPOCH},
+    },
+};
+
+/// List unsolved problems of the contest projects, to upsolve them.
+#[derive(FromArgs)]
+#[argh(subcommand, name = "upsolve")]
+pub struct UpsolveSubCmd {
+    #[argh(positional, default = "String::from(\".\")")]
+    /// contest project, or directory with contest projects in it (defaults to
+    /// the current one)
+    dir: String,
+
+    #[argh(switch)]
+    /// pick one of the unsolved problems at random
+    random: bool,
+
+    #[argh(option)]
+    /// only list problems with the given label: `tag=<tag>`, or
+    /// `difficulty=<min>..<max>` (can be repeated, all must match)
+    filter: Vec<String>,
+}
+
+impl SubCmd for UpsolveSubCmd {
+    fn run(&self) -> Result<()> {
+        let dir = Path::new(&self.dir);
+        let projects = contest_projects(dir)?;
+        if projects.is_empty() {
+            println!("No contest projects found in {}", dir.display());
+            return Ok(());
+        }
+
+        let filters = self
+            .filter
+            .iter()
+            .map(|spec| Filter::parse(spec))
+            .collect::<Result<Vec<_>>>()?;
+        let mut queue = Vec::new();
+        for project in &projects {
+            let store = MetaStore::open(project)?;
+            let ids = problem_ids(project)?;
+            let unsolved = ids
+                .iter()
+                .filter(|id| store.get(id, Event::FirstPass).is_none())
+                .filter(|id| filters.iter().all(|filter| filter.matches(&store, id)))
+                .cloned()
+                .collect::<Vec<_>>();
+            if !unsolved.is_empty() {
+                println!(
+                    "{} ({} of {} unsolved): {}",
+                    project.display(),
+                    unsolved.len(),
+                    ids.len(),
+                    unsolved.join(", ")
+                );
+            }
+            queue.extend(unsolved.into_iter().map(|id| (project, id)));
+        }
+        if queue.is_empty() {
+            println!(
+                "All problems of {} contest project(s) are solved",
+                projects.len()
+            );
+            return Ok(());
+        }
+
+        if self.random {
+            // Picks need not be reproducible, so the clock is random enough.
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.subsec_nanos());
+            let (project, id) = &queue[seed as usize % queue.len()];
+            println!(
+                "\nPicked problem {id:?} of {}, open it with `cd {} && algorist open {id}`",
+                project.display(),
+                project.display()
+            );
+        } else {
+            println!("\n{} problem(s) to upsolve", queue.len());
+        }
+        Ok(())
+    }
+}
+
+/// Contest projects: the directory itself, if it is one, or the ones within
+/// it.
+pub fn contest_projects(dir: &Path) -> Result<Vec<PathBuf>> {
+    if is_project(dir) {
+        return Ok(vec![dir.to_path_buf()]);
+    }
+    let mut projects = fs::read_dir(dir)
+        .context(format!("failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_project(path))
+        .collect::<Vec<_>>();
+    projects.sort();
+    Ok(projects)
+}
+
+/// Whether the directory is a contest project, i.e. has problem files.
+fn is_project(dir: &Path) -> bool {
+    dir.join("Cargo.toml").exists() && dir.join(BIN_DIR).is_dir()
+}
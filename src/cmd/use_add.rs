@@ -0,0 +1,273 @@
+use {
+    crate::cmd::{
+        SubCmd,
+        api::{PublicApi, library_crates},
+        problem::{resolve_id, solution_path},
+        scaffold::Regions,
+    },
+    anyhow::{Context, Result, anyhow},
+    argh::FromArgs,
+    prettyplease::unparse,
+    proc_macro2::LineColumn,
+    std::{fs, path::Path},
+    syn::{UseTree, spanned::Spanned},
+};
+
+/// Import a library module (or item) into a problem file (its companion
+/// module, if it has one).
+#[derive(FromArgs)]
+#[argh(subcommand, name = "use-add")]
+pub struct UseAddSubCmd {
+    #[argh(positional)]
+    /// problem ID
+    id: String,
+
+    #[argh(positional)]
+    /// path of the module or item within the library, e.g. `graph::dijkstra`
+    /// (the crate name may be omitted)
+    path: String,
+}
+
+impl SubCmd for UseAddSubCmd {
+    fn run(&self) -> Result<()> {
+        let id = resolve_id(Path::new("."), &self.id)?;
+        let file = solution_path(Path::new("."), &id);
+        let import = self.resolve(Path::new("."))?;
+        let import_str = unparse(&syn::File {
+            shebang: None,
+            attrs: Vec::new(),
+            items: vec![syn::Item::Use(import.clone())],
+        });
+
+        let content =
+            fs::read_to_string(&file).context(format!("failed to read {}", file.display()))?;
+        match add_import(&content, import).context(format!("failed to parse {}", file.display()))? {
+            Some(content) => {
+                fs::write(&file, content).context(format!("failed to write {}", file.display()))?;
+                println!("Added `{}` to {}", import_str.trim(), file.display());
+            }
+            None => println!("`{}` is already in {}", import_str.trim(), file.display()),
+        }
+        Ok(())
+    }
+}
+
+impl UseAddSubCmd {
+    /// Find the library crate with the given module (or item), and build the
+    /// `use` declaration for it: modules are glob-imported, items are imported
+    /// by name.
+    fn resolve(&self, root: &Path) -> Result<syn::ItemUse> {
+        let segments = self
+            .path
+            .trim_start_matches("::")
+            .split("::")
+            .map(|segment| segment.trim())
+            .collect::<Vec<_>>();
+        if segments.iter().any(|segment| segment.is_empty()) {
+            return Err(anyhow!("invalid path {:?}", self.path));
+        }
+
+        let crates = library_crates(root)?;
+        if crates.is_empty() {
+            return Err(anyhow!(
+                "the project depends on no library crates in `crates`"
+            ));
+        }
+        let mut found = Vec::new();
+        let mut similar = Vec::new();
+        for (name, dir) in &crates {
+            // The path may start with the crate name.
+            let path = match segments.split_first() {
+                Some((first, rest)) if first == name && !rest.is_empty() => rest.join("::"),
+                _ if crates.iter().any(|(name, _)| name == segments[0]) => continue,
+                _ => segments.join("::"),
+            };
+            let api = PublicApi::collect(dir)
+                .context(format!("failed to collect API of crate {name:?}"))?;
+            let last = segments.last().expect("path is not empty");
+            for item in api.paths() {
+                let item = item.trim_end_matches('!');
+                if item == path {
+                    found.push((name, path, false));
+                    break;
+                }
+                if item.starts_with(&format!("{path}::")) {
+                    found.push((name, path, true));
+                    break;
+                }
+                if item.rsplit("::").next() == Some(last) {
+                    similar.push(format!("{name}::{item}"));
+                }
+            }
+        }
+
+        match found.as_slice() {
+            [(name, path, is_module)] => {
+                let tree = format!("{name}::{path}{}", if *is_module { "::*" } else { "" });
+                syn::parse_str(&format!("use {tree};"))
+                    .context(format!("invalid path {:?}", self.path))
+            }
+            [] if similar.is_empty() => Err(anyhow!(
+                "{:?} is not found in the library crates",
+                self.path
+            )),
+            [] => Err(anyhow!(
+                "{:?} is not found in the library crates, did you mean: {}?",
+                self.path,
+                similar.join(", ")
+            )),
+            found => Err(anyhow!(
+                "{:?} is found in several crates, prefix it with the crate name: {}",
+                self.path,
+                found
+                    .iter()
+                    .map(|(name, path, _)| format!("{name}::{path}"))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        }
+    }
+}
+
+/// Add the import to the source file, merging it into the existing `use`
+/// declaration for the same crate, if any (the rest of the file is kept
+/// intact). Declarations within marked regions are the user's, and are never
+/// touched.
+///
+/// Returns the updated content, or `None` if the import is already there.
+pub fn add_import(content: &str, import: syn::ItemUse) -> Result<Option<String>> {
+    let file = syn::parse_file(content)?;
+    let regions = Regions::parse(content).unwrap_or_default();
+    let UseTree::Path(new) = &import.tree else {
+        unreachable!("import path should start with the crate name");
+    };
+
+    let mut uses = file.items.iter().filter_map(|item| match item {
+        syn::Item::Use(item)
+            if item.attrs.is_empty()
+                && item.leading_colon.is_none()
+                && !regions.contains(offset(content, item.span().start())) =>
+        {
+            Some(item)
+        }
+        _ => None,
+    });
+    let existing = uses
+        .clone()
+        .find(|item| matches!(&item.tree, UseTree::Path(path) if path.ident == new.ident));
+    let (start, end, text) = match existing {
+        Some(item) => {
+            let mut merged = item.clone();
+            if !merge_tree(&mut merged.tree, import.tree) {
+                return Ok(None);
+            }
+            let text = unparse(&syn::File {
+                shebang: None,
+                attrs: Vec::new(),
+                items: vec![syn::Item::Use(merged)],
+            });
+            let (start, end) = (item.span().start(), item.span().end());
+            (start, end, text.trim_end().to_string())
+        }
+        None => {
+            let text = unparse(&syn::File {
+                shebang: None,
+                attrs: Vec::new(),
+                items: vec![syn::Item::Use(import)],
+            });
+            // New imports go after the last one, or before the first item.
+            let first = file
+                .items
+                .iter()
+                .find(|item| !regions.contains(offset(content, item.span().start())));
+            match (uses.next_back(), first) {
+                (Some(item), _) => {
+                    let end = item.span().end();
+                    (end, end, format!("\n{}", text.trim_end()))
+                }
+                (None, Some(item)) => {
+                    let start = LineColumn {
+                        line: item.span().start().line,
+                        column: 0,
+                    };
+                    (start, start, format!("{text}\n"))
+                }
+                (None, None) => {
+                    return Ok(Some(format!("{content}{text}")));
+                }
+            }
+        }
+    };
+
+    let (start, end) = (offset(content, start), offset(content, end));
+    Ok(Some(format!(
+        "{}{text}{}",
+        &content[..start],
+        &content[end..]
+    )))
+}
+
+/// Merge the tree of the new import into the existing one.
+///
+/// Returns `false` if the existing tree already has the import.
+fn merge_tree(tree: &mut UseTree, new: UseTree) -> bool {
+    if *tree == new {
+        return false;
+    }
+    match (tree, new) {
+        (UseTree::Path(path), UseTree::Path(new)) if path.ident == new.ident => {
+            merge_tree(&mut path.tree, *new.tree)
+        }
+        // Groups, e.g. of imports carried over from another problem file, are merged
+        // item by item.
+        (tree, UseTree::Group(new)) => {
+            let mut merged = false;
+            for item in new.items {
+                merged |= merge_tree(tree, item);
+            }
+            merged
+        }
+        (UseTree::Group(group), new) => {
+            if group.items.iter().any(|item| *item == new) {
+                return false;
+            }
+            // Paths with the same first segment are merged further down.
+            let same_prefix = |item: &UseTree| match (item, &new) {
+                (UseTree::Path(path), UseTree::Path(new)) => path.ident == new.ident,
+                _ => false,
+            };
+            if let Some(item) = group.items.iter_mut().find(|item| same_prefix(item)) {
+                return merge_tree(item, new);
+            }
+            group.items.push(new);
+            true
+        }
+        (tree, new) => {
+            let old = std::mem::replace(
+                tree,
+                UseTree::Glob(syn::UseGlob {
+                    star_token: Default::default(),
+                }),
+            );
+            *tree = UseTree::Group(syn::UseGroup {
+                brace_token: Default::default(),
+                items: [old, new].into_iter().collect(),
+            });
+            true
+        }
+    }
+}
+
+/// Byte offset of the given location in the content.
+pub fn offset(content: &str, location: LineColumn) -> usize {
+    let line_start = content
+        .split_inclusive('\n')
+        .take(location.line - 1)
+        .map(str::len)
+        .sum::<usize>();
+    let column = content[line_start..]
+        .char_indices()
+        .nth(location.column)
+        .map_or(content.len() - line_start, |(i, _)| i);
+    line_start + column
+}
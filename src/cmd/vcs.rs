@@ -0,0 +1,82 @@
+use {
+    crate::cmd::config::setting,
+    std::{path::Path, process::Command, sync::OnceLock},
+};
+
+/// Version control system, the tool queries (e.g. for bundle headers).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Vcs {
+    Git,
+
+    /// Jujutsu (`jj`), including repositories colocated with git.
+    Jujutsu,
+}
+
+impl Vcs {
+    /// VCS of the repository, the directory belongs to: the configured one
+    /// (`vcs` setting), or the detected one.
+    ///
+    /// In colocated repositories (with both `.jj` and `.git`), Jujutsu is
+    /// preferred, if `jj` is installed.
+    pub fn detect(dir: &Path) -> Option<Self> {
+        match setting("vcs").ok().flatten().as_deref() {
+            Some("git") => return Some(Vcs::Git),
+            Some("jj") => return Some(Vcs::Jujutsu),
+            _ => {}
+        }
+        let dir = dir.canonicalize().ok()?;
+        dir.ancestors().find_map(|dir| {
+            if dir.join(".jj").is_dir() && jj_installed() {
+                Some(Vcs::Jujutsu)
+            } else if dir.join(".git").exists() {
+                Some(Vcs::Git)
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Name of the user, as configured for the VCS.
+    pub fn user_name(self, dir: &Path) -> Option<String> {
+        match self {
+            Vcs::Git => run(dir, "git", &["config", "user.name"]),
+            Vcs::Jujutsu => run(dir, "jj", &["config", "get", "user.name"]),
+        }
+    }
+
+    /// Short ID of the last commit, touching the directory.
+    ///
+    /// For Jujutsu, the working-copy commit counts, as of its last snapshot
+    /// (the working copy is not snapshotted by the query).
+    pub fn last_commit(self, dir: &Path) -> Option<String> {
+        match self {
+            Vcs::Git => run(dir, "git", &["log", "-1", "--format=%h", "--", "."]),
+            Vcs::Jujutsu => run(dir, "jj", &[
+                "log",
+                "--no-graph",
+                "--ignore-working-copy",
+                "--revisions",
+                "latest(::@ & files(\".\"))",
+                "--template",
+                "commit_id.short(7)",
+            ]),
+        }
+    }
+}
+
+fn jj_installed() -> bool {
+    static INSTALLED: OnceLock<bool> = OnceLock::new();
+    *INSTALLED.get_or_init(|| Command::new("jj").arg("--version").output().is_ok())
+}
+
+/// Returns the output of a successful invocation of the tool within the
+/// directory, if any.
+fn run(dir: &Path, tool: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(tool)
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .ok()?;
+    let out = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (output.status.success() && !out.is_empty()).then_some(out)
+}
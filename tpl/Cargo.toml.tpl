@@ -4,5 +4,16 @@ version = "1.0.0"
 edition = "2021"
 rust-version = "1.75.0"
 
+[features]
+default = ["local"]
+# Local-only tooling: put dev-only dependencies (e.g. `proptest`, visualization crates) behind this
+# feature, and gate their usages with `#[cfg(feature = "local")]`. Gated code is stripped by
+# `cargo algorist bundle`, so it never reaches the judge.
+local = []
+
+# Solutions are rebuilt incrementally in release mode as well, so that editing a problem file
+# recompiles only what has changed (library crates are never rebuilt, unless they are modified).
+[profile.release]
+incremental = true
+
 [dependencies]
-{{EXTERNAL_CRATE}}
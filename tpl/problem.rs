@@ -2,7 +2,9 @@ use algorist::io::{test_cases, wln};
 
 fn main() {
     test_cases(&mut |scan, w| {
+        // algorist:begin solve
         let (a, b) = scan.u2();
         wln!(w, "{}", a + b);
+        // algorist:end solve
     });
 }
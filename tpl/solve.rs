@@ -0,0 +1,27 @@
+use algorist::io::{Scanner, wln};
+use std::io::{BufRead, Write};
+
+pub fn solve(scan: &mut Scanner<impl BufRead>, w: &mut impl Write) {
+    // algorist:begin solve
+    let (a, b) = scan.u2();
+    wln!(w, "{}", a + b);
+    // algorist:end solve
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &str) -> String {
+        let mut output = Vec::new();
+        solve(&mut Scanner::new(input.as_bytes()), &mut output);
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn samples() {
+        // algorist:begin tests
+        assert_eq!(run("1 2"), "3\n");
+        // algorist:end tests
+    }
+}
@@ -0,0 +1,8 @@
+#[path = "{{ID}}/solve.rs"]
+mod solve;
+
+use algorist::io::test_cases;
+
+fn main() {
+    test_cases(&mut |scan, w| solve::solve(scan, w));
+}